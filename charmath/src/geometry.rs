@@ -0,0 +1,243 @@
+use crate::linear::vector::{Vec2, Vec2f32, Vec3, Vec3f32, Vector, VectorBase};
+
+#[cfg(target_family = "wasm")]
+use wasm_bindgen::prelude::*;
+
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy)]
+pub struct Sphere {
+    pub center: Vec3f32,
+    pub radius: f32,
+}
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+impl Sphere {
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(constructor))]
+    pub fn new(center: Vec3f32, radius: f32) -> Sphere {
+        Sphere { center, radius }
+    }
+    /// True if the point lies inside or on the sphere.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = contains))]
+    pub fn contains(&self, point: &Vec3f32) -> bool {
+        point.sub_vec(&self.center).len() <= self.radius
+    }
+    /// True if the spheres overlap or touch.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = intersects))]
+    pub fn intersects(&self, other: &Sphere) -> bool {
+        let d = other.center.sub_vec(&self.center);
+        let r = self.radius + other.radius;
+        d.dot(&d) <= r * r
+    }
+}
+impl Sphere {
+    /// Approximate bounding sphere of a point cloud using Ritter's algorithm. The result
+    /// is at most a few percent larger than the minimal sphere and contains every point.
+    /// An empty slice gives a zero sphere at the origin.
+    pub fn from_points(points: &[Vec3f32]) -> Sphere {
+        if points.is_empty() {
+            return Sphere::new(Vec3f32::new(0.0, 0.0, 0.0), 0.0);
+        }
+        let farthest_from = |from: &Vec3f32| {
+            *points
+                .iter()
+                .max_by(|a, b| {
+                    let da = a.sub_vec(from).len();
+                    let db = b.sub_vec(from).len();
+                    da.partial_cmp(&db).unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .unwrap()
+        };
+        let y = farthest_from(&points[0]);
+        let z = farthest_from(&y);
+        let mut center = y.add_vec(&z).mul_num(0.5);
+        let mut radius = z.sub_vec(&y).len() * 0.5;
+        for p in points.iter() {
+            let d = p.sub_vec(&center).len();
+            if d > radius {
+                let new_radius = (radius + d) * 0.5;
+                center = center.add_vec(&p.sub_vec(&center).mul_num((new_radius - radius) / d));
+                radius = new_radius;
+            }
+        }
+        // Growing the sphere can leave earlier points a rounding error outside of it
+        for p in points.iter() {
+            radius = radius.max(p.sub_vec(&center).len());
+        }
+        Sphere::new(center, radius)
+    }
+}
+
+/// Axis-aligned bounding box spanning `min` to `max` on every axis.
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Vec3f32,
+    pub max: Vec3f32,
+}
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+impl Aabb {
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(constructor))]
+    pub fn new(min: Vec3f32, max: Vec3f32) -> Aabb {
+        Aabb { min, max }
+    }
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = center))]
+    pub fn center(&self) -> Vec3f32 {
+        self.min.add_vec(&self.max).mul_num(0.5)
+    }
+    /// True if the point lies inside or on the box.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = contains))]
+    pub fn contains(&self, point: &Vec3f32) -> bool {
+        (0..3).all(|i| point[i] >= self.min[i] && point[i] <= self.max[i])
+    }
+    /// An inverted box with `min` at +infinity and `max` at -infinity. It contains
+    /// nothing and becomes a real box on the first `grow_to_include`, so it is the
+    /// starting point for accumulating bounds.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = newInvalid))]
+    pub fn new_invalid() -> Aabb {
+        Aabb::new(
+            Vec3f32::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            Vec3f32::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        )
+    }
+    /// False for boxes with `min` above `max` on any axis, such as `new_invalid`.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = isValid))]
+    pub fn is_valid(&self) -> bool {
+        (0..3).all(|i| self.min[i] <= self.max[i])
+    }
+    /// Smallest box containing both boxes. An invalid box leaves the other unchanged.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = union))]
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        let mut ret = *self;
+        for i in 0..3 {
+            ret.min[i] = ret.min[i].min(other.min[i]);
+            ret.max[i] = ret.max[i].max(other.max[i]);
+        }
+        ret
+    }
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = growToInclude))]
+    pub fn grow_to_include(&mut self, point: &Vec3f32) {
+        for i in 0..3 {
+            self.min[i] = self.min[i].min(point[i]);
+            self.max[i] = self.max[i].max(point[i]);
+        }
+    }
+}
+impl Aabb {
+    /// Smallest box containing every point. An empty slice gives a zero box at the origin.
+    pub fn from_points(points: &[Vec3f32]) -> Aabb {
+        if points.is_empty() {
+            return Aabb::new(Vec3f32::new(0.0, 0.0, 0.0), Vec3f32::new(0.0, 0.0, 0.0));
+        }
+        let mut min = points[0];
+        let mut max = points[0];
+        for p in points.iter() {
+            for i in 0..3 {
+                min[i] = min[i].min(p[i]);
+                max[i] = max[i].max(p[i]);
+            }
+        }
+        Aabb::new(min, max)
+    }
+    /// The eight corners. Bit 0, 1 and 2 of the index pick `max` over `min` for x, y
+    /// and z, so corners `i` and `i ^ (1 << axis)` share an edge.
+    pub fn corners(&self) -> [Vec3f32; 8] {
+        let mut ret = [self.min; 8];
+        for (i, corner) in ret.iter_mut().enumerate() {
+            for axis in 0..3 {
+                if i & (1 << axis) != 0 {
+                    corner[axis] = self.max[axis];
+                }
+            }
+        }
+        ret
+    }
+}
+
+/// Axis-aligned 2D rectangle spanning `min` to `max`.
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy)]
+pub struct Rect {
+    pub min: Vec2f32,
+    pub max: Vec2f32,
+}
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+impl Rect {
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(constructor))]
+    pub fn new(min: Vec2f32, max: Vec2f32) -> Rect {
+        Rect { min, max }
+    }
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = width))]
+    pub fn width(&self) -> f32 {
+        self.max.get_x() - self.min.get_x()
+    }
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = height))]
+    pub fn height(&self) -> f32 {
+        self.max.get_y() - self.min.get_y()
+    }
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = center))]
+    pub fn center(&self) -> Vec2f32 {
+        self.min.add_vec(&self.max).mul_num(0.5)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube_corners() -> Vec<Vec3f32> {
+        let mut points = Vec::new();
+        for i in 0..8 {
+            let bit = |b: i32| if i & (1 << b) != 0 { 1.0 } else { -1.0 };
+            points.push(Vec3f32::new(bit(0), bit(1), bit(2)));
+        }
+        points
+    }
+
+    #[test]
+    fn ritter_sphere_contains_every_point_and_is_near_minimal() {
+        let mut points = cube_corners();
+        points.push(Vec3f32::new(0.2, -0.3, 0.1));
+        let sphere = Sphere::from_points(&points);
+        assert!(points.iter().all(|p| sphere.contains(p)));
+        let minimal = 3f32.sqrt();
+        assert!(sphere.radius >= minimal - 1e-5 && sphere.radius <= minimal * 1.05);
+        let empty = Sphere::from_points(&[]);
+        assert_eq!(
+            (empty.center.as_tuple(), empty.radius),
+            ((0.0, 0.0, 0.0), 0.0)
+        );
+    }
+
+    #[test]
+    fn spheres_intersect_when_touching() {
+        let a = Sphere::new(Vec3f32::new(0.0, 0.0, 0.0), 1.0);
+        assert!(a.intersects(&Sphere::new(Vec3f32::new(3.0, 0.0, 0.0), 2.0)));
+        assert!(!a.intersects(&Sphere::new(Vec3f32::new(3.0, 0.0, 0.0), 1.9)));
+    }
+
+    #[test]
+    fn aabb_accumulates_from_new_invalid() {
+        let mut aabb = Aabb::new_invalid();
+        assert!(!aabb.is_valid());
+        assert!(!aabb.contains(&Vec3f32::new(0.0, 0.0, 0.0)));
+        for p in cube_corners().iter() {
+            aabb.grow_to_include(p);
+        }
+        assert!(aabb.is_valid());
+        assert_eq!(aabb.min.as_tuple(), (-1.0, -1.0, -1.0));
+        assert_eq!(aabb.max.as_tuple(), (1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn aabb_union_covers_both_boxes() {
+        let a = Aabb::new(Vec3f32::new(0.0, 0.0, 0.0), Vec3f32::new(1.0, 2.0, 1.0));
+        let b = Aabb::new(Vec3f32::new(-1.0, 1.0, 0.5), Vec3f32::new(0.5, 3.0, 0.5));
+        let union = a.union(&b);
+        assert_eq!(union.min.as_tuple(), (-1.0, 0.0, 0.0));
+        assert_eq!(union.max.as_tuple(), (1.0, 3.0, 1.0));
+        let unchanged = a.union(&Aabb::new_invalid());
+        assert_eq!(
+            (unchanged.min.as_tuple(), unchanged.max.as_tuple()),
+            (a.min.as_tuple(), a.max.as_tuple())
+        );
+    }
+}
@@ -1,5 +1,7 @@
+pub mod geometry;
 pub mod linear;
 pub mod numeric;
+pub mod random;
 
 #[cfg(target_family = "wasm")]
 use wasm_bindgen::prelude::*;
@@ -25,6 +27,12 @@ pub trait CharMathCopy<T> {
     fn cm_copy(&self) -> T;
 }
 
+/// Interpolation between two values of the same type, so animation code can blend any
+/// of them without knowing the type. `t = 0` gives self and `t = 1` gives other.
+pub trait Lerp {
+    fn interpolate(&self, other: &Self, t: f32) -> Self;
+}
+
 #[cfg(not(target_family = "wasm"))]
 pub fn link_test() {
     println!("CharMath link verified");
@@ -19,6 +19,20 @@ use crate::linear::vector::{
     Vec4i32, Vec4i64, Vector,
 };
 
+/// Order of the axis rotations in an euler rotation matrix, written as the matrix
+/// product from left to right. `ZYX` builds `Rz * Ry * Rx`.
+#[repr(i32)]
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EulerOrder {
+    XYZ = 0x1,
+    XZY = 0x2,
+    YXZ = 0x4,
+    YZX = 0x8,
+    ZXY = 0x10,
+    ZYX = 0x20,
+}
+
 impl<N: CharMathNumeric<N>> CharMathCopy<Vec<Vec<N>>> for Vec<Vec<N>> {
     fn cm_copy(&self) -> Self {
         let mut ret = Vec::<Vec<N>>::with_capacity(self.len());
@@ -148,6 +162,24 @@ pub trait Matrix<N: CharMathNumeric<N>, MAT: Matrix<N, MAT>>:
     fn sub_num(&self, n: N) -> MAT {
         self.num_operand(n, |l, r| l - r)
     }
+    fn set_row(&mut self, index: usize, row: &[N]) {
+        assert!(
+            row.len() == self.get_width(),
+            "Row length must match matrix width."
+        );
+        for (i, value) in row.iter().enumerate() {
+            *self.get_value_ref_mut(index, i) = *value;
+        }
+    }
+    fn set_col(&mut self, index: usize, col: &[N]) {
+        assert!(
+            col.len() == self.get_height(),
+            "Column length must match matrix height."
+        );
+        for (i, value) in col.iter().enumerate() {
+            *self.get_value_ref_mut(i, index) = *value;
+        }
+    }
 }
 pub trait SquareMatrix<N: CharMathNumeric<N>, MAT: SquareMatrix<N, MAT>>: Matrix<N, MAT> {
     fn adjoint(&self) -> MAT {
@@ -170,6 +202,23 @@ pub trait SquareMatrix<N: CharMathNumeric<N>, MAT: SquareMatrix<N, MAT>>: Matrix
     fn inverse(&self) -> MAT {
         self.adjoint().div_num(self.determinant())
     }
+    /// Multiplies the matrix by itself `n` times using exponentiation by squaring.
+    /// `pow(0)` is the identity.
+    fn pow(&self, n: u32) -> MAT {
+        let mut ret = MAT::from_matrix(&matrices::identity::<N>(self.get_size()));
+        let mut base = self.cm_copy();
+        let mut n = n;
+        while n > 0 {
+            if n & 1 == 1 {
+                ret = ret.mul_mat(&base);
+            }
+            n >>= 1;
+            if n > 0 {
+                base = base.mul_mat(&base);
+            }
+        }
+        ret
+    }
     fn determinant(&self) -> N {
         self.determinant_recursive(self.get_size() as u32)
     }
@@ -218,6 +267,59 @@ pub trait SquareMatrix<N: CharMathNumeric<N>, MAT: SquareMatrix<N, MAT>>: Matrix
         assert!(self.is_square(), "Matrix must be square.");
         self.get_height()
     }
+    /// Solves `self * x = b` using LU decomposition with partial pivoting.
+    /// Returns None if the matrix is singular, or so close to it that a pivot is no
+    /// larger than `n * epsilon` times the largest entry.
+    fn solve(&self, b: &[N]) -> Option<Vec<N>> {
+        let n = self.get_size();
+        assert!(b.len() == n, "Right hand side length must match matrix size.");
+        let abs = |a: N| if a < N::zero() { N::neg(a) } else { a };
+        let mut lu: Vec<Vec<N>> = (0..n).map(|i| self.get_row_vec(i)).collect();
+        let mut perm: Vec<usize> = (0..n).collect();
+        let largest = lu
+            .iter()
+            .flatten()
+            .fold(N::zero(), |max, &v| if abs(v) > max { abs(v) } else { max });
+        let size = (0..n).fold(N::zero(), |size, _| size + N::one());
+        let tolerance = size * N::epsilon() * largest;
+        for k in 0..n {
+            let mut pivot = k;
+            for i in (k + 1)..n {
+                if abs(lu[i][k]) > abs(lu[pivot][k]) {
+                    pivot = i;
+                }
+            }
+            if abs(lu[pivot][k]) <= tolerance {
+                return None;
+            }
+            lu.swap(k, pivot);
+            perm.swap(k, pivot);
+            let (upper, lower) = lu.split_at_mut(k + 1);
+            let pivot_row = &upper[k];
+            for row in lower.iter_mut() {
+                let factor = row[k] / pivot_row[k];
+                row[k] = factor;
+                for (value, pivot_value) in row[(k + 1)..].iter_mut().zip(&pivot_row[(k + 1)..]) {
+                    *value -= factor * *pivot_value;
+                }
+            }
+        }
+        let mut x: Vec<N> = perm.iter().map(|&p| b[p]).collect();
+        for i in 0..n {
+            for j in 0..i {
+                let sub = lu[i][j] * x[j];
+                x[i] -= sub;
+            }
+        }
+        for i in (0..n).rev() {
+            for j in (i + 1)..n {
+                let sub = lu[i][j] * x[j];
+                x[i] -= sub;
+            }
+            x[i] /= lu[i][i];
+        }
+        Some(x)
+    }
 }
 
 #[derive(Debug)]
@@ -258,6 +360,26 @@ impl<N: CharMathNumeric<N>> GenericMatrix<N> {
     pub fn sized(h: usize, w: usize) -> Self {
         Self::from_flat(&[], h, w)
     }
+    /// Copies into a `Mat4`, panicking if the matrix is not 4x4.
+    pub fn to_mat4(&self) -> Mat4<N> {
+        assert!(
+            self.get_height() == 4 && self.get_width() == 4,
+            "Cannot convert a {}x{} matrix to a Mat4.",
+            self.get_height(),
+            self.get_width()
+        );
+        Mat4::<N>::from_flat(&self.flatten(), 4, 4)
+    }
+    /// Copies into a `Mat2`, panicking if the matrix is not 2x2.
+    pub fn to_mat2(&self) -> Mat2<N> {
+        assert!(
+            self.get_height() == 2 && self.get_width() == 2,
+            "Cannot convert a {}x{} matrix to a Mat2.",
+            self.get_height(),
+            self.get_width()
+        );
+        Mat2::<N>::from_flat(&self.flatten(), 2, 2)
+    }
 }
 impl<N: CharMathNumeric<N>> CharMathCopy<GenericMatrix<N>> for GenericMatrix<N> {
     fn cm_copy(&self) -> Self {
@@ -317,6 +439,15 @@ impl<N: CharMathNumeric<N>> MatrixBase<N> for Mat4<N> {
         &mut self.mat[h][w]
     }
 }
+impl<N: CharMathNumeric<N>> Mat4<N> {
+    pub fn to_generic(&self) -> GenericMatrix<N> {
+        GenericMatrix::<N>::from_flat(&self.flatten(), 4, 4)
+    }
+    /// The elements in row-major order, like `flatten` but without copying.
+    pub fn as_slice(&self) -> &[N] {
+        self.mat.as_flattened()
+    }
+}
 impl<N: CharMathNumeric<N>> CharMathCopy<Mat4<N>> for Mat4<N> {
     fn cm_copy(&self) -> Self {
         Mat4::<N> { mat: self.mat }
@@ -396,6 +527,15 @@ impl<N: CharMathNumeric<N>> MatrixBase<N> for Mat2<N> {
         &mut self.mat[h][w]
     }
 }
+impl<N: CharMathNumeric<N>> Mat2<N> {
+    pub fn to_generic(&self) -> GenericMatrix<N> {
+        GenericMatrix::<N>::from_flat(&self.flatten(), 2, 2)
+    }
+    /// The elements in row-major order, like `flatten` but without copying.
+    pub fn as_slice(&self) -> &[N] {
+        self.mat.as_flattened()
+    }
+}
 impl<N: CharMathNumeric<N>> CharMathCopy<Mat2<N>> for Mat2<N> {
     fn cm_copy(&self) -> Self {
         Mat2::<N> { mat: self.mat }
@@ -439,7 +579,9 @@ pub type Mat2D = Mat2<f64>;
 pub type Mat2F = Mat2<f32>;
 
 pub mod matrices {
-    use crate::linear::matrix::{GenericMatrix, Mat2, Mat4, Matrix, MatrixBase};
+    use crate::linear::matrix::{
+        EulerOrder, GenericMatrix, Mat2, Mat4, Matrix, MatrixBase, SquareMatrix,
+    };
     use crate::linear::quaternion::Quaternion;
     use crate::linear::vector::{Vec3, Vec4, VectorBase};
     use crate::numeric::CharMathNumeric;
@@ -495,7 +637,16 @@ pub mod matrices {
             2,
         )
     }
+    /// Euler rotation in `EulerOrder::ZYX` order, i.e. `Rz * Ry * Rx`.
     pub fn rotation_euler_num<N: CharMathNumeric<N>>(x: N, y: N, z: N) -> Mat4<N> {
+        rotation_euler_order(x, y, z, EulerOrder::ZYX)
+    }
+    pub fn rotation_euler_order<N: CharMathNumeric<N>>(
+        x: N,
+        y: N,
+        z: N,
+        order: EulerOrder,
+    ) -> Mat4<N> {
         let mut rot_x = identity::<N>(4);
         rot_x[1][1] = N::cos(x);
         rot_x[2][2] = N::cos(x);
@@ -511,7 +662,15 @@ pub mod matrices {
         rot_z[1][1] = N::cos(z);
         rot_z[0][1] = N::sin(z);
         rot_z[1][0] = N::neg(N::sin(z));
-        Mat4::<N>::from_matrix(&rot_z.mul_mat(&rot_y).mul_mat(&rot_x))
+        let (a, b, c) = match order {
+            EulerOrder::XYZ => (&rot_x, &rot_y, &rot_z),
+            EulerOrder::XZY => (&rot_x, &rot_z, &rot_y),
+            EulerOrder::YXZ => (&rot_y, &rot_x, &rot_z),
+            EulerOrder::YZX => (&rot_y, &rot_z, &rot_x),
+            EulerOrder::ZXY => (&rot_z, &rot_x, &rot_y),
+            EulerOrder::ZYX => (&rot_z, &rot_y, &rot_x),
+        };
+        Mat4::<N>::from_matrix(&a.mul_mat(b).mul_mat(c))
     }
     pub fn rotation_euler<N: CharMathNumeric<N>, V: VectorBase<N>>(v: &V) -> Mat4<N> {
         rotation_euler_num::<N>(v.get_value(0), v.get_value(1), v.get_value(2))
@@ -527,6 +686,38 @@ pub mod matrices {
         ret[3][3] = N::zero();
         ret
     }
+    /// Like `perspective`, but maps the near plane to depth 1 and the far plane to 0 for
+    /// better precision at distance. Use with `clear_depth(0.0)` and `depth_func(Greater)`.
+    pub fn perspective_reverse_z<N: CharMathNumeric<N>>(
+        fov: N,
+        aspect: N,
+        near: N,
+        far: N,
+    ) -> Mat4<N> {
+        let mut ret = perspective::<N>(fov, aspect, near, far);
+        ret[2][2] = near / (near - far);
+        ret[3][2] = (far * near) / (far - near);
+        ret
+    }
+    /// Orthographic projection of the box bounded by the given planes, mapping depth to
+    /// `[0, 1]` with the same +Z forward convention as `perspective`.
+    pub fn orthographic<N: CharMathNumeric<N>>(
+        left: N,
+        right: N,
+        bottom: N,
+        top: N,
+        near: N,
+        far: N,
+    ) -> Mat4<N> {
+        let mut ret = Mat4::<N>::from_matrix(&identity::<N>(4));
+        ret[0][0] = N::two() / (right - left);
+        ret[1][1] = N::two() / (top - bottom);
+        ret[2][2] = N::one() / (far - near);
+        ret[3][0] = N::neg(right + left) / (right - left);
+        ret[3][1] = N::neg(top + bottom) / (top - bottom);
+        ret[3][2] = N::neg(near) / (far - near);
+        ret
+    }
     pub fn look_at_3d<N: CharMathNumeric<N>, V: Vec3<N, V>>(
         pos: &V,
         target: &V,
@@ -560,6 +751,153 @@ pub mod matrices {
             4,
         )
     }
+    /// Builds a right-handed view matrix looking from `eye` towards `center`, matching
+    /// the conventional OpenGL `lookAt`. The camera looks down its local -Z axis, so points
+    /// in front of it have a negative view-space z. No inverse is required.
+    pub fn look_at_rh<N: CharMathNumeric<N>, V: Vec3<N, V>>(
+        eye: &V,
+        center: &V,
+        up: &V,
+    ) -> Mat4<N> {
+        let f = center.sub_vec(eye).normalized();
+        let s = f.cross(up).normalized();
+        let u = s.cross(&f);
+        Mat4::<N>::from_flat(
+            &[
+                s[0],
+                u[0],
+                N::neg(f[0]),
+                N::zero(),
+                s[1],
+                u[1],
+                N::neg(f[1]),
+                N::zero(),
+                s[2],
+                u[2],
+                N::neg(f[2]),
+                N::zero(),
+                N::neg(s.dot(eye)),
+                N::neg(u.dot(eye)),
+                f.dot(eye),
+                N::one(),
+            ],
+            4,
+            4,
+        )
+    }
+    /// Places a camera-facing quad at `object`. Rows are the right, up and forward axes
+    /// followed by the position, like `look_at_3d`, with +Z pointing at `camera` and +Y
+    /// as close to `up` as possible. When the camera looks along `up`, another world
+    /// axis picks the roll; a camera exactly at `object` gives the identity rotation.
+    pub fn billboard<N: CharMathNumeric<N>, V: Vec3<N, V>>(
+        camera: &V,
+        object: &V,
+        up: &V,
+    ) -> Mat4<N> {
+        let forward = facing_axis(&camera.sub_vec(object));
+        let fallbacks = [
+            V::new(N::zero(), N::zero(), N::one()),
+            V::new(N::one(), N::zero(), N::zero()),
+        ];
+        let mut right = up.cross(&forward);
+        for axis in fallbacks.iter() {
+            if right.dot(&right) > N::epsilon() {
+                break;
+            }
+            right = axis.cross(&forward);
+        }
+        let right = right.normalized();
+        oriented_at(&right, &forward.cross(&right), &forward, object)
+    }
+    /// Like `billboard`, but only turns the quad about the world Y axis so it stays
+    /// upright, as for trees and name labels.
+    pub fn billboard_cylindrical<N: CharMathNumeric<N>, V: Vec3<N, V>>(
+        camera: &V,
+        object: &V,
+    ) -> Mat4<N> {
+        let mut to_camera = camera.sub_vec(object);
+        to_camera.set_y(N::zero());
+        let forward = facing_axis(&to_camera);
+        let up = V::new(N::zero(), N::one(), N::zero());
+        oriented_at(&up.cross(&forward), &up, &forward, object)
+    }
+    /// `dir` normalized, or +Z when it is too short to have a direction.
+    fn facing_axis<N: CharMathNumeric<N>, V: Vec3<N, V>>(dir: &V) -> V {
+        if dir.dot(dir) > N::epsilon() {
+            dir.normalized()
+        } else {
+            V::new(N::zero(), N::zero(), N::one())
+        }
+    }
+    fn oriented_at<N: CharMathNumeric<N>, V: Vec3<N, V>>(
+        right: &V,
+        up: &V,
+        forward: &V,
+        pos: &V,
+    ) -> Mat4<N> {
+        Mat4::<N>::from_flat(
+            &[
+                right[0],
+                right[1],
+                right[2],
+                N::zero(),
+                up[0],
+                up[1],
+                up[2],
+                N::zero(),
+                forward[0],
+                forward[1],
+                forward[2],
+                N::zero(),
+                pos[0],
+                pos[1],
+                pos[2],
+                N::one(),
+            ],
+            4,
+            4,
+        )
+    }
+    /// Builds a 3x3 tangent space basis with the tangent, bitangent and normal as rows,
+    /// so a tangent space row vector multiplied by it is brought into object space.
+    pub fn tbn<N: CharMathNumeric<N>, V: Vec3<N, V>>(
+        tangent: &V,
+        bitangent: &V,
+        normal: &V,
+    ) -> GenericMatrix<N> {
+        GenericMatrix::<N>::from_flat(
+            &[
+                tangent[0],
+                tangent[1],
+                tangent[2],
+                bitangent[0],
+                bitangent[1],
+                bitangent[2],
+                normal[0],
+                normal[1],
+                normal[2],
+            ],
+            3,
+            3,
+        )
+    }
+    /// Inverse-transpose of the upper 3x3 of a 4x4 transform, for transforming normals.
+    pub fn normal_matrix<N: CharMathNumeric<N>>(m: &dyn MatrixBase<N>) -> Mat4<N> {
+        let mut upper = GenericMatrix::<N>::from_flat(&[], 3, 3);
+        for i in 0..3 {
+            for j in 0..3 {
+                *upper.get_value_ref_mut(i, j) = *m.get_value_ref(i, j);
+            }
+        }
+        let inv = upper.inverse();
+        let mut ret = identity::<N>(4);
+        for i in 0..3 {
+            for j in 0..3 {
+                *ret.get_value_ref_mut(i, j) = *inv.get_value_ref(j, i);
+            }
+        }
+        Mat4::<N>::from_matrix(&ret)
+    }
     // #[cfg_attr(target_family = "wasm", wasm_bindgen)]
     pub fn rotation_quaternion_num<N: CharMathNumeric<N>>(x: N, y: N, z: N, w: N) -> Mat4<N> {
         let two = N::two();
@@ -591,6 +929,66 @@ pub mod matrices {
     pub fn rotation_quaternion<N: CharMathNumeric<N>>(q: &Quaternion<N>) -> Mat4<N> {
         rotation_quaternion_num::<N>(q.get_x(), q.get_y(), q.get_z(), q.get_w())
     }
+    /// Rotates then translates, equal to `rotation_quaternion(rotation) * translation_3d(translation)`.
+    pub fn transform_qt<N: CharMathNumeric<N>, V: VectorBase<N>>(
+        rotation: &Quaternion<N>,
+        translation: &V,
+    ) -> Mat4<N> {
+        let mut ret = rotation_quaternion::<N>(rotation);
+        let trans = translation.get_internal_array();
+        let n = trans.len().min(3);
+        ret[3][..n].copy_from_slice(&trans[..n]);
+        ret
+    }
+    /// Scales, rotates then translates, equal to
+    /// `scale_3d(scale) * rotation_quaternion(rotation) * translation_3d(translation)`.
+    pub fn transform_qts<N: CharMathNumeric<N>, V: VectorBase<N>>(
+        rotation: &Quaternion<N>,
+        translation: &V,
+        scale: &V,
+    ) -> Mat4<N> {
+        let mut ret = transform_qt::<N, V>(rotation, translation);
+        let scales = scale.get_internal_array();
+        for (i, s) in scales.iter().take(3).enumerate() {
+            for value in ret[i][..3].iter_mut() {
+                *value *= *s;
+            }
+        }
+        ret
+    }
+    /// Scales, rotates about z then translates in the xy plane, equal to
+    /// `scale_3d(scale) * rotation_euler_num(0, 0, rotation) * translation_3d(position)`.
+    /// Rotation is counter-clockwise in radians, for use with an orthographic projection.
+    pub fn transform_2d<N: CharMathNumeric<N>, V: VectorBase<N>>(
+        position: &V,
+        rotation: N,
+        scale: &V,
+    ) -> Mat4<N> {
+        let (sin, cos) = (N::sin(rotation), N::cos(rotation));
+        let (sx, sy) = (scale.get_value(0), scale.get_value(1));
+        Mat4::<N>::from_flat(
+            &[
+                sx * cos,
+                sx * sin,
+                N::zero(),
+                N::zero(),
+                N::neg(sy * sin),
+                sy * cos,
+                N::zero(),
+                N::zero(),
+                N::zero(),
+                N::zero(),
+                N::one(),
+                N::zero(),
+                position.get_value(0),
+                position.get_value(1),
+                N::zero(),
+                N::one(),
+            ],
+            4,
+            4,
+        )
+    }
 }
 
 macro_rules! gen_wasm_square_matrix {
@@ -767,6 +1165,12 @@ macro_rules! gen_wasm_square_matrix {
             }
         }
         impl SquareMatrix<$NUM, $CLASS> for $CLASS {}
+        impl $CLASS {
+            /// The elements in row-major order, like `flatten` but without copying.
+            pub fn as_slice(&self) -> &[$NUM] {
+                self.mat.as_flattened()
+            }
+        }
     };
 }
 macro_rules! gen_wasm_sq_mat4 {
@@ -783,6 +1187,15 @@ macro_rules! gen_wasm_sq_mat4 {
             pub fn wrotation_euler(vec: &$SVEC) -> $CLASS {
                 $CLASS::from_matrix(&matrices::rotation_euler::<$NUM, $SVEC>(vec))
             }
+            #[wasm_bindgen(js_name = rotationEulerOrder)]
+            pub fn wrotation_euler_order(vec: &$SVEC, order: EulerOrder) -> $CLASS {
+                $CLASS::from_matrix(&matrices::rotation_euler_order::<$NUM>(
+                    vec[0],
+                    vec[1],
+                    vec[2],
+                    order,
+                ))
+            }
             #[wasm_bindgen(js_name = rotationQuaternion)]
             pub fn wrotation_quaternion(q: &$QUA) -> $CLASS {
                 $CLASS::from_matrix(&matrices::rotation_quaternion_num::<$NUM>(
@@ -792,10 +1205,47 @@ macro_rules! gen_wasm_sq_mat4 {
                     q.get_w(),
                 ))
             }
+            #[wasm_bindgen(js_name = transformQt)]
+            pub fn wtransform_qt(rotation: &$QUA, translation: &$SVEC) -> $CLASS {
+                $CLASS::from_matrix(&matrices::transform_qt::<$NUM, $SVEC>(
+                    &crate::linear::quaternion::Quaternion::<$NUM>::new_arr(&[
+                        rotation.get_x(),
+                        rotation.get_y(),
+                        rotation.get_z(),
+                        rotation.get_w(),
+                    ]),
+                    translation,
+                ))
+            }
+            #[wasm_bindgen(js_name = transformQts)]
+            pub fn wtransform_qts(rotation: &$QUA, translation: &$SVEC, scale: &$SVEC) -> $CLASS {
+                $CLASS::from_matrix(&matrices::transform_qts::<$NUM, $SVEC>(
+                    &crate::linear::quaternion::Quaternion::<$NUM>::new_arr(&[
+                        rotation.get_x(),
+                        rotation.get_y(),
+                        rotation.get_z(),
+                        rotation.get_w(),
+                    ]),
+                    translation,
+                    scale,
+                ))
+            }
             #[wasm_bindgen(js_name = lookAt)]
             pub fn wlook_at(pos: &$SVEC, target: &$SVEC, up: &$SVEC) -> $CLASS {
                 $CLASS::from_matrix(&matrices::look_at_3d::<$NUM, $SVEC>(pos, target, up))
             }
+            #[wasm_bindgen(js_name = lookAtRh)]
+            pub fn wlook_at_rh(eye: &$SVEC, center: &$SVEC, up: &$SVEC) -> $CLASS {
+                $CLASS::from_matrix(&matrices::look_at_rh::<$NUM, $SVEC>(eye, center, up))
+            }
+            #[wasm_bindgen(js_name = billboard)]
+            pub fn wbillboard(camera: &$SVEC, object: &$SVEC, up: &$SVEC) -> $CLASS {
+                $CLASS::from_matrix(&matrices::billboard::<$NUM, $SVEC>(camera, object, up))
+            }
+            #[wasm_bindgen(js_name = billboardCylindrical)]
+            pub fn wbillboard_cylindrical(camera: &$SVEC, object: &$SVEC) -> $CLASS {
+                $CLASS::from_matrix(&matrices::billboard_cylindrical::<$NUM, $SVEC>(camera, object))
+            }
             #[wasm_bindgen(js_name = perspective)]
             pub fn wperspective(fov: f64, aspect: f64, near: f64, far: f64) -> $CLASS {
                 $CLASS::from_matrix(&matrices::perspective::<$NUM>(
@@ -805,6 +1255,33 @@ macro_rules! gen_wasm_sq_mat4 {
                     far as $NUM,
                 ))
             }
+            #[wasm_bindgen(js_name = orthographic)]
+            pub fn worthographic(
+                left: f64,
+                right: f64,
+                bottom: f64,
+                top: f64,
+                near: f64,
+                far: f64,
+            ) -> $CLASS {
+                $CLASS::from_matrix(&matrices::orthographic::<$NUM>(
+                    left as $NUM,
+                    right as $NUM,
+                    bottom as $NUM,
+                    top as $NUM,
+                    near as $NUM,
+                    far as $NUM,
+                ))
+            }
+            #[wasm_bindgen(js_name = perspectiveReverseZ)]
+            pub fn wperspective_reverse_z(fov: f64, aspect: f64, near: f64, far: f64) -> $CLASS {
+                $CLASS::from_matrix(&matrices::perspective_reverse_z::<$NUM>(
+                    fov as $NUM,
+                    aspect as $NUM,
+                    near as $NUM,
+                    far as $NUM,
+                ))
+            }
         }
     };
 }
@@ -841,3 +1318,242 @@ gen_wasm_sq_mat2!(Mat2f64, f64, Vec2f64);
 gen_wasm_sq_mat2!(Mat2f32, f32, Vec2f32);
 gen_wasm_sq_mat2!(Mat2i64, i64, Vec2i64);
 gen_wasm_sq_mat2!(Mat2i32, i32, Vec2i32);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::linear::quaternion::Quaternion;
+    use crate::linear::vector::{Vec3, Vec3f32, Vec4, Vec4f32};
+
+    #[test]
+    fn set_row_and_set_col_write_in_place() {
+        let mut mat = GenericMatrix::<f32>::sized(2, 3);
+        mat.set_row(1, &[1.0, 2.0, 3.0]);
+        mat.set_col(0, &[4.0, 5.0]);
+        assert_eq!(mat.get_row_vec(0), vec![4.0, 0.0, 0.0]);
+        assert_eq!(mat.get_row_vec(1), vec![5.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn transform_qts_matches_the_composed_product() {
+        let rotation = Quaternion::<f32>::angle_axis(0.7, &Vec3f32::new(0.0, 0.6, 0.8));
+        let translation = Vec3f32::new(1.0, -2.0, 3.0);
+        let scale = Vec3f32::new(2.0, 0.5, 3.0);
+        let composed = matrices::scale_3d::<f32, _>(&scale)
+            .mul_mat(&matrices::rotation_quaternion(&rotation))
+            .mul_mat(&matrices::translation_3d::<f32, _>(&translation));
+        assert_flat_near(
+            &matrices::transform_qts(&rotation, &translation, &scale).flatten(),
+            &composed.flatten(),
+        );
+        let composed = matrices::rotation_quaternion(&rotation)
+            .mul_mat(&matrices::translation_3d::<f32, _>(&translation));
+        assert_flat_near(
+            &matrices::transform_qt(&rotation, &translation).flatten(),
+            &composed.flatten(),
+        );
+    }
+
+    #[test]
+    fn solve_recovers_a_known_solution() {
+        let mat = GenericMatrix::<f64>::from_flat(
+            &[2.0, 1.0, -1.0, -3.0, -1.0, 2.0, -2.0, 1.0, 2.0],
+            3,
+            3,
+        );
+        let x = mat.solve(&[8.0, -11.0, -3.0]).unwrap();
+        for (x, expected) in x.iter().zip([2.0, 3.0, -1.0]) {
+            assert!((x - expected).abs() < 1e-12, "{:?}", x);
+        }
+    }
+
+    #[test]
+    fn solve_pivots_past_a_zero_diagonal() {
+        let mat = GenericMatrix::<f32>::from_flat(&[0.0, 1.0, 1.0, 0.0], 2, 2);
+        assert_eq!(mat.solve(&[3.0, 4.0]), Some(vec![4.0, 3.0]));
+    }
+
+    #[test]
+    fn solve_rejects_singular_matrices() {
+        let exact = GenericMatrix::<f32>::from_flat(&[1.0, 2.0, 2.0, 4.0], 2, 2);
+        assert_eq!(exact.solve(&[1.0, 2.0]), None);
+        // Only singular up to rounding, so elimination leaves a tiny nonzero pivot
+        let rounded =
+            GenericMatrix::<f32>::from_flat(&[0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9], 3, 3);
+        assert_eq!(rounded.solve(&[1.0, 2.0, 3.0]), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Row length must match matrix width.")]
+    fn set_row_rejects_wrong_length() {
+        GenericMatrix::<f32>::sized(2, 3).set_row(0, &[1.0, 2.0]);
+    }
+
+    fn assert_flat_near(a: &[f32], b: &[f32]) {
+        assert_eq!(a.len(), b.len());
+        for (x, y) in a.iter().zip(b.iter()) {
+            assert!((x - y).abs() < 1e-5, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn look_at_rh_puts_the_target_down_negative_z() {
+        let (eye, up) = (Vec3f32::new(1.0, 2.0, 3.0), Vec3f32::new(0.0, 1.0, 0.0));
+        let view = matrices::look_at_rh(&eye, &Vec3f32::new(1.0, 2.0, -2.0), &up);
+        let to_view = |x: f32, y: f32, z: f32| {
+            let (x, y, z, w) = view.mul_row_vec(&Vec4f32::new(x, y, z, 1.0)).as_tuple();
+            [x, y, z, w]
+        };
+        assert_flat_near(&to_view(1.0, 2.0, 3.0), &[0.0, 0.0, 0.0, 1.0]);
+        assert_flat_near(&to_view(1.0, 2.0, -2.0), &[0.0, 0.0, -5.0, 1.0]);
+        assert_flat_near(&to_view(1.0, 3.0, 3.0), &[0.0, 1.0, 0.0, 1.0]);
+        assert_flat_near(&to_view(2.0, 2.0, 3.0), &[1.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn rotation_euler_order_composes_in_the_named_order() {
+        let (x, y, z) = (0.3f32, -0.8, 1.1);
+        let rx = matrices::rotation_euler_order(x, 0.0, 0.0, EulerOrder::XYZ);
+        let ry = matrices::rotation_euler_order(0.0, y, 0.0, EulerOrder::XYZ);
+        let rz = matrices::rotation_euler_order(0.0, 0.0, z, EulerOrder::XYZ);
+        let xyz = matrices::rotation_euler_order(x, y, z, EulerOrder::XYZ);
+        let zyx = matrices::rotation_euler_order(x, y, z, EulerOrder::ZYX);
+        assert_flat_near(&xyz.flatten(), &rx.mul_mat(&ry).mul_mat(&rz).flatten());
+        assert_flat_near(&zyx.flatten(), &rz.mul_mat(&ry).mul_mat(&rx).flatten());
+        assert_flat_near(
+            &zyx.flatten(),
+            &matrices::rotation_euler_num(x, y, z).flatten(),
+        );
+        assert!(xyz.flatten()[1] != zyx.flatten()[1]);
+    }
+
+    #[test]
+    fn tbn_of_an_orthonormal_frame_is_a_rotation() {
+        use crate::linear::vector::Vector;
+        let rot = Quaternion::<f32>::angle_axis(0.7, &Vec3f32::new(1.0, 2.0, -0.5).normalized());
+        let tangent = rot.rotate_vec(&Vec3f32::new(1.0, 0.0, 0.0));
+        let bitangent = rot.rotate_vec(&Vec3f32::new(0.0, 1.0, 0.0));
+        let normal = tangent.cross(&bitangent);
+        let m = matrices::tbn(&tangent, &bitangent, &normal);
+        for i in 0..3 {
+            for j in 0..3 {
+                let dot: f32 = (0..3)
+                    .map(|k| m.get_value_ref(i, k) * m.get_value_ref(j, k))
+                    .sum();
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!(
+                    (dot - expected).abs() < 1e-5,
+                    "row {} . row {} = {}",
+                    i,
+                    j,
+                    dot
+                );
+            }
+        }
+        // Right-handed: tangent x bitangent = normal
+        assert!((m.determinant() - 1.0).abs() < 1e-5);
+        let flipped = matrices::tbn(&tangent, &bitangent, &normal.mul_num(-1.0));
+        assert!((flipped.determinant() + 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn transform_2d_matches_the_composed_product() {
+        use crate::linear::vector::{Vec2, Vec2f32};
+        use std::f32::consts::FRAC_PI_2;
+        let (position, scale) = (Vec2f32::new(3.0, -1.0), Vec2f32::new(2.0, 0.5));
+        let translation = Vec3f32::new(3.0, -1.0, 0.0);
+        let composed = matrices::scale_3d::<f32, _>(&Vec3f32::new(2.0, 0.5, 1.0))
+            .mul_mat(&matrices::rotation_euler_num(0.0, 0.0, 0.6))
+            .mul_mat(&matrices::translation_3d::<f32, _>(&translation));
+        let transform = matrices::transform_2d(&position, 0.6, &scale);
+        assert_flat_near(&transform.flatten(), &composed.flatten());
+        // Counter-clockwise: +x turns towards +y
+        let (origin, unit) = (Vec2f32::new(0.0, 0.0), Vec2f32::new(1.0, 1.0));
+        let turned = matrices::transform_2d(&origin, FRAC_PI_2, &unit)
+            .mul_row_vec(&Vec4f32::new(1.0, 0.0, 0.0, 1.0));
+        assert_flat_near(&[turned.get_x(), turned.get_y()], &[0.0, 1.0]);
+    }
+
+    #[test]
+    fn perspective_reverse_z_maps_near_to_one_and_far_to_zero() {
+        let proj = matrices::perspective_reverse_z(1.2f32, 1.5, 0.1, 100.0);
+        let depth = |z: f32| {
+            let clip = proj.mul_row_vec(&Vec4f32::new(0.0, 0.0, z, 1.0));
+            clip.get_z() / clip.get_w()
+        };
+        assert_flat_near(&[depth(0.1), depth(100.0)], &[1.0, 0.0]);
+        assert!(depth(1.0) > depth(10.0));
+    }
+
+    #[test]
+    fn fixed_matrices_round_trip_through_generic() {
+        let flat: Vec<f32> = (0..16).map(|i| i as f32 * 0.5 - 3.0).collect();
+        let mat4 = Mat4::<f32>::from_flat(&flat, 4, 4);
+        let generic = mat4.to_generic();
+        assert_eq!((generic.get_height(), generic.get_width()), (4, 4));
+        assert_eq!(generic.to_mat4().flatten(), flat);
+        let mat2 = Mat2::<f32>::from_flat(&[1.0, -2.0, 3.5, 4.0], 2, 2);
+        assert_eq!(mat2.to_generic().to_mat2().flatten(), mat2.flatten());
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot convert a 3x4 matrix to a Mat4.")]
+    fn to_mat4_rejects_other_sizes() {
+        GenericMatrix::<f32>::sized(3, 4).to_mat4();
+    }
+
+    #[test]
+    fn pow_matches_repeated_multiplication() {
+        let flat: Vec<f32> = (0..16).map(|i| ((i * 7) % 5) as f32 * 0.25 - 0.5).collect();
+        let m = Mat4::<f32>::from_flat(&flat, 4, 4);
+        let cubed = m.mul_mat(&m).mul_mat(&m);
+        assert_flat_near(&m.pow(3).flatten(), &cubed.flatten());
+        assert_flat_near(&m.pow(6).flatten(), &cubed.mul_mat(&cubed).flatten());
+        assert_eq!(m.pow(1).flatten(), flat);
+        assert_eq!(m.pow(0).flatten(), matrices::identity::<f32>(4).flatten());
+    }
+
+    #[test]
+    fn as_slice_matches_flatten_without_copying() {
+        let flat: Vec<f32> = (0..16).map(|i| i as f32).collect();
+        let mat4 = Mat4::<f32>::from_flat(&flat, 4, 4);
+        assert_eq!(mat4.as_slice(), flat.as_slice());
+        assert_eq!(mat4.as_slice().as_ptr(), &mat4[0][0] as *const f32);
+        let mat2 = Mat2::<f32>::from_flat(&[1.0, 2.0, 3.0, 4.0], 2, 2);
+        assert_eq!(mat2.as_slice(), mat2.flatten().as_slice());
+        let mat4f32 = Mat4f32::from_flat(&flat, 4, 4);
+        assert_eq!(mat4f32.as_slice(), flat.as_slice());
+    }
+
+    #[test]
+    fn billboard_faces_the_camera() {
+        use crate::linear::vector::Vector;
+        let (camera, object) = (Vec3f32::new(3.0, 4.0, -2.0), Vec3f32::new(1.0, 0.0, 2.0));
+        let up = Vec3f32::new(0.0, 1.0, 0.0);
+        let to_camera = camera.sub_vec(&object).normalized();
+        let flat = matrices::billboard(&camera, &object, &up).flatten();
+        let (right, up_axis) = (
+            Vec3f32::new(flat[0], flat[1], flat[2]),
+            Vec3f32::new(flat[4], flat[5], flat[6]),
+        );
+        assert_flat_near(&flat[8..11], &[to_camera[0], to_camera[1], to_camera[2]]);
+        assert_flat_near(&flat[12..16], &[1.0, 0.0, 2.0, 1.0]);
+        assert_flat_near(
+            &[right.dot(&up_axis), right.dot(&to_camera), up_axis.len()],
+            &[0.0, 0.0, 1.0],
+        );
+        assert!(up_axis.get_y() > 0.0);
+        // Looking straight down still gives an orthonormal basis
+        let above = matrices::billboard(&Vec3f32::new(1.0, 5.0, 2.0), &object, &up).flatten();
+        assert_flat_near(&above[8..11], &[0.0, 1.0, 0.0]);
+        assert!((Vec3f32::new(above[0], above[1], above[2]).len() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn cylindrical_billboard_stays_upright() {
+        let (camera, object) = (Vec3f32::new(4.0, 9.0, 0.0), Vec3f32::new(0.0, 0.0, 0.0));
+        let flat = matrices::billboard_cylindrical(&camera, &object).flatten();
+        assert_flat_near(&flat[4..7], &[0.0, 1.0, 0.0]);
+        assert_flat_near(&flat[8..11], &[1.0, 0.0, 0.0]);
+    }
+}
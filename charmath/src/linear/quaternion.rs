@@ -10,7 +10,7 @@ extern "C" {
 
 use crate::linear::vector::{Vec3, Vec4, Vector, VectorBase};
 use crate::numeric::CharMathNumeric;
-use crate::CharMathCopy;
+use crate::{CharMathCopy, Lerp};
 use crate::{Algebraic, AlgebraicAssignable};
 use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Sub, SubAssign};
 
@@ -89,7 +89,7 @@ macro_rules! quat_assign_operand {
     };
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 #[repr(C)]
 pub struct Quaternion<N: CharMathNumeric<N>> {
     vec: [N; 4],
@@ -161,6 +161,15 @@ impl<N: CharMathNumeric<N>> Quaternion<N> {
             N::cos(angle * N::half()),
         )
     }
+    /// Rotation matching `matrices::rotation_euler(angles)`, so that
+    /// `from_euler(a).rotate_vec(v)` equals `v.rotated_euler(a)`.
+    pub fn from_euler<V: Vec3<N, V>>(angles: &V) -> Quaternion<N> {
+        let (x, y, z) = angles.as_tuple();
+        let qx = Self::angle_axis(x, &V::new(N::one(), N::zero(), N::zero()));
+        let qy = Self::angle_axis(N::neg(y), &V::new(N::zero(), N::one(), N::zero()));
+        let qz = Self::angle_axis(z, &V::new(N::zero(), N::zero(), N::one()));
+        qx.hamilton(&qy).hamilton(&qz)
+    }
     pub fn get_complex<V: Vec4<N, V>>(&self) -> V {
         V::new_arr(&[self.get_x(), self.get_y(), self.get_z()])
     }
@@ -169,12 +178,75 @@ impl<N: CharMathNumeric<N>> Quaternion<N> {
         self.set_y(complex.get_y());
         self.set_z(complex.get_z());
     }
+    /// Hamilton product `self * rhs`, applying the rotation `rhs` before `self`.
+    pub fn hamilton(&self, rhs: &Quaternion<N>) -> Quaternion<N> {
+        let (x1, y1, z1, w1) = (self.get_x(), self.get_y(), self.get_z(), self.get_w());
+        let (x2, y2, z2, w2) = (rhs.get_x(), rhs.get_y(), rhs.get_z(), rhs.get_w());
+        Self::new(
+            w1 * x2 + x1 * w2 + y1 * z2 - z1 * y2,
+            w1 * y2 - x1 * z2 + y1 * w2 + z1 * x2,
+            w1 * z2 + x1 * y2 - y1 * x2 + z1 * w2,
+            w1 * w2 - x1 * x2 - y1 * y2 - z1 * z2,
+        )
+    }
+    /// Inverse of `angle_axis`, returning the unit rotation axis and the angle in
+    /// `[0, 2pi)`. The quaternion does not need to be normalized. With no rotation the
+    /// axis is arbitrary, so +X is returned with an angle of zero.
+    pub fn to_axis_angle<V: Vec3<N, V>>(&self) -> (V, N) {
+        let (x, y, z, w) = (self.get_x(), self.get_y(), self.get_z(), self.get_w());
+        let s = N::sqrt(x * x + y * y + z * z);
+        if s == N::zero() {
+            return (V::new(N::one(), N::zero(), N::zero()), N::zero());
+        }
+        (V::new(x / s, y / s, z / s), N::two() * N::atan2(s, w))
+    }
+    /// Spherical interpolation between unit quaternions along the shortest arc, at a
+    /// constant angular speed. Nearly equal rotations fall back to a normalized lerp.
+    pub fn slerp(&self, other: &Quaternion<N>, t: N) -> Quaternion<N> {
+        let mut d = self.dot(other);
+        let mut end = other.cm_copy();
+        if d < N::zero() {
+            d = N::neg(d);
+            end = end.mul_num(N::neg(N::one()));
+        }
+        let sin_theta = if d < N::one() {
+            N::sqrt(N::one() - d * d)
+        } else {
+            N::zero()
+        };
+        if sin_theta <= N::epsilon() {
+            return self.mul_num(N::one() - t).add_vec(&end.mul_num(t)).normalized();
+        }
+        let theta = N::atan2(sin_theta, d);
+        let a = N::sin((N::one() - t) * theta) / sin_theta;
+        let b = N::sin(t * theta) / sin_theta;
+        self.mul_num(a).add_vec(&end.mul_num(b))
+    }
+    /// Angle in radians of the smallest rotation between two unit quaternions, from 0
+    /// to pi. `q` and `-q` are the same rotation, so their angle is 0.
+    pub fn angle_between(&self, other: &Quaternion<N>) -> N {
+        let mut end = other.cm_copy();
+        if self.dot(other) < N::zero() {
+            end = end.mul_num(N::neg(N::one()));
+        }
+        // Equal to 2 * acos(dot), but keeps its precision for nearly equal rotations
+        let quarter = N::atan2(self.sub_vec(&end).len(), self.add_vec(&end).len());
+        N::two() * N::two() * quarter
+    }
+    /// Rotates a vector by this quaternion, which must be normalized.
+    pub fn rotate_vec<V: Vec3<N, V>>(&self, v: &V) -> V {
+        let u = V::new(self.get_x(), self.get_y(), self.get_z());
+        let uv = u.cross(v);
+        let uuv = u.cross(&uv);
+        v.add_vec(&uv.mul_num(N::two() * self.get_w()))
+            .add_vec(&uuv.mul_num(N::two()))
+    }
 }
 
 macro_rules! gen_wasm_quat {
     ($QUAT:ident, $NUM:ident, $SVEC:ident) => {
         #[cfg_attr(target_family = "wasm", wasm_bindgen)]
-        #[derive(Debug)]
+        #[derive(Debug, Clone, Copy)]
         #[repr(C)]
         pub struct $QUAT {
             vec: [$NUM; 4],
@@ -294,6 +366,17 @@ macro_rules! gen_wasm_quat {
                     $NUM::cos(angle * $NUM::half()),
                 )
             }
+            /// Rotation matching `matrices::rotation_euler(angles)`, so that
+            /// `from_euler(a).rotate_vec(v)` equals `v.rotated_euler(a)`.
+            #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = fromEuler))]
+            pub fn from_euler(angles: &$SVEC) -> $QUAT {
+                let (x, y, z) = angles.as_tuple();
+                let (zero, one) = ($NUM::zero(), $NUM::one());
+                let qx = Self::angle_axis(x, &$SVEC::new(one, zero, zero));
+                let qy = Self::angle_axis(-y, &$SVEC::new(zero, one, zero));
+                let qz = Self::angle_axis(z, &$SVEC::new(zero, zero, one));
+                qx.hamilton(&qy).hamilton(&qz)
+            }
             #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = getComplex))]
             pub fn get_complex(&self) -> $SVEC {
                 $SVEC::new_arr(&[self.get_x(), self.get_y(), self.get_z()])
@@ -303,6 +386,61 @@ macro_rules! gen_wasm_quat {
                 self.set_x(complex.get_x());
                 self.set_y(complex.get_y());
                 self.set_z(complex.get_z());
+            }
+            #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = hamilton))]
+            pub fn hamilton(&self, rhs: &$QUAT) -> $QUAT {
+                let (x1, y1, z1, w1) = (self.get_x(), self.get_y(), self.get_z(), self.get_w());
+                let (x2, y2, z2, w2) = (rhs.get_x(), rhs.get_y(), rhs.get_z(), rhs.get_w());
+                Self::new(
+                    w1 * x2 + x1 * w2 + y1 * z2 - z1 * y2,
+                    w1 * y2 - x1 * z2 + y1 * w2 + z1 * x2,
+                    w1 * z2 + x1 * y2 - y1 * x2 + z1 * w2,
+                    w1 * w2 - x1 * x2 - y1 * y2 - z1 * z2,
+                )
+            }
+            /// Spherical interpolation between unit quaternions along the shortest arc, at
+            /// a constant angular speed. Nearly equal rotations fall back to a normalized
+            /// lerp.
+            #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = slerp))]
+            pub fn slerp(&self, other: &$QUAT, t: $NUM) -> $QUAT {
+                let mut d = self.dot(other);
+                let mut end = other.cm_copy();
+                if d < $NUM::zero() {
+                    d = $NUM::neg(d);
+                    end = end.mul_num($NUM::neg($NUM::one()));
+                }
+                let sin_theta = if d < $NUM::one() {
+                    $NUM::sqrt($NUM::one() - d * d)
+                } else {
+                    $NUM::zero()
+                };
+                if sin_theta <= $NUM::epsilon() {
+                    return self.mul_num($NUM::one() - t).add_vec(&end.mul_num(t)).normalized();
+                }
+                let theta = $NUM::atan2(sin_theta, d);
+                let a = $NUM::sin(($NUM::one() - t) * theta) / sin_theta;
+                let b = $NUM::sin(t * theta) / sin_theta;
+                self.mul_num(a).add_vec(&end.mul_num(b))
+            }
+            /// Angle in radians of the smallest rotation between two unit quaternions,
+            /// from 0 to pi. `q` and `-q` are the same rotation, so their angle is 0.
+            #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = angleBetween))]
+            pub fn angle_between(&self, other: &$QUAT) -> $NUM {
+                let mut end = other.cm_copy();
+                if self.dot(other) < $NUM::zero() {
+                    end = end.mul_num($NUM::neg($NUM::one()));
+                }
+                // Equal to 2 * acos(dot), but keeps its precision for nearly equal rotations
+                let quarter = $NUM::atan2(self.sub_vec(&end).len(), self.add_vec(&end).len());
+                $NUM::two() * $NUM::two() * quarter
+            }
+            #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = rotateVec))]
+            pub fn rotate_vec(&self, v: &$SVEC) -> $SVEC {
+                let u = $SVEC::new(self.get_x(), self.get_y(), self.get_z());
+                let uv = u.cross(v);
+                let uuv = u.cross(&uv);
+                v.add_vec(&uv.mul_num($NUM::two() * self.get_w()))
+                    .add_vec(&uuv.mul_num($NUM::two()))
             }
 			#[cfg(target_family = "wasm")]
             #[wasm_bindgen(js_name = normalized)]
@@ -315,6 +453,19 @@ macro_rules! gen_wasm_quat {
                 self.normalize();
             }
         }
+        impl $QUAT {
+            /// Inverse of `angle_axis`, returning the unit rotation axis and the angle in
+            /// `[0, 2pi)`. The quaternion does not need to be normalized. With no rotation
+            /// the axis is arbitrary, so +X is returned with an angle of zero.
+            pub fn to_axis_angle(&self) -> ($SVEC, $NUM) {
+                let (x, y, z, w) = (self.get_x(), self.get_y(), self.get_z(), self.get_w());
+                let s = $NUM::sqrt(x * x + y * y + z * z);
+                if s == $NUM::zero() {
+                    return ($SVEC::new($NUM::one(), $NUM::zero(), $NUM::zero()), $NUM::zero());
+                }
+                ($SVEC::new(x / s, y / s, z / s), $NUM::two() * $NUM::atan2(s, w))
+            }
+        }
     };
 }
 
@@ -324,3 +475,100 @@ gen_wasm_quat!(Quaternionf64, f64, Vec3f64);
 gen_wasm_quat!(Quaternionf32, f32, Vec3f32);
 gen_wasm_quat!(Quaternioni64, i64, Vec3i64);
 gen_wasm_quat!(Quaternioni32, i32, Vec3i32);
+
+// Integer quaternions cannot represent the rotations in between, so only floats blend
+macro_rules! quat_lerp {
+    ($QUAT:ident, $NUM:ident) => {
+        impl Lerp for $QUAT {
+            fn interpolate(&self, other: &$QUAT, t: f32) -> $QUAT {
+                self.slerp(other, t as $NUM)
+            }
+        }
+    };
+}
+
+quat_lerp!(Quaternionf64, f64);
+quat_lerp!(Quaternionf32, f32);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::linear::vector::Vec3f32;
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn axis_angle_round_trips() {
+        let axis = Vec3f32::new(1.0, -2.0, 2.0).normalized();
+        let (out_axis, angle): (Vec3f32, f32) =
+            Quaternion::<f32>::angle_axis(1.1, &axis).to_axis_angle();
+        assert_near(angle, 1.1);
+        assert_near(out_axis.sub_vec(&axis).len(), 0.0);
+    }
+
+    #[test]
+    fn identity_has_zero_angle() {
+        let (axis, angle): (Vec3f32, f32) =
+            Quaternion::<f32>::new(0.0, 0.0, 0.0, 1.0).to_axis_angle();
+        assert_eq!(angle, 0.0);
+        assert_near(axis.len(), 1.0);
+    }
+
+    #[test]
+    fn slerp_moves_at_constant_angular_speed() {
+        let axis = Vec3f32::new(0.0, 1.0, 0.0);
+        let a = Quaternion::<f32>::angle_axis(0.0, &axis);
+        let b = Quaternion::<f32>::angle_axis(1.2, &axis);
+        for t in [0.0, 0.25, 0.5, 1.0] {
+            let (_, angle): (Vec3f32, f32) = a.slerp(&b, t).to_axis_angle();
+            assert_near(angle, 1.2 * t);
+        }
+        // -b is the same rotation, and slerp takes the shortest arc to it
+        let (_, angle): (Vec3f32, f32) = a.slerp(&b.mul_num(-1.0), 0.5).to_axis_angle();
+        assert_near(angle, 0.6);
+    }
+
+    #[test]
+    fn quaternion_lerp_is_slerp() {
+        let axis = Vec3f32::new(1.0, 0.0, 0.0);
+        let a = Quaternionf32::angle_axis(0.3, &axis);
+        let b = Quaternionf32::angle_axis(2.0, &axis);
+        let (_, angle) = a.interpolate(&b, 0.5).to_axis_angle();
+        assert_near(angle, 1.15);
+    }
+
+    #[test]
+    fn angle_between_takes_the_smallest_rotation() {
+        let axis = Vec3f32::new(0.0, 0.0, 1.0);
+        let a = Quaternion::<f32>::angle_axis(0.2, &axis);
+        let b = Quaternion::<f32>::angle_axis(1.7, &axis);
+        assert_near(a.angle_between(&b), 1.5);
+        assert_near(a.angle_between(&b.mul_num(-1.0)), 1.5);
+        assert_near(a.angle_between(&a), 0.0);
+        let c = Quaternion::<f32>::angle_axis(0.2 + 1e-3, &axis);
+        assert!((a.angle_between(&c) - 1e-3).abs() < 1e-5);
+    }
+
+    #[test]
+    fn from_euler_matches_rotated_euler() {
+        use std::f32::consts::FRAC_PI_2;
+        let v = Vec3f32::new(0.0, 0.0, 1.0);
+        // rotation_euler turns a positive y angle from +Z towards -X
+        let quarter_turn = Vec3f32::new(0.0, -FRAC_PI_2, 0.0);
+        let out = Quaternion::<f32>::from_euler(&quarter_turn).rotate_vec(&v);
+        let (x, y, z) = out.as_tuple();
+        assert_near(x, 1.0);
+        assert_near(y, 0.0);
+        assert_near(z, 0.0);
+
+        let angles = Vec3f32::new(0.3, -1.2, 0.8);
+        let v = Vec3f32::new(0.5, -2.0, 1.5);
+        let out = Quaternion::<f32>::from_euler(&angles).rotate_vec(&v);
+        let expected = v.rotated_euler(&angles);
+        for i in 0..3 {
+            assert_near(out[i], expected[i]);
+        }
+    }
+}
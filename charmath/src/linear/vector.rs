@@ -9,7 +9,7 @@ extern "C" {
 }
 
 use crate::numeric::CharMathNumeric;
-use crate::{Algebraic, AlgebraicAssignable, CharMathCopy};
+use crate::{Algebraic, AlgebraicAssignable, CharMathCopy, Lerp};
 use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Sub, SubAssign};
 
 macro_rules! charmath_def_operand {
@@ -239,6 +239,35 @@ macro_rules! define_vec {
             pub fn wnormalize(&mut self) {
                 self.normalize();
             }
+            #[wasm_bindgen(js_name = lerp)]
+            pub fn wlerp(&self, o: &$CLASS, t: f64) -> $CLASS {
+                self.lerp(o, t as $NUM)
+            }
+            #[wasm_bindgen(js_name = clamp)]
+            pub fn wclamp(&self, min: &$CLASS, max: &$CLASS) -> $CLASS {
+                self.clamp(min, max)
+            }
+            #[wasm_bindgen(js_name = saturate)]
+            pub fn wsaturate(&self) -> $CLASS {
+                self.saturate()
+            }
+            #[wasm_bindgen(js_name = componentMin)]
+            pub fn wcomponent_min(&self, o: &$CLASS) -> $CLASS {
+                self.component_min(o)
+            }
+            #[wasm_bindgen(js_name = componentMax)]
+            pub fn wcomponent_max(&self, o: &$CLASS) -> $CLASS {
+                self.component_max(o)
+            }
+        }
+        impl Lerp for $CLASS {
+            fn interpolate(&self, other: &$CLASS, t: f32) -> $CLASS {
+                let mut ret = *self;
+                for i in 0..$LEN {
+                    ret.vec[i] = self.vec[i].interpolate(&other.vec[i], t);
+                }
+                ret
+            }
         }
         vector_def!($CLASS, $NUM, $LEN);
         vec_op_overload!($CLASS, $NUM);
@@ -321,6 +350,10 @@ macro_rules! define_vec3 {
             pub fn wcross(&self, o: &$CLASS) -> $CLASS {
                 self.cross(o)
             }
+            #[wasm_bindgen(js_name = rotatedEuler)]
+            pub fn wrotated_euler(&self, angles: &$CLASS) -> $CLASS {
+                self.rotated_euler(angles)
+            }
         }
     };
 }
@@ -480,6 +513,38 @@ pub trait Vector<NUM: Copy + Algebraic<NUM, NUM> + CharMathNumeric<NUM>, VEC: Ve
     fn mul_num(&self, other: NUM) -> VEC {
         self.num_op(other, |ls: NUM, rs: NUM| ls * rs)
     }
+    fn component_min(&self, other: &VEC) -> VEC {
+        self.vec_op(other, |ls: NUM, rs: NUM| if rs < ls { rs } else { ls })
+    }
+    fn component_max(&self, other: &VEC) -> VEC {
+        self.vec_op(other, |ls: NUM, rs: NUM| if rs > ls { rs } else { ls })
+    }
+    /// Clamps each component between the matching components of min and max.
+    fn clamp(&self, min: &VEC, max: &VEC) -> VEC {
+        self.component_max(min).component_min(max)
+    }
+    /// Linear interpolation, giving self at `t = 0` and other at `t = 1`. `t` is not
+    /// clamped.
+    fn lerp(&self, other: &VEC, t: NUM) -> VEC {
+        self.mul_num(NUM::one() - t).add_vec(&other.mul_num(t))
+    }
+    /// Clamps each component to the range [0, 1].
+    fn saturate(&self) -> VEC {
+        self.num_op(NUM::zero(), |ls: NUM, rs: NUM| if rs > ls { rs } else { ls })
+            .num_op(NUM::one(), |ls: NUM, rs: NUM| if rs < ls { rs } else { ls })
+    }
+    /// Angle in radians between two vectors, or zero if either has no length.
+    ///
+    /// Uses the half-angle form `2 * atan2(|u - v|, |u + v|)` of the unit vectors, which
+    /// stays in `[0, pi]` and avoids the precision loss of `acos` near parallel vectors.
+    fn angle_between(&self, other: &VEC) -> NUM {
+        if self.len() == NUM::zero() || other.len() == NUM::zero() {
+            return NUM::zero();
+        }
+        let u = self.normalized();
+        let v = other.normalized();
+        NUM::two() * NUM::atan2(u.sub_vec(&v).len(), u.add_vec(&v).len())
+    }
     fn add_eq_vec(&mut self, other: &VEC) -> &Self {
         self.set(&self.add_vec(other))
     }
@@ -559,6 +624,41 @@ pub mod vector_utils {
     }
 }
 
+pub mod vectors {
+    use crate::linear::vector::Vec3;
+    use crate::numeric::CharMathNumeric;
+
+    /// Orthonormalizes the given vectors in place using modified Gram-Schmidt.
+    /// Earlier vectors keep their direction; later ones are made perpendicular to them.
+    pub fn orthonormalize<N: CharMathNumeric<N>, V: Vec3<N, V>>(vectors: &mut [V]) {
+        for i in 0..vectors.len() {
+            let normalized = vectors[i].normalized();
+            vectors[i].set(&normalized);
+            for j in (i + 1)..vectors.len() {
+                let proj = vectors[i].mul_num(vectors[j].dot(&vectors[i]));
+                vectors[j].sub_eq_vec(&proj);
+            }
+        }
+    }
+    /// Builds an orthonormal basis from a single direction.
+    /// Returns (tangent, bitangent, normal), where normal is the normalized input.
+    pub fn orthonormal_basis_from<N: CharMathNumeric<N>, V: Vec3<N, V>>(normal: &V) -> (V, V, V) {
+        let n = normal.normalized();
+        let abs = |a: N| if a < N::zero() { N::neg(a) } else { a };
+        let (x, y, z) = (abs(n.get_x()), abs(n.get_y()), abs(n.get_z()));
+        let helper = if x <= y && x <= z {
+            V::new(N::one(), N::zero(), N::zero())
+        } else if y <= z {
+            V::new(N::zero(), N::one(), N::zero())
+        } else {
+            V::new(N::zero(), N::zero(), N::one())
+        };
+        let tangent = helper.cross(&n).normalized();
+        let bitangent = n.cross(&tangent);
+        (tangent, bitangent, n)
+    }
+}
+
 pub trait Vec2<N: CharMathNumeric<N>, V: Vec2<N, V>>: Vector<N, V> {
     fn new(x: N, y: N) -> V;
     fn get_x(&self) -> N {
@@ -606,6 +706,27 @@ pub trait Vec3<N: CharMathNumeric<N>, V: Vec3<N, V>>: Vector<N, V> {
     fn as_tuple(&self) -> (N, N, N) {
         (self.get_x(), self.get_y(), self.get_z())
     }
+    /// Rotates by euler angles in radians, giving the same result as multiplying by
+    /// `matrices::rotation_euler(angles)` without building the matrix.
+    fn rotated_euler(&self, angles: &V) -> V {
+        let (sx, cx) = (N::sin(angles.get_x()), N::cos(angles.get_x()));
+        let (sy, cy) = (N::sin(angles.get_y()), N::cos(angles.get_y()));
+        let (sz, cz) = (N::sin(angles.get_z()), N::cos(angles.get_z()));
+        let (x, y, z) = self.as_tuple();
+        let (x, y) = (x * cz - y * sz, x * sz + y * cz);
+        let (x, z) = (x * cy - z * sy, x * sy + z * cy);
+        let (y, z) = (y * cx - z * sx, y * sx + z * cx);
+        V::new(x, y, z)
+    }
+    fn xy<V2: Vec2<N, V2>>(&self) -> V2 {
+        V2::new(self.get_x(), self.get_y())
+    }
+    fn xz<V2: Vec2<N, V2>>(&self) -> V2 {
+        V2::new(self.get_x(), self.get_z())
+    }
+    fn yz<V2: Vec2<N, V2>>(&self) -> V2 {
+        V2::new(self.get_y(), self.get_z())
+    }
 }
 pub trait Vec4<N: CharMathNumeric<N>, V: Vec4<N, V>>: Vector<N, V> {
     fn new(x: N, y: N, z: N, w: N) -> V;
@@ -636,6 +757,34 @@ pub trait Vec4<N: CharMathNumeric<N>, V: Vec4<N, V>>: Vector<N, V> {
     fn as_tuple(&self) -> (N, N, N, N) {
         (self.get_x(), self.get_y(), self.get_z(), self.get_w())
     }
+    fn xy<V2: Vec2<N, V2>>(&self) -> V2 {
+        V2::new(self.get_x(), self.get_y())
+    }
+    fn xz<V2: Vec2<N, V2>>(&self) -> V2 {
+        V2::new(self.get_x(), self.get_z())
+    }
+    fn yz<V2: Vec2<N, V2>>(&self) -> V2 {
+        V2::new(self.get_y(), self.get_z())
+    }
+    fn zw<V2: Vec2<N, V2>>(&self) -> V2 {
+        V2::new(self.get_z(), self.get_w())
+    }
+    fn xyz<V3: Vec3<N, V3>>(&self) -> V3 {
+        V3::new(self.get_x(), self.get_y(), self.get_z())
+    }
+    fn yzw<V3: Vec3<N, V3>>(&self) -> V3 {
+        V3::new(self.get_y(), self.get_z(), self.get_w())
+    }
+    /// Homogeneous divide, returning `(x/w, y/w, z/w)`. When `w` is within epsilon of
+    /// zero the point lies at infinity, and `(x, y, z)` is returned undivided.
+    fn perspective_divide<V3: Vec3<N, V3>>(&self) -> V3 {
+        let w = self.get_w();
+        if w <= N::epsilon() && w >= N::neg(N::epsilon()) {
+            self.xyz()
+        } else {
+            V3::new(self.get_x() / w, self.get_y() / w, self.get_z() / w)
+        }
+    }
 }
 
 define_vec2!(Vec2f32, f32);
@@ -683,3 +832,129 @@ pub type Vec3D = Vec3f64;
 pub type Vec3F = Vec3f32;
 pub type Vec4D = Vec4f64;
 pub type Vec4F = Vec4f32;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamp_limits_each_component() {
+        let v = Vec3f32::new(-2.0, 0.5, 7.0);
+        let min = Vec3f32::new(-1.0, 0.0, 0.0);
+        let max = Vec3f32::new(1.0, 1.0, 5.0);
+        assert_eq!(v.clamp(&min, &max).as_tuple(), (-1.0, 0.5, 5.0));
+    }
+
+    #[test]
+    fn saturate_limits_to_unit_range() {
+        let v = Vec4f32::new(-0.5, 0.25, 1.0, 3.0);
+        assert_eq!(v.saturate().as_tuple(), (0.0, 0.25, 1.0, 1.0));
+    }
+
+    fn assert_near(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn orthonormalize_makes_unit_perpendicular_vectors() {
+        let mut basis = [
+            Vec3f32::new(2.0, 0.0, 0.0),
+            Vec3f32::new(1.0, 3.0, 0.0),
+            Vec3f32::new(1.0, 1.0, 4.0),
+        ];
+        vectors::orthonormalize(&mut basis);
+        for i in 0..3 {
+            assert_near(basis[i].len(), 1.0);
+            for j in (i + 1)..3 {
+                assert_near(basis[i].dot(&basis[j]), 0.0);
+            }
+        }
+        assert_eq!(basis[0].as_tuple(), (1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn orthonormal_basis_from_is_right_handed() {
+        let (t, b, n) = vectors::orthonormal_basis_from(&Vec3f32::new(0.3, -2.0, 0.5));
+        assert_near(t.dot(&b), 0.0);
+        assert_near(t.dot(&n), 0.0);
+        assert_near(t.cross(&b).sub_vec(&n).len(), 0.0);
+    }
+
+    #[test]
+    fn swizzles_pick_the_named_components() {
+        let v3 = Vec3f32::new(1.0, 2.0, 3.0);
+        assert_eq!(v3.xz::<Vec2f32>().as_tuple(), (1.0, 3.0));
+        assert_eq!(v3.yz::<Vec2f32>().as_tuple(), (2.0, 3.0));
+        let v4 = Vec4f32::new(1.0, 2.0, 3.0, 4.0);
+        assert_eq!(v4.xy::<Vec2f32>().as_tuple(), (1.0, 2.0));
+        assert_eq!(v4.zw::<Vec2f32>().as_tuple(), (3.0, 4.0));
+        assert_eq!(v4.xyz::<Vec3f32>().as_tuple(), (1.0, 2.0, 3.0));
+        assert_eq!(v4.yzw::<Vec3f32>().as_tuple(), (2.0, 3.0, 4.0));
+    }
+
+    #[test]
+    fn angle_between_handles_parallel_opposite_and_zero_vectors() {
+        let x = Vec3f32::new(2.0, 0.0, 0.0);
+        assert_near(
+            x.angle_between(&Vec3f32::new(0.0, 0.0, 3.0)),
+            std::f32::consts::FRAC_PI_2,
+        );
+        assert_near(
+            x.angle_between(&Vec3f32::new(-1.0, 0.0, 0.0)),
+            std::f32::consts::PI,
+        );
+        assert_near(x.angle_between(&Vec3f32::new(1.0, 1e-4, 0.0)), 1e-4);
+        assert_eq!(x.angle_between(&Vec3f32::new(0.0, 0.0, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn rotated_euler_matches_the_rotation_matrix() {
+        use crate::linear::matrix::{matrices, Matrix};
+        let angles = Vec3f32::new(0.4, -1.1, 2.3);
+        let rotation = matrices::rotation_euler::<f32, _>(&angles);
+        for v in [Vec3f32::new(1.0, 0.0, 0.0), Vec3f32::new(-2.0, 0.5, 3.0)] {
+            let expected = rotation.mul_row_vec(&Vec4f32::new(v[0], v[1], v[2], 1.0));
+            let (x, y, z) = v.rotated_euler(&angles).as_tuple();
+            assert_near(x, expected[0]);
+            assert_near(y, expected[1]);
+            assert_near(z, expected[2]);
+        }
+    }
+
+    #[test]
+    fn perspective_divide_handles_points_at_infinity() {
+        let p: Vec3f32 = Vec4f32::new(2.0, -4.0, 6.0, 2.0).perspective_divide();
+        assert_eq!(p.as_tuple(), (1.0, -2.0, 3.0));
+        let inf: Vec3f32 = Vec4f32::new(2.0, -4.0, 6.0, 0.0).perspective_divide();
+        assert_eq!(inf.as_tuple(), (2.0, -4.0, 6.0));
+        let near_inf: Vec3f32 =
+            Vec4f32::new(1.0, 1.0, 1.0, -f32::EPSILON / 2.0).perspective_divide();
+        assert_eq!(near_inf.as_tuple(), (1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn vector_lerp_interpolates_each_component() {
+        let a = Vec3f32::new(0.0, 2.0, -4.0);
+        let b = Vec3f32::new(1.0, 4.0, 4.0);
+        assert_eq!(a.interpolate(&b, 0.5).as_tuple(), (0.5, 3.0, 0.0));
+    }
+}
+
+#[cfg(all(test, target_family = "wasm"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn js_bindings_lerp_and_clamp() {
+        let a = Vec3f32::wnew(0.0, 2.0, -4.0);
+        let b = Vec3f32::wnew(10.0, 4.0, 4.0);
+        assert_eq!(a.wlerp(&b, 0.25).as_tuple(), (2.5, 2.5, -2.0));
+        assert_eq!(a.wcomponent_min(&b).as_tuple(), (0.0, 2.0, -4.0));
+        assert_eq!(a.wcomponent_max(&b).as_tuple(), (10.0, 4.0, 4.0));
+        let min = Vec3f32::wnew(1.0, 1.0, 1.0);
+        let max = Vec3f32::wnew(3.0, 3.0, 3.0);
+        assert_eq!(a.wclamp(&min, &max).as_tuple(), (1.0, 2.0, 1.0));
+        assert_eq!(Vec3f32::wnew(-0.5, 0.5, 1.5).wsaturate().as_tuple(), (0.0, 0.5, 1.0));
+    }
+}
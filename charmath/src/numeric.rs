@@ -1,6 +1,6 @@
-use crate::{Algebraic, AlgebraicAssignable};
+use crate::{Algebraic, AlgebraicAssignable, Lerp};
 
-pub trait CharMathNumeric<NUM>: Algebraic<NUM, NUM> + AlgebraicAssignable<NUM> + Copy + std::fmt::Debug + std::cmp::PartialEq {
+pub trait CharMathNumeric<NUM>: Algebraic<NUM, NUM> + AlgebraicAssignable<NUM> + Copy + std::fmt::Debug + std::cmp::PartialEq + std::cmp::PartialOrd {
     fn sqrt(a: NUM) -> NUM;
     fn zero() -> NUM;
     fn half() -> NUM;
@@ -12,6 +12,9 @@ pub trait CharMathNumeric<NUM>: Algebraic<NUM, NUM> + AlgebraicAssignable<NUM> +
     fn cos(a: NUM) -> NUM;
     fn sin(a: NUM) -> NUM;
     fn tan(a: NUM) -> NUM;
+    fn atan2(y: NUM, x: NUM) -> NUM;
+    /// Machine epsilon of the closest float type. Zero for integers.
+    fn epsilon() -> NUM;
 }
 
 #[macro_export]
@@ -42,9 +45,15 @@ macro_rules! charmath_numeric {
             fn tan(a: $NUM) -> $NUM {
                 Self::sin(a) / Self::cos(a)
             }
+            fn atan2(y: $NUM, x: $NUM) -> $NUM {
+                $CLOSEST_FLOAT::atan2(y as $CLOSEST_FLOAT, x as $CLOSEST_FLOAT) as $NUM
+            }
             fn half() -> $NUM {
                 0.5 as $NUM
             }
+            fn epsilon() -> $NUM {
+                $CLOSEST_FLOAT::EPSILON as $NUM
+            }
             fn to_radians(deg: $NUM) -> $NUM {
                 (deg as $CLOSEST_FLOAT * (std::$CLOSEST_FLOAT::consts::PI / 180 as $CLOSEST_FLOAT))
                     as $NUM
@@ -75,3 +84,133 @@ charmath_numeric!(u32, f32, 1);
 charmath_numeric!(u64, f64, 1);
 charmath_numeric!(u128, f64, 1);
 charmath_numeric!(usize, f64, 1);
+
+macro_rules! lerp_float {
+    ($NUM:ident) => {
+        impl Lerp for $NUM {
+            fn interpolate(&self, other: &$NUM, t: f32) -> $NUM {
+                self + (other - self) * t as $NUM
+            }
+        }
+    };
+}
+/// Integers interpolate in `f64` and round to the nearest value.
+macro_rules! lerp_int {
+    ($NUM:ident) => {
+        impl Lerp for $NUM {
+            fn interpolate(&self, other: &$NUM, t: f32) -> $NUM {
+                let (a, b) = (*self as f64, *other as f64);
+                (a + (b - a) * t as f64).round() as $NUM
+            }
+        }
+    };
+}
+
+lerp_float!(f32);
+lerp_float!(f64);
+
+lerp_int!(i8);
+lerp_int!(i16);
+lerp_int!(i32);
+lerp_int!(i64);
+lerp_int!(i128);
+lerp_int!(isize);
+
+lerp_int!(u8);
+lerp_int!(u16);
+lerp_int!(u32);
+lerp_int!(u64);
+lerp_int!(u128);
+lerp_int!(usize);
+
+/// Converts to the nearest IEEE 754 half-precision value, returned as its bit pattern.
+/// Values too large for a half become infinity, values too small become zero and NaN
+/// stays NaN.
+pub fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exp == 0xff {
+        let nan_bit = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan_bit;
+    }
+    let half_exp = exp - 127 + 15;
+    if half_exp >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if half_exp <= 0 {
+        if half_exp < -10 {
+            return sign;
+        }
+        // Subnormal half, shifting in the implicit leading bit
+        let m = mantissa | 0x80_0000;
+        let shift = (14 - half_exp) as u32;
+        let half_m = m >> shift;
+        let rem = m & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        let round_up = rem > halfway || (rem == halfway && half_m & 1 != 0);
+        return sign | (half_m + round_up as u32) as u16;
+    }
+    let half_m = mantissa >> 13;
+    let rem = mantissa & 0x1fff;
+    let round_up = rem > 0x1000 || (rem == 0x1000 && half_m & 1 != 0);
+    // Rounding can carry into the exponent, which is still the correctly rounded value
+    sign | ((((half_exp as u32) << 10) | half_m) + round_up as u32) as u16
+}
+
+/// Expands a half-precision bit pattern to an f32. Every half is exactly representable.
+pub fn f16_to_f32(bits: u16) -> f32 {
+    let sign = ((bits & 0x8000) as u32) << 16;
+    let exp = ((bits >> 10) & 0x1f) as u32;
+    let mantissa = (bits & 0x3ff) as u32;
+    match exp {
+        0 => {
+            let magnitude = mantissa as f32 / (1 << 24) as f32;
+            if sign != 0 {
+                -magnitude
+            } else {
+                magnitude
+            }
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(sign | ((exp + 127 - 15) << 23) | (mantissa << 13)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f16_round_trips_within_half_precision() {
+        for value in [0.0f32, 1.0, -2.5, 0.1, 3.3, 1000.0, 65504.0] {
+            let back = f16_to_f32(f32_to_f16(value));
+            assert!(
+                (back - value).abs() <= value.abs() / 1024.0,
+                "{} -> {}",
+                value,
+                back
+            );
+        }
+        assert_eq!(f32_to_f16(1.0), 0x3c00);
+        assert_eq!(f32_to_f16(-2.0), 0xc000);
+    }
+
+    #[test]
+    fn f16_handles_out_of_range_values() {
+        assert_eq!(f32_to_f16(1e6), 0x7c00);
+        assert_eq!(f16_to_f32(f32_to_f16(f32::NEG_INFINITY)), f32::NEG_INFINITY);
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+        assert_eq!(f32_to_f16(1e-10), 0);
+        // Smallest subnormal half
+        assert_eq!(f16_to_f32(f32_to_f16(5.96e-8)), 2f32.powi(-24));
+    }
+
+    #[test]
+    fn numeric_lerp_interpolates_and_rounds_integers() {
+        assert_eq!(2.0f32.interpolate(&6.0, 0.25), 3.0);
+        assert_eq!(10i32.interpolate(&20, 0.26), 13);
+        assert_eq!(200u8.interpolate(&100, 0.5), 150);
+    }
+}
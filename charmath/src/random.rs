@@ -0,0 +1,156 @@
+#[cfg(target_family = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Small seedable PCG32 generator. Sequences are deterministic for a given seed.
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pcg32 {
+    state: u64,
+    inc: u64,
+}
+// Outside the impl, which wasm_bindgen does not allow consts in
+const PCG_MULTIPLIER: u64 = 6364136223846793005;
+
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+impl Pcg32 {
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(constructor))]
+    pub fn new(seed: u64) -> Pcg32 {
+        let mut ret = Pcg32 {
+            state: 0,
+            inc: (0xda3e39cb94b95bdb << 1) | 1,
+        };
+        ret.next_u32();
+        ret.state = ret.state.wrapping_add(seed);
+        ret.next_u32();
+        ret
+    }
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = nextU32))]
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(PCG_MULTIPLIER).wrapping_add(self.inc);
+        let xor_shifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xor_shifted.rotate_right(rot)
+    }
+    /// Uniform float in `[0, 1)`.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = nextF32))]
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32
+    }
+    /// Uniform float in `[lo, hi)`.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = range))]
+    pub fn range(&mut self, lo: f32, hi: f32) -> f32 {
+        lo + (hi - lo) * self.next_f32()
+    }
+}
+
+/// Seeded 2D lattice noise. Output is in `[-1, 1]` and deterministic for a given seed.
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Noise {
+    seed: u32,
+}
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+impl Noise {
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(constructor))]
+    pub fn new(seed: u32) -> Noise {
+        Noise { seed }
+    }
+    /// Smoothly interpolated random values placed on the integer lattice.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = valueNoise2d))]
+    pub fn value_noise_2d(&self, x: f32, y: f32) -> f32 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (ix, iy) = (x0 as i32, y0 as i32);
+        let (u, v) = (fade(x - x0), fade(y - y0));
+        let value = |cx: i32, cy: i32| {
+            (self.hash(cx, cy) >> 8) as f32 / (1u32 << 23) as f32 - 1.0
+        };
+        let a = lerp(value(ix, iy), value(ix + 1, iy), u);
+        let b = lerp(value(ix, iy + 1), value(ix + 1, iy + 1), u);
+        lerp(a, b, v)
+    }
+    /// Gradient noise with unit gradients, scaled to fill `[-1, 1]`.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = perlin2d))]
+    pub fn perlin_2d(&self, x: f32, y: f32) -> f32 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (ix, iy) = (x0 as i32, y0 as i32);
+        let (fx, fy) = (x - x0, y - y0);
+        let (u, v) = (fade(fx), fade(fy));
+        let grad = |cx: i32, cy: i32, dx: f32, dy: f32| {
+            const D: f32 = std::f32::consts::FRAC_1_SQRT_2;
+            let (gx, gy) = match self.hash(cx, cy) & 7 {
+                0 => (1.0, 0.0),
+                1 => (-1.0, 0.0),
+                2 => (0.0, 1.0),
+                3 => (0.0, -1.0),
+                4 => (D, D),
+                5 => (-D, D),
+                6 => (D, -D),
+                _ => (-D, -D),
+            };
+            gx * dx + gy * dy
+        };
+        let a = lerp(grad(ix, iy, fx, fy), grad(ix + 1, iy, fx - 1.0, fy), u);
+        let b = lerp(
+            grad(ix, iy + 1, fx, fy - 1.0),
+            grad(ix + 1, iy + 1, fx - 1.0, fy - 1.0),
+            u,
+        );
+        (lerp(a, b, v) * std::f32::consts::SQRT_2).clamp(-1.0, 1.0)
+    }
+}
+impl Noise {
+    fn hash(&self, x: i32, y: i32) -> u32 {
+        let mut h = self.seed ^ (x as u32).wrapping_mul(0x27d4eb2d);
+        h ^= (y as u32).wrapping_mul(0x165667b1);
+        h = (h ^ (h >> 15)).wrapping_mul(0x85ebca6b);
+        h = (h ^ (h >> 13)).wrapping_mul(0xc2b2ae35);
+        h ^ (h >> 16)
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pcg32_is_deterministic_per_seed() {
+        let (mut a, mut b, mut c) = (Pcg32::new(42), Pcg32::new(42), Pcg32::new(43));
+        let first: Vec<u32> = (0..8).map(|_| a.next_u32()).collect();
+        assert_eq!(first, (0..8).map(|_| b.next_u32()).collect::<Vec<_>>());
+        assert_ne!(first, (0..8).map(|_| c.next_u32()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn pcg32_floats_stay_in_range() {
+        let mut rng = Pcg32::new(7);
+        for _ in 0..1000 {
+            let f = rng.next_f32();
+            assert!((0.0..1.0).contains(&f));
+            let r = rng.range(-2.0, 3.0);
+            assert!((-2.0..3.0).contains(&r));
+        }
+    }
+
+    #[test]
+    fn noise_is_bounded_and_zero_on_the_perlin_lattice() {
+        let (noise, other) = (Noise::new(1), Noise::new(2));
+        let mut differs = false;
+        for i in 0..200 {
+            let (x, y) = (i as f32 * 0.37 - 20.0, i as f32 * 0.61 - 50.0);
+            assert!((-1.0..=1.0).contains(&noise.value_noise_2d(x, y)));
+            assert!((-1.0..=1.0).contains(&noise.perlin_2d(x, y)));
+            assert_eq!(noise.perlin_2d(x, y), Noise::new(1).perlin_2d(x, y));
+            differs |= noise.value_noise_2d(x, y) != other.value_noise_2d(x, y);
+        }
+        assert!(differs);
+        assert_eq!(noise.perlin_2d(3.0, -4.0), 0.0);
+    }
+}
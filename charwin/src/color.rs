@@ -0,0 +1,131 @@
+use charmath::Lerp;
+#[cfg(target_family = "wasm")]
+use wasm_bindgen::prelude::*;
+
+/// Linear RGBA colour with components in `[0, 1]`.
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+impl Color {
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(constructor))]
+    pub fn new(r: f32, g: f32, b: f32, a: f32) -> Color {
+        Color { r, g, b, a }
+    }
+    /// Opaque colour.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = rgb))]
+    pub fn rgb(r: f32, g: f32, b: f32) -> Color {
+        Color::new(r, g, b, 1.0)
+    }
+    /// Opaque colour from hue in degrees, saturation and value. Hue wraps around 360.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = fromHsv))]
+    pub fn from_hsv(h: f32, s: f32, v: f32) -> Color {
+        let c = v * s;
+        Self::from_chroma(h, c, v - c)
+    }
+    /// Opaque colour from hue in degrees, saturation and lightness. Hue wraps around 360.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = fromHsl))]
+    pub fn from_hsl(h: f32, s: f32, l: f32) -> Color {
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        Self::from_chroma(h, c, l - c / 2.0)
+    }
+}
+impl Color {
+    /// Hue in degrees `[0, 360)`, saturation and value. Greys have a hue and saturation of zero.
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let (max, min) = self.max_min();
+        let s = if max == 0.0 { 0.0 } else { (max - min) / max };
+        (self.hue(max, min), s, max)
+    }
+    /// Hue in degrees `[0, 360)`, saturation and lightness. Greys have a hue and saturation of zero.
+    pub fn to_hsl(&self) -> (f32, f32, f32) {
+        let (max, min) = self.max_min();
+        let l = (max + min) / 2.0;
+        let s = if max == min {
+            0.0
+        } else {
+            (max - min) / (1.0 - (2.0 * l - 1.0).abs())
+        };
+        (self.hue(max, min), s, l)
+    }
+    fn from_chroma(h: f32, c: f32, m: f32) -> Color {
+        let h = h.rem_euclid(360.0) / 60.0;
+        let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as i32 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        Color::rgb(r + m, g + m, b + m)
+    }
+    fn max_min(&self) -> (f32, f32) {
+        (
+            self.r.max(self.g).max(self.b),
+            self.r.min(self.g).min(self.b),
+        )
+    }
+    fn hue(&self, max: f32, min: f32) -> f32 {
+        let d = max - min;
+        let h = if d == 0.0 {
+            0.0
+        } else if max == self.r {
+            ((self.g - self.b) / d).rem_euclid(6.0)
+        } else if max == self.g {
+            (self.b - self.r) / d + 2.0
+        } else {
+            (self.r - self.g) / d + 4.0
+        };
+        h * 60.0
+    }
+}
+impl Lerp for Color {
+    /// Interpolates each channel, including alpha.
+    fn interpolate(&self, other: &Color, t: f32) -> Color {
+        Color::new(
+            self.r.interpolate(&other.r, t),
+            self.g.interpolate(&other.g, t),
+            self.b.interpolate(&other.b, t),
+            self.a.interpolate(&other.a, t),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_color_near(a: Color, b: Color) {
+        let d = [a.r - b.r, a.g - b.g, a.b - b.b, a.a - b.a];
+        assert!(d.iter().all(|d| d.abs() < 1e-5), "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn hsv_and_hsl_match_known_colors() {
+        assert_color_near(Color::from_hsv(0.0, 1.0, 1.0), Color::rgb(1.0, 0.0, 0.0));
+        assert_color_near(Color::from_hsv(120.0, 1.0, 0.5), Color::rgb(0.0, 0.5, 0.0));
+        assert_color_near(Color::from_hsv(-120.0, 1.0, 1.0), Color::rgb(0.0, 0.0, 1.0));
+        assert_color_near(Color::from_hsl(60.0, 1.0, 0.5), Color::rgb(1.0, 1.0, 0.0));
+        assert_color_near(
+            Color::from_hsl(0.0, 0.0, 0.25),
+            Color::rgb(0.25, 0.25, 0.25),
+        );
+    }
+
+    #[test]
+    fn hsv_and_hsl_round_trip() {
+        let color = Color::rgb(0.2, 0.7, 0.4);
+        let (h, s, v) = color.to_hsv();
+        assert_color_near(Color::from_hsv(h, s, v), color);
+        let (h, s, l) = color.to_hsl();
+        assert_color_near(Color::from_hsl(h, s, l), color);
+        assert_eq!(Color::rgb(0.5, 0.5, 0.5).to_hsv(), (0.0, 0.0, 0.5));
+    }
+}
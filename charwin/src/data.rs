@@ -1,14 +1,21 @@
 use crate::char_panic;
-use crate::platform::{Buffer, Program, Shader, Texture2D, VertexArray, Window};
+use crate::error::CharWinError;
+use crate::platform::{dbg_log, Buffer, Program, Shader, Texture2D, VertexArray, Window};
 use crate::window::*;
-use charmath::linear::matrix::{Mat2F, Mat2f32, Mat4F, Mat4f32, MatrixBase};
+use charmath::linear::matrix::{Mat2F, Mat2f32, Mat3f32, Mat4F, Mat4f32, Matrix};
 use charmath::linear::vector::{
-    Vec2, Vec2f32, Vec2i32, Vec3, Vec3f32, Vec3i32, Vec4, Vec4f32, Vec4i32,
+    Vec2, Vec2f32, Vec2i32, Vec3, Vec3f32, Vec3i32, Vec4, Vec4f32, Vec4i32, Vector, VectorBase,
 };
-use image::{ColorType, DynamicImage, GenericImage, GenericImageView};
+use charmath::numeric::{f16_to_f32, f32_to_f16};
+use image::{ColorType, DynamicImage, GenericImage, GenericImageView, ImageFormat};
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::marker::PhantomData;
-use std::mem::size_of;
+use std::mem::{size_of, size_of_val};
 use std::ops::{Index, IndexMut};
+#[cfg(not(target_family = "wasm"))]
+use std::path::{Path, PathBuf};
 
 #[cfg(target_family = "wasm")]
 use wasm_bindgen::prelude::*;
@@ -65,6 +72,39 @@ pub trait VertexBase: Copy + Sized {
     fn get_attribs() -> Vec<VertexAttrib>;
     fn to_f32_array(&self) -> Vec<f32>;
     fn from_f32_array(arr: &[f32]) -> Self;
+    /// Transforms the position by `transform` and any normal by `normal_transform`.
+    ///
+    /// By default the first three floats of `to_f32_array` are transformed as the
+    /// position and everything after them is left unchanged.
+    fn transformed(&self, transform: &Mat4f32, _normal_transform: &Mat4f32) -> Self {
+        let mut arr = self.to_f32_array();
+        let mut pos = [0f32; 3];
+        for (p, a) in pos.iter_mut().zip(arr.iter()) {
+            *p = *a;
+        }
+        let pos = transform_point(transform, &Vec3f32::new(pos[0], pos[1], pos[2]));
+        for (i, a) in arr.iter_mut().take(3).enumerate() {
+            *a = pos[i];
+        }
+        Self::from_f32_array(&arr)
+    }
+    /// Vertex position, with z set to zero for 2D vertices.
+    ///
+    /// By default this is the first three floats of `to_f32_array`.
+    fn position(&self) -> Vec3f32 {
+        let mut pos = [0f32; 3];
+        for (p, a) in pos.iter_mut().zip(self.to_f32_array()) {
+            *p = a;
+        }
+        Vec3f32::new(pos[0], pos[1], pos[2])
+    }
+}
+
+fn transform_point(m: &Mat4f32, p: &Vec3f32) -> Vec3f32 {
+    Vec3f32::new_vec(&m.mul_row_vec(&Vec4f32::new(p[0], p[1], p[2], 1.0)))
+}
+fn transform_normal(m: &Mat4f32, n: &Vec3f32) -> Vec3f32 {
+    Vec3f32::new_vec(&m.mul_row_vec(&Vec4f32::new(n[0], n[1], n[2], 0.0))).normalized()
 }
 
 pub trait TriangleBase: Sized {
@@ -99,6 +139,15 @@ impl<V: VertexBase> TriangleBase for Triangle<V> {
         self.v = *nv;
     }
 }
+impl<V: VertexBase> Triangle<V> {
+    /// Area of the triangle, half the length of the cross product of two edges.
+    pub fn area(&self) -> f32 {
+        let a = self.v[0].position();
+        let ab = self.v[1].position().sub_vec(&a);
+        let ac = self.v[2].position().sub_vec(&a);
+        ab.cross(&ac).len() * 0.5
+    }
+}
 
 #[derive(Debug)]
 #[repr(C)]
@@ -137,6 +186,18 @@ impl<V: VertexBase> TriCPUBuffer<V> {
     pub fn data_ptr(&self) -> *const f32 {
         self.tris.as_ptr() as *const f32
     }
+    pub fn push_triangle(&mut self, tri: Triangle<V>) {
+        self.tris.push(tri);
+    }
+    pub fn extend(&mut self, tris: &[Triangle<V>]) {
+        self.tris.extend_from_slice(tris);
+    }
+    pub fn clear(&mut self) {
+        self.tris.clear();
+    }
+    pub fn as_slice(&self) -> &[Triangle<V>] {
+        &self.tris
+    }
 }
 impl<V: VertexBase> DataBuffer for TriCPUBuffer<V> {
     type Data = Vec<Triangle<V>>;
@@ -208,7 +269,7 @@ impl VertexBase for VertexV2D {
         }
     }
     fn get_attribs() -> Vec<VertexAttrib> {
-        vec![VertexAttrib(0, 2, size_of::<Self>(), 0)]
+        vec![VertexAttrib::new(0, 2, size_of::<Self>(), 0)]
     }
     fn float_size() -> usize {
         2usize
@@ -223,6 +284,15 @@ impl VertexBase for VertexV2D {
         }
         ret
     }
+    fn transformed(&self, transform: &Mat4f32, _: &Mat4f32) -> Self {
+        let v = transform_point(transform, &Vec3f32::new(self.v[0], self.v[1], 0.0));
+        Self {
+            v: Vec2f32::new(v[0], v[1]),
+        }
+    }
+    fn position(&self) -> Vec3f32 {
+        Vec3f32::new(self.v[0], self.v[1], 0.0)
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -237,7 +307,7 @@ impl VertexBase for VertexV {
         }
     }
     fn get_attribs() -> Vec<VertexAttrib> {
-        vec![VertexAttrib(0, 3, size_of::<Self>(), 0)]
+        vec![VertexAttrib::new(0, 3, size_of::<Self>(), 0)]
     }
     fn float_size() -> usize {
         3usize
@@ -252,6 +322,14 @@ impl VertexBase for VertexV {
         }
         ret
     }
+    fn transformed(&self, transform: &Mat4f32, _: &Mat4f32) -> Self {
+        Self {
+            v: transform_point(transform, &self.v),
+        }
+    }
+    fn position(&self) -> Vec3f32 {
+        self.v
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -270,8 +348,8 @@ impl VertexBase for VertexVT {
     fn get_attribs() -> Vec<VertexAttrib> {
         let step = size_of::<Self>();
         vec![
-            VertexAttrib(0, 3, step, 0),
-            VertexAttrib(1, 2, step, size_of::<Vec3f32>()),
+            VertexAttrib::new(0, 3, step, 0),
+            VertexAttrib::new(1, 2, step, size_of::<Vec3f32>()),
         ]
     }
     fn float_size() -> usize {
@@ -290,6 +368,79 @@ impl VertexBase for VertexVT {
         }
         ret
     }
+    fn transformed(&self, transform: &Mat4f32, _: &Mat4f32) -> Self {
+        Self {
+            v: transform_point(transform, &self.v),
+            t: self.t,
+        }
+    }
+    fn position(&self) -> Vec3f32 {
+        self.v
+    }
+}
+
+/// `VertexVT` stored as half floats, taking 12 bytes instead of 20. Halves keep about
+/// three significant digits, so large or finely detailed meshes may visibly lose precision.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct VertexVTHalf {
+    /// Position bits, padded to four halves to keep the texture coordinate aligned.
+    pub v: [u16; 4],
+    pub t: [u16; 2],
+}
+impl VertexVTHalf {
+    pub fn from_vertex(vert: &VertexVT) -> Self {
+        Self {
+            v: [
+                f32_to_f16(vert.v[0]),
+                f32_to_f16(vert.v[1]),
+                f32_to_f16(vert.v[2]),
+                0,
+            ],
+            t: [f32_to_f16(vert.t[0]), f32_to_f16(vert.t[1])],
+        }
+    }
+    pub fn to_vertex(&self) -> VertexVT {
+        VertexVT {
+            v: Vec3f32::new(
+                f16_to_f32(self.v[0]),
+                f16_to_f32(self.v[1]),
+                f16_to_f32(self.v[2]),
+            ),
+            t: Vec2f32::new(f16_to_f32(self.t[0]), f16_to_f32(self.t[1])),
+        }
+    }
+}
+impl VertexBase for VertexVTHalf {
+    fn new() -> Self {
+        Self {
+            v: [0; 4],
+            t: [0; 2],
+        }
+    }
+    fn get_attribs() -> Vec<VertexAttrib> {
+        let step = size_of::<Self>();
+        vec![
+            VertexAttrib::new(0, 3, step, 0).with_type(GlVertexAttribType::HalfFloat, false),
+            VertexAttrib::new(1, 2, step, size_of::<[u16; 4]>())
+                .with_type(GlVertexAttribType::HalfFloat, false),
+        ]
+    }
+    fn float_size() -> usize {
+        5usize
+    }
+    fn to_f32_array(&self) -> Vec<f32> {
+        self.to_vertex().to_f32_array()
+    }
+    fn from_f32_array(arr: &[f32]) -> Self {
+        Self::from_vertex(&VertexVT::from_f32_array(arr))
+    }
+    fn transformed(&self, transform: &Mat4f32, normal_transform: &Mat4f32) -> Self {
+        Self::from_vertex(&self.to_vertex().transformed(transform, normal_transform))
+    }
+    fn position(&self) -> Vec3f32 {
+        self.to_vertex().v
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -310,9 +461,9 @@ impl VertexBase for VertexVTN {
     fn get_attribs() -> Vec<VertexAttrib> {
         let step = size_of::<Self>();
         vec![
-            VertexAttrib(0, 3, step, 0),
-            VertexAttrib(1, 2, step, size_of::<Vec3f32>()),
-            VertexAttrib(2, 3, step, size_of::<Vec3f32>() + size_of::<Vec2f32>()),
+            VertexAttrib::new(0, 3, step, 0),
+            VertexAttrib::new(1, 2, step, size_of::<Vec3f32>()),
+            VertexAttrib::new(2, 3, step, size_of::<Vec3f32>() + size_of::<Vec2f32>()),
         ]
     }
     fn float_size() -> usize {
@@ -336,11 +487,153 @@ impl VertexBase for VertexVTN {
         }
         ret
     }
+    fn transformed(&self, transform: &Mat4f32, normal_transform: &Mat4f32) -> Self {
+        Self {
+            v: transform_point(transform, &self.v),
+            t: self.t,
+            n: transform_normal(normal_transform, &self.n),
+        }
+    }
+    fn position(&self) -> Vec3f32 {
+        self.v
+    }
+}
+
+/// Sources available to `#include "name"` lines in shaders. Virtual files are used
+/// first; on native, other names are read from disk relative to the base directory.
+#[derive(Debug, Clone, Default)]
+pub struct ShaderIncludes {
+    files: HashMap<String, String>,
+    #[cfg(not(target_family = "wasm"))]
+    base_dir: Option<PathBuf>,
+}
+impl ShaderIncludes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn from_files(files: HashMap<String, String>) -> Self {
+        Self {
+            files,
+            ..Self::default()
+        }
+    }
+    #[cfg(not(target_family = "wasm"))]
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            base_dir: Some(dir.as_ref().to_path_buf()),
+            ..Self::default()
+        }
+    }
+    /// Adds a virtual file that can be included as `name`.
+    pub fn add_file(&mut self, name: &str, src: &str) -> &mut Self {
+        self.files.insert(name.to_string(), src.to_string());
+        self
+    }
+    /// Replaces every `#include "name"` line with the named source, recursively.
+    /// Fails if a file cannot be found or includes itself.
+    pub fn resolve(&self, src: &str) -> Result<String, CharWinError> {
+        self.resolve_nested(src, &mut Vec::new())
+    }
+    fn resolve_nested(&self, src: &str, stack: &mut Vec<String>) -> Result<String, CharWinError> {
+        let mut out = String::with_capacity(src.len());
+        for line in src.lines() {
+            match Self::include_name(line) {
+                Some(name) => {
+                    if stack.iter().any(|n| n == name) {
+                        return Err(CharWinError::ShaderInclude(format!(
+                            "Include cycle {} -> {}.",
+                            stack.join(" -> "),
+                            name
+                        )));
+                    }
+                    let included = self.load(name)?;
+                    stack.push(name.to_string());
+                    out.push_str(&self.resolve_nested(&included, stack)?);
+                    stack.pop();
+                }
+                None => {
+                    out.push_str(line);
+                    out.push('\n');
+                }
+            }
+        }
+        Ok(out)
+    }
+    fn include_name(line: &str) -> Option<&str> {
+        let rest = line.trim().strip_prefix("#include")?.trim();
+        rest.strip_prefix('"')?.strip_suffix('"')
+    }
+    #[cfg(not(target_family = "wasm"))]
+    fn load(&self, name: &str) -> Result<String, CharWinError> {
+        if let Some(src) = self.files.get(name) {
+            return Ok(src.clone());
+        }
+        match self.base_dir.as_ref() {
+            Some(dir) => std::fs::read_to_string(dir.join(name))
+                .map_err(|e| CharWinError::ShaderInclude(format!("\"{}\": {}", name, e))),
+            None => Err(CharWinError::ShaderInclude(format!(
+                "\"{}\" not found.",
+                name
+            ))),
+        }
+    }
+    #[cfg(target_family = "wasm")]
+    fn load(&self, name: &str) -> Result<String, CharWinError> {
+        self.files
+            .get(name)
+            .cloned()
+            .ok_or_else(|| CharWinError::ShaderInclude(format!("\"{}\" not found.", name)))
+    }
+}
+
+/// Draw calls and triangles issued through `GPUShader` since the last reset.
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    pub triangles: u32,
+}
+thread_local! {
+    static RENDER_STATS: Cell<RenderStats> = Cell::new(RenderStats::default());
+}
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+impl RenderStats {
+    /// Counters accumulated on this thread since the last reset.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = current))]
+    pub fn current() -> RenderStats {
+        RENDER_STATS.with(|s| s.get())
+    }
+    /// Returns the accumulated counters and zeroes them. Call once per frame.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = reset))]
+    pub fn reset() -> RenderStats {
+        RENDER_STATS.with(|s| s.replace(RenderStats::default()))
+    }
 }
+impl RenderStats {
+    fn record(n_tris: u32) {
+        RENDER_STATS.with(|s| {
+            let mut st = s.get();
+            st.draw_calls += 1;
+            st.triangles += n_tris;
+            s.set(st);
+        });
+    }
+}
+
+/// Prepended to shader sources that do not start with a `#version` directive.
+#[cfg(not(target_family = "wasm"))]
+pub const DEFAULT_GLSL_HEADER: &str = "#version 330 core\n";
+/// Prepended to shader sources that do not start with a `#version` directive.
+#[cfg(target_family = "wasm")]
+pub const DEFAULT_GLSL_HEADER: &str =
+    "#version 300 es\nprecision highp float;\nprecision highp int;\nprecision highp sampler2D;\n";
 
 #[cfg_attr(target_family = "wasm", wasm_bindgen)]
 pub struct GPUShader {
     prog: Program,
+    strict: bool,
+    /// Missing uniforms already warned about in strict mode.
+    warned: RefCell<HashSet<String>>,
 }
 #[cfg_attr(target_family = "wasm", wasm_bindgen)]
 impl GPUShader {
@@ -348,37 +641,47 @@ impl GPUShader {
     pub fn new(w: &Window) -> Self {
         Self {
             prog: Program::new(w),
+            strict: false,
+            warned: RefCell::new(HashSet::new()),
         }
     }
     #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = compile))]
     pub fn compile(&self, w: &Window, v: &str, f: &str) {
-        match (
-            Shader::from_source(w, GlShaderType::Vertex, v),
-            Shader::from_source(w, GlShaderType::Fragment, f),
-        ) {
-            (Ok(vs), Ok(fs)) => {
-                self.prog.attach_shader(&vs);
-                self.prog.attach_shader(&fs);
-                self.prog.link_program();
-                if let Some(err) = self.prog.get_link_status() {
-                    char_panic!("Error linking program: \n{}", err);
-                }
-            }
-            (Err(vs), Err(fs)) => {
-                char_panic!(
-                    "Error compiling vertex and fragment shaders: \n{}\n\n{}",
-                    vs,
-                    fs
-                );
-            }
-            (Err(vs), Ok(_)) => {
-                char_panic!("Error compiling vertex shader: \n{}", vs);
-            }
-            (Ok(_), Err(fs)) => {
-                char_panic!("Error compiling framgnet shader: \n{}", fs);
-            }
+        if let Err(e) = self.try_compile(w, v, f) {
+            char_panic!("{}", e);
         }
     }
+    /// Whether the linked program has an active uniform called `name`. Uniforms the
+    /// compiler optimizes out are reported as missing.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = hasUniform))]
+    pub fn has_uniform(&self, name: &str) -> bool {
+        self.prog.shader_loc(name).exists()
+    }
+    /// When strict, setting a uniform the program does not have logs a warning instead
+    /// of silently doing nothing. Each name is only warned about once.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = setStrict))]
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+    /// Binds vertex attribute `name` to location `index` for the next compile, for
+    /// shaders without explicit `layout(location = N)` qualifiers.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = withAttribLocation))]
+    pub fn with_attrib_location(self, index: u32, name: &str) -> Self {
+        self.prog.bind_attrib_location(index, name);
+        self
+    }
+    /// Location of the active vertex attribute `name` after linking, or -1 if it is
+    /// missing or optimized out.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = attribLocation))]
+    pub fn attrib_location(&self, name: &str) -> i32 {
+        self.prog.attrib_location(name)
+    }
+    /// Reads the uniform block `name` from uniform buffer binding point `binding`, as
+    /// set with `UniformGPUBuffer::bind_base`. Returns false if the block is missing.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = bindUniformBlock))]
+    pub fn bind_uniform_block(&self, name: &str, binding: u32) -> bool {
+        self.prog.uniform_block_binding(name, binding)
+    }
     #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = use))]
     pub fn use_shader(&self) {
         self.prog.bind();
@@ -391,6 +694,20 @@ impl GPUShader {
     pub fn draw_from(&self, start: i32, n_tris: i32) {
         self.prog
             .draw_arrays(GlDrawMode::Triangles, start, n_tris * 3);
+        RenderStats::record(n_tris.max(0) as u32);
+    }
+    /// Draws `count` points, lines or triangles of the given mode from the start of the
+    /// bound vertex array.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = drawPrimitives))]
+    pub fn draw_primitives(&self, mode: GlDrawMode, count: i32) {
+        self.prog.draw_arrays(mode, 0, mode.vertex_count(count));
+        let tris = if mode.is_triangles() { count.max(0) } else { 0 };
+        RenderStats::record(tris as u32);
+    }
+    pub fn draw_indexed(&self, n_indices: i32, index_type: GlIndexType) {
+        self.prog
+            .draw_elements(GlDrawMode::Triangles, n_indices, index_type, 0);
+        RenderStats::record(n_indices.max(0) as u32 / 3);
     }
     #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = fromSources))]
     pub fn from_sources(w: &Window, v: &str, f: &str) -> Self {
@@ -401,60 +718,148 @@ impl GPUShader {
     #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = setVec4f))]
     pub fn set_vec4f(&self, name: &str, vec: &Vec4f32) {
         self.prog
-            .uniform_4f(&self.prog.shader_loc(name), vec.as_tuple());
+            .uniform_4f(&self.uniform_loc(name), vec.as_tuple());
     }
     #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = setVec3f))]
     pub fn set_vec3f(&self, name: &str, vec: &Vec3f32) {
         self.prog
-            .uniform_3f(&self.prog.shader_loc(name), vec.as_tuple());
+            .uniform_3f(&self.uniform_loc(name), vec.as_tuple());
     }
     #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = setVec2f))]
     pub fn set_vec2f(&self, name: &str, vec: &Vec2f32) {
         self.prog
-            .uniform_2f(&self.prog.shader_loc(name), vec.as_tuple());
+            .uniform_2f(&self.uniform_loc(name), vec.as_tuple());
     }
     #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = setFloat))]
     pub fn set_float(&self, name: &str, vec: f32) {
-        self.prog.uniform_1f(&self.prog.shader_loc(name), vec);
+        self.prog.uniform_1f(&self.uniform_loc(name), vec);
     }
     #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = setVec4i))]
     pub fn set_vec4i(&self, name: &str, vec: &Vec4i32) {
         self.prog
-            .uniform_4i(&self.prog.shader_loc(name), vec.as_tuple());
+            .uniform_4i(&self.uniform_loc(name), vec.as_tuple());
     }
     #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = setVec3i))]
     pub fn set_vec3i(&self, name: &str, vec: &Vec3i32) {
         self.prog
-            .uniform_3i(&self.prog.shader_loc(name), vec.as_tuple());
+            .uniform_3i(&self.uniform_loc(name), vec.as_tuple());
     }
     #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = setVec2i))]
     pub fn set_vec2i(&self, name: &str, vec: &Vec2i32) {
         self.prog
-            .uniform_2i(&self.prog.shader_loc(name), vec.as_tuple());
+            .uniform_2i(&self.uniform_loc(name), vec.as_tuple());
     }
     #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = setInt))]
     pub fn set_int(&self, name: &str, vec: i32) {
-        self.prog.uniform_1i(&self.prog.shader_loc(name), vec);
+        self.prog.uniform_1i(&self.uniform_loc(name), vec);
     }
     #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = setMat4f))]
     pub fn set_mat4f32(&self, name: &str, mat: &Mat4f32) {
         self.prog
-            .uniform_mat4f(&self.prog.shader_loc(name), &mat.flatten());
+            .uniform_mat4f(&self.uniform_loc(name), mat.as_slice());
+    }
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = setMat3f))]
+    pub fn set_mat3f32(&self, name: &str, mat: &Mat3f32) {
+        self.prog
+            .uniform_mat3f(&self.uniform_loc(name), mat.as_slice());
     }
     #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = setMat2f))]
     pub fn set_mat2f32(&self, name: &str, mat: &Mat2f32) {
         self.prog
-            .uniform_mat2f(&self.prog.shader_loc(name), &mat.flatten());
+            .uniform_mat2f(&self.uniform_loc(name), mat.as_slice());
     }
 }
 impl GPUShader {
+    /// Binds `tex` to texture unit `unit` and points the sampler uniform `sampler_name`
+    /// at it. The shader must be in use.
+    pub fn bind_texture(&self, unit: u32, tex: &mut GPUTexture, sampler_name: &str) {
+        tex.tex.set_slot(unit);
+        tex.tex.bind();
+        self.set_int(sampler_name, unit as i32);
+    }
+    /// Uniforms the linked program uses. Uniforms the compiler optimizes out are not listed.
+    pub fn active_uniforms(&self) -> Vec<UniformInfo> {
+        self.prog.active_uniforms()
+    }
+    /// Vertex attributes the linked program uses.
+    pub fn active_attributes(&self) -> Vec<UniformInfo> {
+        self.prog.active_attributes()
+    }
+    fn uniform_loc(&self, name: &str) -> <Program as GlProgram>::ShaderLoc {
+        let loc = self.prog.shader_loc(name);
+        if self.strict && !loc.exists() && self.warned.borrow_mut().insert(name.to_string()) {
+            dbg_log(&format!(
+                "Warning: Shader has no active uniform \"{}\".",
+                name
+            ));
+        }
+        loc
+    }
+    /// Prepends `DEFAULT_GLSL_HEADER` when the source has no `#version` directive, so
+    /// the same shader body compiles on native and web. Sources with their own
+    /// directive are left untouched. Compile errors in headed sources report line
+    /// numbers offset by the header length.
+    pub fn preprocess_source(src: &str) -> Cow<'_, str> {
+        if src.trim_start().starts_with("#version") {
+            Cow::Borrowed(src)
+        } else {
+            Cow::Owned(format!("{}{}", DEFAULT_GLSL_HEADER, src))
+        }
+    }
+    pub fn try_compile(&self, w: &Window, v: &str, f: &str) -> Result<(), CharWinError> {
+        match (
+            Shader::from_source(w, GlShaderType::Vertex, &Self::preprocess_source(v)),
+            Shader::from_source(w, GlShaderType::Fragment, &Self::preprocess_source(f)),
+        ) {
+            (Ok(vs), Ok(fs)) => {
+                self.prog.attach_shader(&vs);
+                self.prog.attach_shader(&fs);
+                self.prog.link_program();
+                match self.prog.get_link_status() {
+                    Some(err) => Err(CharWinError::ShaderLink(err)),
+                    None => Ok(()),
+                }
+            }
+            (Err(vs), Err(fs)) => Err(CharWinError::ShaderCompile(format!(
+                "Vertex: \n{}\n\nFragment: \n{}",
+                vs, fs
+            ))),
+            (Err(vs), Ok(_)) => Err(CharWinError::ShaderCompile(format!("Vertex: \n{}", vs))),
+            (Ok(_), Err(fs)) => Err(CharWinError::ShaderCompile(format!("Fragment: \n{}", fs))),
+        }
+    }
+    pub fn try_from_sources(w: &Window, v: &str, f: &str) -> Result<Self, CharWinError> {
+        let ret = Self::new(w);
+        ret.try_compile(w, v, f)?;
+        Ok(ret)
+    }
+    /// Inlines `#include` lines in both sources before compiling them.
+    pub fn try_compile_with_includes(
+        &self,
+        w: &Window,
+        v: &str,
+        f: &str,
+        includes: &ShaderIncludes,
+    ) -> Result<(), CharWinError> {
+        self.try_compile(w, &includes.resolve(v)?, &includes.resolve(f)?)
+    }
+    pub fn try_from_sources_with_includes(
+        w: &Window,
+        v: &str,
+        f: &str,
+        includes: &ShaderIncludes,
+    ) -> Result<Self, CharWinError> {
+        let ret = Self::new(w);
+        ret.try_compile_with_includes(w, v, f, includes)?;
+        Ok(ret)
+    }
     pub fn set_mat4f(&self, name: &str, mat: &Mat4F) {
         self.prog
-            .uniform_mat4f(&self.prog.shader_loc(name), &mat.flatten());
+            .uniform_mat4f(&self.uniform_loc(name), mat.as_slice());
     }
     pub fn set_mat2f(&self, name: &str, mat: &Mat2F) {
         self.prog
-            .uniform_mat2f(&self.prog.shader_loc(name), &mat.flatten());
+            .uniform_mat2f(&self.uniform_loc(name), mat.as_slice());
     }
 }
 
@@ -462,17 +867,13 @@ pub struct TriGPUBuffer<V: VertexBase> {
     pub vbo: Buffer,
     pub vao: VertexArray,
     n_tris: i32,
+    usage: GlStorageMode,
     phantom: PhantomData<V>,
 }
 impl<V: VertexBase> GPUBuffer for TriGPUBuffer<V> {
     type CPUType = TriCPUBuffer<V>;
     fn new(win: &mut Window) -> Self {
-        Self {
-            vbo: Buffer::new(win, GlBufferType::ArrayBuffer),
-            vao: VertexArray::new(win),
-            n_tris: 0,
-            phantom: PhantomData,
-        }
+        Self::new_with_usage(win, GlStorageMode::Static)
     }
 }
 impl<V: VertexBase> DataBuffer for TriGPUBuffer<V> {
@@ -488,25 +889,15 @@ impl<V: VertexBase> DataBuffer for TriGPUBuffer<V> {
         self.vbo.buffer_data(
             data.len() * size_of::<Triangle<V>>(),
             data.as_ptr() as *const f32,
-            GlStorageMode::Static,
+            self.usage,
         );
         self.vbo.unbind();
         self.vao.unbind();
     }
     fn sub_data(&mut self, start: usize, len: usize, data: &Self::Data) {
-        if data.len() < len {
-            char_panic!("TriGPUBuffer.sub_data: Input data size too small.");
+        if let Err(e) = self.try_sub_data(start, len, data) {
+            char_panic!("TriGPUBuffer.sub_data: {}", e);
         }
-        if start + len > self.n_tris as usize * V::float_size() * 3 {
-            char_panic!("TriGPUBuffer.sub_data: Input range will not fit inside butter.");
-        }
-        self.vbo.bind();
-        self.vbo.buffer_sub_data(
-            start * size_of::<V>() * 3,
-            len * size_of::<V>() * 3,
-            data.as_ptr() as *const f32,
-        );
-        self.vbo.unbind();
     }
     fn get_sub_data(&self, start: usize, len: usize) -> Self::Data {
         let mut recv = Self::Data::with_capacity(len);
@@ -531,9 +922,432 @@ impl<V: VertexBase> DataBuffer for TriGPUBuffer<V> {
     }
 }
 impl<V: VertexBase> TriGPUBuffer<V> {
+    /// Creates an empty buffer whose uploads are all hinted with `usage`.
+    pub fn new_with_usage(win: &mut Window, usage: GlStorageMode) -> Self {
+        Self {
+            vbo: Buffer::new(win, GlBufferType::ArrayBuffer),
+            vao: VertexArray::new(win),
+            n_tris: 0,
+            usage,
+            phantom: PhantomData,
+        }
+    }
+    pub fn usage(&self) -> GlStorageMode {
+        self.usage
+    }
     pub fn n_tris(&self) -> i32 {
         self.n_tris
     }
+    /// Overwrites `len` triangles starting at triangle `start`.
+    pub fn try_sub_data(
+        &mut self,
+        start: usize,
+        len: usize,
+        data: &[Triangle<V>],
+    ) -> Result<(), CharWinError> {
+        Self::check_sub_data_range(start, len, data.len(), self.n_tris as usize)?;
+        self.vbo.bind();
+        self.vbo.buffer_sub_data(
+            start * size_of::<Triangle<V>>(),
+            len * size_of::<Triangle<V>>(),
+            data.as_ptr() as *const f32,
+        );
+        self.vbo.unbind();
+        Ok(())
+    }
+    /// Checks that `data_len` triangles hold `len`, and that `start..start + len` fits in a
+    /// buffer of `n_tris`.
+    fn check_sub_data_range(
+        start: usize,
+        len: usize,
+        data_len: usize,
+        n_tris: usize,
+    ) -> Result<(), CharWinError> {
+        if data_len < len {
+            return Err(CharWinError::BufferRange {
+                start: 0,
+                len,
+                size: data_len,
+            });
+        }
+        if start + len > n_tris {
+            return Err(CharWinError::BufferRange {
+                start,
+                len,
+                size: n_tris,
+            });
+        }
+        Ok(())
+    }
+    /// Replaces the buffer contents for data that is rewritten every frame.
+    ///
+    /// The old storage is orphaned and reallocated before uploading, so the driver does
+    /// not need to wait for draws still reading the previous contents. Static buffers are
+    /// reallocated as dynamic; other buffers keep their usage.
+    pub fn stream_data(&mut self, data: &[Triangle<V>]) {
+        let size = size_of_val(data);
+        self.n_tris = data.len() as i32;
+        self.vao.bind();
+        self.vbo.bind();
+        for attrib in V::get_attribs() {
+            self.vao.attrib_ptr(&attrib);
+        }
+        let usage = match self.usage {
+            GlStorageMode::Static => GlStorageMode::Dynamic,
+            usage => usage,
+        };
+        self.vbo.orphan_data(size, usage);
+        self.vbo
+            .buffer_sub_data(0, size, data.as_ptr() as *const f32);
+        self.vbo.unbind();
+        self.vao.unbind();
+    }
+}
+
+pub trait IndexBase: Copy + Sized {
+    fn gl_index_type() -> GlIndexType;
+    fn from_usize(i: usize) -> Self;
+    /// Number of distinct vertices this index type can address.
+    fn max_vertices() -> usize;
+}
+impl IndexBase for u16 {
+    fn gl_index_type() -> GlIndexType {
+        GlIndexType::UnsignedShort
+    }
+    fn from_usize(i: usize) -> Self {
+        i as u16
+    }
+    fn max_vertices() -> usize {
+        u16::MAX as usize + 1
+    }
+}
+impl IndexBase for u32 {
+    fn gl_index_type() -> GlIndexType {
+        GlIndexType::UnsignedInt
+    }
+    fn from_usize(i: usize) -> Self {
+        i as u32
+    }
+    fn max_vertices() -> usize {
+        // usize is only 32 bits on wasm32, where every representable count fits
+        usize::try_from(u32::MAX).map_or(usize::MAX, |max| max.saturating_add(1))
+    }
+}
+
+/// The smallest index type able to address `n_verts` distinct vertices.
+pub fn smallest_index_type(n_verts: usize) -> GlIndexType {
+    if n_verts <= u16::max_vertices() {
+        u16::gl_index_type()
+    } else {
+        u32::gl_index_type()
+    }
+}
+
+/// Hashable key for a vertex with every component rounded to the nearest multiple of
+/// `epsilon`, so vertices that differ only by float noise can be merged.
+///
+/// The grid is fixed, so two values closer than `epsilon` can still fall either side
+/// of a cell boundary and get different keys. A larger `epsilon` merges more of these
+/// near-duplicates but can also merge vertices meant to stay apart, such as the two
+/// sides of a UV seam.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QuantizedVertex(Vec<i64>);
+impl QuantizedVertex {
+    /// Fine enough to keep distinct positions and UVs of typical meshes apart.
+    pub const DEFAULT_EPSILON: f32 = 1e-5;
+
+    pub fn new<V: VertexBase>(vert: &V, epsilon: f32) -> Self {
+        if epsilon.is_nan() || epsilon <= 0.0 {
+            char_panic!("QuantizedVertex epsilon must be positive, got {}", epsilon);
+        }
+        Self(
+            vert.to_f32_array()
+                .iter()
+                .map(|f| (f / epsilon).round() as i64)
+                .collect(),
+        )
+    }
+}
+
+/// Vertex buffer drawn through an element array buffer of `u16` or `u32` indices.
+pub struct IndexedTriGPUBuffer<V: VertexBase> {
+    pub vbo: Buffer,
+    pub ebo: Buffer,
+    pub vao: VertexArray,
+    n_verts: usize,
+    n_indices: i32,
+    index_type: GlIndexType,
+    usage: GlStorageMode,
+    phantom: PhantomData<V>,
+}
+impl<V: VertexBase> IndexedTriGPUBuffer<V> {
+    pub fn new(win: &mut Window) -> Self {
+        Self::new_with_usage(win, GlStorageMode::Static)
+    }
+    /// Creates an empty buffer whose vertex and index uploads are hinted with `usage`.
+    pub fn new_with_usage(win: &mut Window, usage: GlStorageMode) -> Self {
+        Self {
+            vbo: Buffer::new(win, GlBufferType::ArrayBuffer),
+            ebo: Buffer::new(win, GlBufferType::ElementArrayBuffer),
+            vao: VertexArray::new(win),
+            n_verts: 0,
+            n_indices: 0,
+            index_type: GlIndexType::UnsignedInt,
+            usage,
+            phantom: PhantomData,
+        }
+    }
+    pub fn from_data<I: IndexBase>(win: &mut Window, verts: &[V], indices: &[I]) -> Self {
+        let mut ret = Self::new(win);
+        ret.set_data(verts, indices);
+        ret
+    }
+    /// Uploads the data using `u16` indices when every vertex can be addressed by one,
+    /// falling back to `u32` otherwise.
+    pub fn from_data_smallest(win: &mut Window, verts: &[V], indices: &[u32]) -> Self {
+        let mut ret = Self::new(win);
+        ret.set_data_smallest(verts, indices);
+        ret
+    }
+    pub fn set_data<I: IndexBase>(&mut self, verts: &[V], indices: &[I]) {
+        if verts.len() > I::max_vertices() {
+            char_panic!(
+                "IndexedTriGPUBuffer.set_data: {} vertices cannot be addressed by {:?} indices.",
+                verts.len(),
+                I::gl_index_type()
+            );
+        }
+        self.n_verts = verts.len();
+        self.n_indices = indices.len() as i32;
+        self.index_type = I::gl_index_type();
+        // Pad to a whole number of floats so the upload size is a multiple of 4 bytes
+        let index_bytes = size_of_val(indices);
+        let mut index_data = indices.to_vec();
+        if index_bytes % size_of::<f32>() != 0 {
+            index_data.push(I::from_usize(0));
+        }
+        self.vao.bind();
+        self.vbo.bind();
+        for attrib in V::get_attribs() {
+            self.vao.attrib_ptr(&attrib);
+        }
+        self.vbo.buffer_data(
+            size_of_val(verts),
+            verts.as_ptr() as *const f32,
+            self.usage,
+        );
+        self.ebo.bind();
+        self.ebo.buffer_data(
+            index_data.len() * size_of::<I>(),
+            index_data.as_ptr() as *const f32,
+            self.usage,
+        );
+        self.vao.unbind();
+        self.vbo.unbind();
+        self.ebo.unbind();
+    }
+    pub fn set_data_smallest(&mut self, verts: &[V], indices: &[u32]) {
+        if smallest_index_type(verts.len()) == GlIndexType::UnsignedShort {
+            let short_indices: Vec<u16> = indices.iter().map(|i| *i as u16).collect();
+            self.set_data(verts, &short_indices);
+        } else {
+            self.set_data(verts, indices);
+        }
+    }
+    pub fn n_verts(&self) -> usize {
+        self.n_verts
+    }
+    pub fn n_indices(&self) -> i32 {
+        self.n_indices
+    }
+    pub fn n_tris(&self) -> i32 {
+        self.n_indices / 3
+    }
+    pub fn index_type(&self) -> GlIndexType {
+        self.index_type
+    }
+    pub fn usage(&self) -> GlStorageMode {
+        self.usage
+    }
+    pub fn draw(&self, shader: &GPUShader) {
+        self.vao.bind();
+        shader.draw_indexed(self.n_indices, self.index_type);
+        self.vao.unbind();
+    }
+}
+
+/// A buffer of `u32` atomic counters for `layout(binding = n) uniform atomic_uint` in
+/// shaders. Atomic counters are not available in WebGL2.
+#[cfg(not(target_family = "wasm"))]
+pub struct AtomicCounterGPUBuffer {
+    pub buff: Buffer,
+    n_counters: usize,
+}
+#[cfg(not(target_family = "wasm"))]
+impl AtomicCounterGPUBuffer {
+    pub fn new(win: &Window, n_counters: usize) -> Self {
+        let ret = Self {
+            buff: Buffer::new(win, GlBufferType::AtomicCounterBuffer),
+            n_counters,
+        };
+        ret.reset();
+        ret
+    }
+    /// Binds the counters to the atomic counter binding point `index`.
+    pub fn bind_base(&self, index: u32) {
+        self.buff.bind_base(index);
+    }
+    /// Sets every counter to zero.
+    pub fn reset(&self) {
+        let zeroes = vec![0u32; self.n_counters];
+        self.buff.bind();
+        self.buff.buffer_data(
+            self.n_counters * size_of::<u32>(),
+            zeroes.as_ptr() as *const f32,
+            GlStorageMode::Dynamic,
+        );
+        self.buff.unbind();
+    }
+    pub fn get_values(&self) -> Vec<u32> {
+        let mut ret = vec![0u32; self.n_counters];
+        self.buff.bind();
+        self.buff.get_buffer_sub_data(
+            0,
+            self.n_counters * size_of::<u32>(),
+            ret.as_mut_ptr() as *mut f32,
+        );
+        self.buff.unbind();
+        ret
+    }
+    pub fn n_counters(&self) -> usize {
+        self.n_counters
+    }
+}
+
+/// A uniform buffer object backing a `layout(std140) uniform` block, which lets a group
+/// of uniforms be updated with a single buffer write.
+pub struct UniformGPUBuffer {
+    pub buff: Buffer,
+    size: usize,
+}
+impl UniformGPUBuffer {
+    /// Allocates `size` bytes of uninitialized storage.
+    pub fn new(win: &Window, size: usize) -> Self {
+        let buff = Buffer::new(win, GlBufferType::UniformBuffer);
+        buff.bind();
+        buff.orphan_data(size, GlStorageMode::Dynamic);
+        buff.unbind();
+        Self { buff, size }
+    }
+    /// Writes `data` starting `offset` bytes into the buffer.
+    pub fn sub_data(&self, offset: usize, data: &[f32]) {
+        let bytes = size_of_val(data);
+        if offset + bytes > self.size {
+            char_panic!(
+                "UniformGPUBuffer.sub_data: writing {} bytes at {} overflows the {} byte buffer.",
+                bytes,
+                offset,
+                self.size
+            );
+        }
+        self.buff.bind();
+        self.buff.buffer_sub_data(offset, bytes, data.as_ptr());
+        self.buff.unbind();
+    }
+    pub fn get_data(&self) -> Vec<f32> {
+        self.buff.read_typed::<f32>()
+    }
+    /// Binds the buffer to uniform buffer binding point `index`.
+    pub fn bind_base(&self, index: u32) {
+        self.buff.bind_base(index);
+    }
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+/// Dynamic vertex buffers shared by immediate-mode draws and reused every frame.
+///
+/// Writes are packed one after another into the current buffer. When the next write
+/// does not fit, the pool moves on to the next buffer, allocating one sized to the
+/// write if it runs out. `begin_frame` rewinds to the first buffer and orphans the used
+/// storage, so new writes do not wait on draws from the previous frame.
+pub struct TransientBufferPool {
+    buffers: Vec<Buffer>,
+    /// Size of each buffer in bytes.
+    capacities: Vec<usize>,
+    current: usize,
+    offset: usize,
+}
+impl TransientBufferPool {
+    /// Smallest buffer the pool allocates, in bytes.
+    pub const MIN_BUFFER_SIZE: usize = 64 * 1024;
+
+    pub fn new() -> Self {
+        Self {
+            buffers: Vec::new(),
+            capacities: Vec::new(),
+            current: 0,
+            offset: 0,
+        }
+    }
+    /// Makes every buffer available again. Handles from earlier writes must not be
+    /// drawn from after this.
+    pub fn begin_frame(&mut self) {
+        let used = (self.current + 1).min(self.buffers.len());
+        for (buff, capacity) in self.buffers[..used].iter().zip(self.capacities.iter()) {
+            buff.bind();
+            buff.orphan_data(*capacity, GlStorageMode::Dynamic);
+            buff.unbind();
+        }
+        self.current = 0;
+        self.offset = 0;
+    }
+    /// Uploads `data` and returns the buffer it was written to along with its offset
+    /// in bytes.
+    pub fn write(&mut self, win: &Window, data: &[f32]) -> (&Buffer, usize) {
+        let bytes = size_of_val(data);
+        (self.current, self.offset) =
+            Self::place(&self.capacities, self.current, self.offset, bytes);
+        if self.current == self.buffers.len() {
+            let capacity = Self::new_capacity(bytes);
+            let buff = Buffer::new(win, GlBufferType::ArrayBuffer);
+            buff.bind();
+            buff.orphan_data(capacity, GlStorageMode::Dynamic);
+            buff.unbind();
+            self.buffers.push(buff);
+            self.capacities.push(capacity);
+        }
+        let offset = self.offset;
+        let buff = &self.buffers[self.current];
+        buff.bind();
+        buff.buffer_sub_data(offset, bytes, data.as_ptr());
+        buff.unbind();
+        self.offset += bytes;
+        (buff, offset)
+    }
+    /// Number of buffers allocated so far.
+    pub fn n_buffers(&self) -> usize {
+        self.buffers.len()
+    }
+    /// Buffer index and byte offset for a write of `bytes`, continuing from `offset` in
+    /// buffer `current`. An index of `capacities.len()` means a new buffer is needed.
+    fn place(capacities: &[usize], current: usize, offset: usize, bytes: usize) -> (usize, usize) {
+        let (mut current, mut offset) = (current, offset);
+        while current < capacities.len() && offset + bytes > capacities[current] {
+            current += 1;
+            offset = 0;
+        }
+        (current, offset)
+    }
+    fn new_capacity(bytes: usize) -> usize {
+        bytes.next_power_of_two().max(Self::MIN_BUFFER_SIZE)
+    }
+}
+impl Default for TransientBufferPool {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl DataBuffer for DynamicImage {
@@ -567,6 +1381,35 @@ impl CPUBuffer for DynamicImage {
         Self::new_rgba8(0, 0)
     }
 }
+/// How a decoded image is prepared before it is uploaded as a texture.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TextureLoadOptions {
+    /// Mip level to upload, as passed to [`GPUTexture::set_data_mips`].
+    pub mips: Option<u32>,
+    /// Flips the rows, so images stored top row first appear upright with OpenGL's
+    /// bottom-left texture origin.
+    pub flip_y: bool,
+    /// Multiplies colour by alpha, see [`premultiply_alpha`].
+    pub premultiply_alpha: bool,
+}
+impl TextureLoadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Applies `flip_y` and `premultiply_alpha` to a decoded image.
+    pub fn prepare(&self, image: DynamicImage) -> DynamicImage {
+        let image = if self.flip_y { image.flipv() } else { image };
+        if self.premultiply_alpha {
+            premultiply_alpha(image)
+        } else {
+            image
+        }
+    }
+}
+/// Colour of the 1x1 texture `load_texture_rgba` returns while the image loads.
+pub const DEFAULT_PLACEHOLDER_COLOR: [u8; 4] = [0xff, 0x80, 0xff, 0xff];
+/// Fully transparent placeholder, for textures that should not show until loaded.
+pub const TRANSPARENT_PLACEHOLDER_COLOR: [u8; 4] = [0, 0, 0, 0];
 pub trait DynamicImageColorable {
     fn solid_color(col: [u8; 4]) -> Self;
 }
@@ -634,6 +1477,66 @@ impl GlImageFormatConvertable for DynamicImage {
     }
 }
 
+/// Decodes an encoded image, guessing the format from its header when no hint is given.
+pub fn image_from_bytes(
+    bytes: &[u8],
+    format_hint: Option<ImageFormat>,
+) -> Result<DynamicImage, CharWinError> {
+    match format_hint {
+        Some(format) => image::load_from_memory_with_format(bytes, format),
+        None => image::load_from_memory(bytes),
+    }
+    .map_err(|e| CharWinError::TextureLoad(format!("{:?}", e)))
+}
+
+/// Multiplies the colour channels of each pixel by its alpha. Images without an alpha
+/// channel are returned unchanged.
+///
+/// Premultiplied textures should be blended with `(One, OneMinusSrcAlpha)` instead of
+/// `(SrcAlpha, OneMinusSrcAlpha)`, otherwise the colour is scaled by alpha twice.
+pub fn premultiply_alpha(image: DynamicImage) -> DynamicImage {
+    use DynamicImage::*;
+    match image {
+        ImageLumaA8(mut img) => {
+            premultiply_u8(&mut img, 2);
+            ImageLumaA8(img)
+        }
+        ImageRgba8(mut img) => {
+            premultiply_u8(&mut img, 4);
+            ImageRgba8(img)
+        }
+        ImageBgra8(mut img) => {
+            premultiply_u8(&mut img, 4);
+            ImageBgra8(img)
+        }
+        ImageLumaA16(mut img) => {
+            premultiply_u16(&mut img, 2);
+            ImageLumaA16(img)
+        }
+        ImageRgba16(mut img) => {
+            premultiply_u16(&mut img, 4);
+            ImageRgba16(img)
+        }
+        img => img,
+    }
+}
+fn premultiply_u8(data: &mut [u8], channels: usize) {
+    for px in data.chunks_exact_mut(channels) {
+        let a = px[channels - 1] as u32;
+        for c in &mut px[..channels - 1] {
+            *c = ((*c as u32 * a + 127) / 255) as u8;
+        }
+    }
+}
+fn premultiply_u16(data: &mut [u16], channels: usize) {
+    for px in data.chunks_exact_mut(channels) {
+        let a = px[channels - 1] as u64;
+        for c in &mut px[..channels - 1] {
+            *c = ((*c as u64 * a + 32767) / 65535) as u16;
+        }
+    }
+}
+
 pub struct GPUTexture {
     pub tex: Texture2D,
     pub size: (u32, u32),
@@ -666,6 +1569,39 @@ impl GPUTexture {
         );
         self.tex.unbind();
     }
+    /// Restricts sampling to mip levels `base..=max`, e.g. while higher levels stream in.
+    pub fn set_mip_range(&mut self, base: u32, max: u32) {
+        self.tex.bind();
+        self.tex.set_mip_range(base, max);
+        self.tex.unbind();
+    }
+    /// Biases mip selection; positive values pick blurrier levels. Ignored on WebGL.
+    pub fn set_lod_bias(&mut self, bias: f32) {
+        self.tex.bind();
+        self.tex.set_lod_bias(bias);
+        self.tex.unbind();
+    }
+    /// Changes the filters of a loaded texture, e.g. `Nearest` for a pixelated zoom.
+    /// The min filter replaces mipmapped sampling, so any mip levels go unused until
+    /// `set_data_mips` uploads and regenerates them, which also restores the default
+    /// mipmapped filter.
+    pub fn set_filter(&mut self, min: GlTextureFilter, mag: GlTextureFilter) {
+        self.tex.bind();
+        self.tex.set_filter(min, mag);
+        self.tex.unbind();
+    }
+    /// Changes the wrap mode along each axis of a loaded texture.
+    pub fn set_wrap(&mut self, s: GlTextureWrap, t: GlTextureWrap) {
+        self.tex.bind();
+        self.tex.set_wrap(s, t);
+        self.tex.unbind();
+    }
+    /// Binds the texture to image `unit` so compute shaders can `imageLoad` and
+    /// `imageStore` it. `format` must match the layout qualifier in the shader.
+    #[cfg(not(target_family = "wasm"))]
+    pub fn bind_image(&self, unit: u32, access: GlImageAccess, format: GlInternalTextureFormat) {
+        self.tex.bind_image(unit, access, format);
+    }
 }
 impl DataBuffer for GPUTexture {
     type Data = DynamicImage;
@@ -719,3 +1655,291 @@ impl WebGlTriGPUBufferVertexV {
         self.buff.n_tris()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use charmath::linear::matrix::matrices;
+
+    /// Vertex relying on every provided `VertexBase` method.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct ColoredVertex {
+        pos: [f32; 3],
+        color: [f32; 4],
+    }
+    impl VertexBase for ColoredVertex {
+        fn new() -> Self {
+            Self {
+                pos: [0.0; 3],
+                color: [0.0; 4],
+            }
+        }
+        fn float_size() -> usize {
+            7
+        }
+        fn get_attribs() -> Vec<VertexAttrib> {
+            vec![
+                VertexAttrib::new(0, 3, size_of::<Self>(), 0),
+                VertexAttrib::new(1, 4, size_of::<Self>(), 3 * size_of::<f32>()),
+            ]
+        }
+        fn to_f32_array(&self) -> Vec<f32> {
+            self.pos.iter().chain(self.color.iter()).copied().collect()
+        }
+        fn from_f32_array(arr: &[f32]) -> Self {
+            let mut ret = Self::new();
+            ret.pos.copy_from_slice(&arr[..3]);
+            ret.color.copy_from_slice(&arr[3..7]);
+            ret
+        }
+    }
+
+    #[test]
+    fn default_transformed_moves_only_the_position() {
+        let vert = ColoredVertex {
+            pos: [1.0, 2.0, 3.0],
+            color: [0.1, 0.2, 0.3, 1.0],
+        };
+        let transform =
+            Mat4f32::from_matrix(&matrices::translation_3d(&Vec3f32::new(1.0, -2.0, 0.5)));
+        let moved = vert.transformed(&transform, &Mat4f32::from_matrix(&matrices::identity(4)));
+        assert_eq!(moved.pos, [2.0, 0.0, 3.5]);
+        assert_eq!(moved.color, vert.color);
+    }
+
+    #[test]
+    fn default_position_reads_the_first_three_floats() {
+        let vert = ColoredVertex {
+            pos: [1.0, 2.0, 3.0],
+            color: [0.1, 0.2, 0.3, 1.0],
+        };
+        assert_eq!(vert.position().as_tuple(), (1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn quantized_vertex_merges_within_a_cell() {
+        let a = VertexV {
+            v: Vec3f32::new(1.0, 2.0, 3.0),
+        };
+        let b = VertexV {
+            v: Vec3f32::new(1.0 + 1e-7, 2.0, 3.0 - 1e-7),
+        };
+        let c = VertexV {
+            v: Vec3f32::new(1.001, 2.0, 3.0),
+        };
+        let eps = QuantizedVertex::DEFAULT_EPSILON;
+        assert_eq!(QuantizedVertex::new(&a, eps), QuantizedVertex::new(&b, eps));
+        assert_ne!(QuantizedVertex::new(&a, eps), QuantizedVertex::new(&c, eps));
+        assert_eq!(
+            QuantizedVertex::new(&a, 0.01),
+            QuantizedVertex::new(&c, 0.01)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "epsilon must be positive")]
+    fn quantized_vertex_rejects_nan_epsilon() {
+        QuantizedVertex::new(&VertexV::new(), f32::NAN);
+    }
+
+    #[test]
+    fn builtin_2d_position_has_zero_z() {
+        let vert = VertexV2D {
+            v: Vec2f32::new(4.0, -1.0),
+        };
+        assert_eq!(vert.position().as_tuple(), (4.0, -1.0, 0.0));
+    }
+
+    #[test]
+    fn smallest_index_type_switches_past_u16() {
+        assert_eq!(smallest_index_type(3), GlIndexType::UnsignedShort);
+        assert_eq!(smallest_index_type(65536), GlIndexType::UnsignedShort);
+        assert_eq!(smallest_index_type(65537), GlIndexType::UnsignedInt);
+        assert!(u32::max_vertices() > u16::max_vertices());
+    }
+
+    #[test]
+    fn premultiplied_half_transparent_white_is_half_grey() {
+        let image = DynamicImage::solid_color([255, 255, 255, 128]);
+        let options = TextureLoadOptions {
+            premultiply_alpha: true,
+            ..TextureLoadOptions::new()
+        };
+        let image = options.prepare(image);
+        assert_eq!(
+            image.as_rgba8().unwrap().as_raw(),
+            &vec![128, 128, 128, 128]
+        );
+        let unchanged =
+            TextureLoadOptions::new().prepare(DynamicImage::solid_color([255, 255, 255, 128]));
+        assert_eq!(
+            unchanged.as_rgba8().unwrap().as_raw(),
+            &vec![255, 255, 255, 128]
+        );
+    }
+
+    #[test]
+    fn flip_y_reverses_the_row_order() {
+        let mut image = DynamicImage::new_rgba8(2, 2);
+        let rgba = image.as_mut_rgba8().unwrap();
+        for x in 0..2 {
+            rgba.put_pixel(x, 0, image::Rgba([255, 0, 0, 255]));
+            rgba.put_pixel(x, 1, image::Rgba([0, 0, 255, 255]));
+        }
+        let flipped = TextureLoadOptions {
+            flip_y: true,
+            ..TextureLoadOptions::new()
+        }
+        .prepare(image.clone());
+        let top_row = |image: &DynamicImage| image.as_rgba8().unwrap().get_pixel(0, 0).0;
+        assert_eq!(top_row(&flipped), [0, 0, 255, 255]);
+        assert_eq!(
+            flipped.as_rgba8().unwrap().get_pixel(1, 1).0,
+            [255, 0, 0, 255]
+        );
+        assert_eq!(
+            top_row(&TextureLoadOptions::new().prepare(image)),
+            [255, 0, 0, 255]
+        );
+    }
+
+    #[test]
+    fn render_stats_accumulate_until_reset() {
+        RenderStats::reset();
+        RenderStats::record(12);
+        RenderStats::record(2);
+        let expected = RenderStats {
+            draw_calls: 2,
+            triangles: 14,
+        };
+        assert_eq!(RenderStats::current(), expected);
+        assert_eq!(RenderStats::reset(), expected);
+        assert_eq!(RenderStats::current(), RenderStats::default());
+        // Counters are kept per thread
+        std::thread::spawn(|| RenderStats::record(5))
+            .join()
+            .unwrap();
+        assert_eq!(RenderStats::current(), RenderStats::default());
+    }
+
+    #[test]
+    fn transient_pool_places_writes_in_the_first_buffer_with_room() {
+        let min = TransientBufferPool::MIN_BUFFER_SIZE;
+        let place = TransientBufferPool::place;
+        // No buffers yet, so the first write needs one
+        assert_eq!(place(&[], 0, 0, 100), (0, 0));
+        assert_eq!(place(&[min], 0, 100, 100), (0, 100));
+        assert_eq!(place(&[min], 0, min - 100, 100), (0, min - 100));
+        // A full buffer moves on, reusing the next buffer before allocating
+        assert_eq!(place(&[min, min], 0, min - 50, 100), (1, 0));
+        assert_eq!(place(&[min, min], 1, min - 50, 100), (2, 0));
+        // Writes that fit no existing buffer skip to a new one
+        assert_eq!(place(&[min, min], 0, 0, min + 1), (2, 0));
+        assert_eq!(TransientBufferPool::new_capacity(100), min);
+        assert_eq!(TransientBufferPool::new_capacity(min + 1), 2 * min);
+    }
+
+    #[test]
+    fn sub_data_ranges_outside_the_buffer_are_errors() {
+        let check = TriGPUBuffer::<ColoredVertex>::check_sub_data_range;
+        assert_eq!(check(2, 3, 3, 5), Ok(()));
+        assert_eq!(
+            check(3, 3, 3, 5),
+            Err(CharWinError::BufferRange {
+                start: 3,
+                len: 3,
+                size: 5
+            })
+        );
+        // Too little data for `len` triangles
+        assert_eq!(
+            check(0, 4, 3, 5),
+            Err(CharWinError::BufferRange {
+                start: 0,
+                len: 4,
+                size: 3
+            })
+        );
+        let message = check(3, 3, 3, 5).unwrap_err().to_string();
+        assert_eq!(message, "Range 3..6 will not fit inside buffer of size 5.");
+    }
+
+    #[test]
+    fn tri_cpu_buffer_appends_and_clears() {
+        let mut buffer = TriCPUBuffer::<VertexV>::new();
+        let mut tri = Triangle::<VertexV>::new();
+        tri.v[1].v = Vec3f32::new(1.0, 0.0, 0.0);
+        buffer.push_triangle(tri);
+        buffer.extend(&[tri, tri]);
+        assert_eq!(buffer.n_tris(), 3);
+        assert_eq!(buffer.to_f32_array().len(), 3 * 3 * VertexV::float_size());
+        assert_eq!(buffer[2].v[1].v.as_tuple(), (1.0, 0.0, 0.0));
+        buffer.clear();
+        assert_eq!(buffer.n_tris(), 0);
+    }
+
+    fn tri(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> Triangle<ColoredVertex> {
+        let vert = |pos| ColoredVertex {
+            pos,
+            color: [1.0; 4],
+        };
+        Triangle {
+            v: [vert(a), vert(b), vert(c)],
+        }
+    }
+
+    #[test]
+    fn triangle_area_is_half_the_edge_cross_product() {
+        let right = tri([0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+        assert!((right.area() - 0.5).abs() < 1e-6);
+        let quad = [
+            right,
+            tri([1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]),
+        ];
+        let total: f32 = quad.iter().map(|t| t.area()).sum();
+        assert!((total - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn shader_includes_inline_nested_files() {
+        let mut includes = ShaderIncludes::new();
+        includes
+            .add_file(
+                "light.glsl",
+                "#include \"consts.glsl\"\nfloat light() { return K; }",
+            )
+            .add_file("consts.glsl", "#define K 0.5");
+        let src = "#version 300 es\n  #include \"light.glsl\"\nvoid main() { light(); }";
+        assert_eq!(
+            includes.resolve(src).unwrap(),
+            "#version 300 es\n#define K 0.5\nfloat light() { return K; }\nvoid main() { light(); }\n"
+        );
+    }
+
+    #[test]
+    fn shader_includes_reject_cycles_and_missing_files() {
+        let mut includes = ShaderIncludes::new();
+        includes
+            .add_file("a.glsl", "#include \"b.glsl\"")
+            .add_file("b.glsl", "#include \"a.glsl\"");
+        assert!(matches!(
+            includes.resolve("#include \"a.glsl\""),
+            Err(CharWinError::ShaderInclude(_))
+        ));
+        assert!(matches!(
+            includes.resolve("#include \"missing.glsl\""),
+            Err(CharWinError::ShaderInclude(_))
+        ));
+    }
+
+    #[test]
+    #[cfg(not(target_family = "wasm"))]
+    fn shader_includes_read_from_the_base_dir() {
+        let dir = std::env::temp_dir().join(format!("charwin-includes-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("helper.glsl"), "float helper() { return 1.0; }").unwrap();
+        let resolved = ShaderIncludes::from_dir(&dir).resolve("#include \"helper.glsl\"");
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(resolved.unwrap(), "float helper() { return 1.0; }\n");
+    }
+}
@@ -0,0 +1,46 @@
+use std::error::Error;
+use std::fmt;
+
+/// Errors returned by the fallible charwin APIs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CharWinError {
+    /// Shader stage and the compiler log.
+    ShaderCompile(String),
+    /// Program linker log.
+    ShaderLink(String),
+    /// Missing or cyclic `#include` in a shader source.
+    ShaderInclude(String),
+    TextureLoad(String),
+    ContextCreate(String),
+    /// Requested range `start..start + len` does not fit in a buffer of `size` elements.
+    BufferRange { start: usize, len: usize, size: usize },
+    /// A panic while the graphics context was locked left its mutex poisoned or, on wasm
+    /// where panics do not unwind, still locked.
+    ContextPoisoned,
+}
+
+impl fmt::Display for CharWinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use CharWinError::*;
+        match self {
+            ShaderCompile(log) => write!(f, "Error compiling shader: \n{}", log),
+            ShaderLink(log) => write!(f, "Error linking program: \n{}", log),
+            ShaderInclude(msg) => write!(f, "Could not resolve shader include: {}", msg),
+            TextureLoad(msg) => write!(f, "Could not load texture: {}", msg),
+            ContextCreate(msg) => write!(f, "Could not create context: {}", msg),
+            ContextPoisoned => write!(
+                f,
+                "Graphics context is unusable: an earlier panic left its lock poisoned or held."
+            ),
+            BufferRange { start, len, size } => write!(
+                f,
+                "Range {}..{} will not fit inside buffer of size {}.",
+                start,
+                start + len,
+                size
+            ),
+        }
+    }
+}
+
+impl Error for CharWinError {}
@@ -129,11 +129,100 @@ pub enum Key {
 }
 
 impl Key {
+    /// Converts a GLFW key code, returning `Key::Unknown` for codes without a variant.
     pub fn from_i32(n: i32) -> Key {
-        if (0..=Key::Menu as i32).contains(&n) {
-            unsafe { std::mem::transmute(n) }
-        } else {
-            Key::Unknown
+        match n {
+            // Exactly the discriminants declared on `Key`
+            32
+            | 39
+            | 44..=57
+            | 59
+            | 61
+            | 65..=93
+            | 96
+            | 161
+            | 162
+            | 256..=269
+            | 280..=284
+            | 290..=314
+            | 320..=336
+            | 340..=348 => unsafe { std::mem::transmute::<i32, Key>(n) },
+            _ => Key::Unknown,
+        }
+    }
+    /// Maps a browser `KeyboardEvent.code` physical key name such as `"KeyW"` or
+    /// `"ArrowUp"`, returning `Key::Unknown` for names without a variant.
+    pub fn from_js_code(code: &str) -> Key {
+        if let Some(c) = code.strip_prefix("Key") {
+            if let [letter @ b'A'..=b'Z'] = c.as_bytes() {
+                return Key::from_i32(*letter as i32);
+            }
+        }
+        if let Some(d) = code.strip_prefix("Digit") {
+            if let [digit @ b'0'..=b'9'] = d.as_bytes() {
+                return Key::from_i32(*digit as i32);
+            }
+        }
+        if let Some(d) = code.strip_prefix("Numpad") {
+            if let [digit @ b'0'..=b'9'] = d.as_bytes() {
+                return Key::from_i32(Key::Kp0 as i32 + (digit - b'0') as i32);
+            }
+        }
+        if let Some(n) = code.strip_prefix('F') {
+            if let Ok(n @ 1..=25) = n.parse::<i32>() {
+                return Key::from_i32(Key::F1 as i32 + n - 1);
+            }
+        }
+        match code {
+            "Space" => Key::Space,
+            "Quote" => Key::Apostrophe,
+            "Comma" => Key::Comma,
+            "Minus" => Key::Minus,
+            "Period" => Key::Period,
+            "Slash" => Key::Slash,
+            "Semicolon" => Key::Semicolon,
+            "Equal" => Key::Equal,
+            "BracketLeft" => Key::LeftBracket,
+            "Backslash" => Key::Backslash,
+            "BracketRight" => Key::RightBracket,
+            "Backquote" => Key::GraveAccent,
+            "IntlBackslash" => Key::World1,
+            "Escape" => Key::Escape,
+            "Enter" => Key::Enter,
+            "Tab" => Key::Tab,
+            "Backspace" => Key::Backspace,
+            "Insert" => Key::Insert,
+            "Delete" => Key::Delete,
+            "ArrowRight" => Key::Right,
+            "ArrowLeft" => Key::Left,
+            "ArrowDown" => Key::Down,
+            "ArrowUp" => Key::Up,
+            "PageUp" => Key::PageUp,
+            "PageDown" => Key::PageDown,
+            "Home" => Key::Home,
+            "End" => Key::End,
+            "CapsLock" => Key::CapsLock,
+            "ScrollLock" => Key::ScrollLock,
+            "NumLock" => Key::NumLock,
+            "PrintScreen" => Key::PrintScreen,
+            "Pause" => Key::Pause,
+            "NumpadDecimal" => Key::KpDecimal,
+            "NumpadDivide" => Key::KpDivide,
+            "NumpadMultiply" => Key::KpMultiply,
+            "NumpadSubtract" => Key::KpSubtract,
+            "NumpadAdd" => Key::KpAdd,
+            "NumpadEnter" => Key::KpEnter,
+            "NumpadEqual" => Key::KpEqual,
+            "ShiftLeft" => Key::LeftShift,
+            "ControlLeft" => Key::LeftControl,
+            "AltLeft" => Key::LeftAlt,
+            "MetaLeft" | "OSLeft" => Key::LeftSuper,
+            "ShiftRight" => Key::RightShift,
+            "ControlRight" => Key::RightControl,
+            "AltRight" => Key::RightAlt,
+            "MetaRight" | "OSRight" => Key::RightSuper,
+            "ContextMenu" => Key::Menu,
+            _ => Key::Unknown,
         }
     }
 }
@@ -162,3 +251,50 @@ impl MouseButton {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_from_i32_round_trips_declared_codes() {
+        for key in [
+            Key::Space,
+            Key::Num0,
+            Key::A,
+            Key::World2,
+            Key::Escape,
+            Key::F25,
+            Key::Kp0,
+            Key::Menu,
+        ] {
+            assert_eq!(Key::from_i32(key as i32), key);
+        }
+    }
+
+    #[test]
+    fn key_from_i32_maps_gaps_to_unknown() {
+        for n in [-1, 0, 31, 33, 58, 60, 94, 160, 163, 270, 315, 349, 1000] {
+            assert_eq!(Key::from_i32(n), Key::Unknown);
+        }
+    }
+
+    #[test]
+    fn js_codes_map_to_physical_keys() {
+        for (code, key) in [
+            ("KeyW", Key::W),
+            ("Digit1", Key::Num1),
+            ("ArrowUp", Key::Up),
+            ("Numpad7", Key::Kp7),
+            ("NumpadEnter", Key::KpEnter),
+            ("F12", Key::F12),
+            ("MetaLeft", Key::LeftSuper),
+            ("Keyw", Key::Unknown),
+            ("F26", Key::Unknown),
+            ("LaunchMail", Key::Unknown),
+            ("", Key::Unknown),
+        ] {
+            assert_eq!(Key::from_js_code(code), key, "{:?}", code);
+        }
+    }
+}
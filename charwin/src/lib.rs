@@ -1,5 +1,10 @@
+pub mod color;
 pub mod data;
+pub mod error;
 pub mod input;
 pub mod platform;
 pub mod state;
 pub mod window;
+
+#[cfg(all(not(target_family = "wasm"), not(feature = "glfw"), not(feature = "winit")))]
+compile_error!("charwin needs the `glfw` or `winit` feature on native targets.");
@@ -1,5 +1,7 @@
-#[cfg(not(target_family = "wasm"))]
+#[cfg(all(not(target_family = "wasm"), feature = "glfw", not(feature = "winit")))]
 pub type Window = crate::window::opengl_window::NativeGlWindow;
+#[cfg(all(not(target_family = "wasm"), feature = "winit"))]
+pub type Window = crate::window::winit_window::WinitGlWindow;
 #[cfg(not(target_family = "wasm"))]
 pub type Context = crate::window::opengl_window::NativeGlContext;
 #[cfg(not(target_family = "wasm"))]
@@ -12,6 +14,10 @@ pub type VertexArray = crate::window::opengl_window::NativeGlVertexArray;
 pub type Program = crate::window::opengl_window::NativeGlProgram;
 #[cfg(not(target_family = "wasm"))]
 pub type Texture2D = crate::window::opengl_window::NativeGlTexture2D;
+#[cfg(not(target_family = "wasm"))]
+pub type Query = crate::window::opengl_window::NativeGlQuery;
+#[cfg(not(target_family = "wasm"))]
+pub type Framebuffer = crate::window::opengl_window::NativeGlFramebuffer;
 
 #[cfg(target_family = "wasm")]
 pub type Window = crate::window::webgl_window::WebGlWindow;
@@ -27,6 +33,10 @@ pub type VertexArray = crate::window::webgl_window::WebGlVertexArray;
 pub type Program = crate::window::webgl_window::WebGlProgram;
 #[cfg(target_family = "wasm")]
 pub type Texture2D = crate::window::webgl_window::WebGlTexture2D;
+#[cfg(target_family = "wasm")]
+pub type Query = crate::window::webgl_window::WebGlQuery;
+#[cfg(target_family = "wasm")]
+pub type Framebuffer = crate::window::webgl_window::WebGlFramebuffer;
 
 #[cfg(target_family = "wasm")]
 use wasm_bindgen::prelude::*;
@@ -7,12 +7,21 @@ pub trait State: 'static {
     fn destroy(&mut self, win: &mut Window, manager: &mut dyn EventManager, exit_code: i32);
 }
 
+/// Longest delta in seconds reported to `State::update` by default, so a stalled frame
+/// (debugger, window drag) does not produce one huge time step.
+pub const DEFAULT_MAX_DELTA: f64 = 0.1;
+
+/// Exit code passed to `State::destroy` when the render loop stops because of an
+/// unrecoverable error, such as a panic poisoning the web window's context.
+pub const CRASH_EXIT_CODE: i32 = -1;
+
 #[cfg(not(target_family = "wasm"))]
 #[derive(Debug)]
 pub struct FrameManager {
     delta: f64,
     target_delta_micro: Option<u128>,
     last_frame_micro: u128,
+    max_delta: Option<f64>,
 }
 #[cfg(not(target_family = "wasm"))]
 impl FrameManager {
@@ -21,6 +30,7 @@ impl FrameManager {
             delta: 0f64,
             target_delta_micro: None,
             last_frame_micro: Self::current_time_micro(),
+            max_delta: Some(DEFAULT_MAX_DELTA),
         };
         ret.set_fps(fps);
         ret
@@ -34,15 +44,18 @@ impl FrameManager {
     pub fn next_frame_ready(&mut self) -> bool {
         if let Some(target_delta_mcs) = self.target_delta_micro {
             if Self::current_time_micro() - self.last_frame_micro >= target_delta_mcs {
-                self.delta =
-                    (Self::current_time_micro() - self.last_frame_micro) as f64 / 1000000f64;
+                self.delta = self.clamp_delta(
+                    (Self::current_time_micro() - self.last_frame_micro) as f64 / 1000000f64,
+                );
                 self.last_frame_micro = Self::current_time_micro();
                 true
             } else {
                 false
             }
         } else {
-            self.delta = (Self::current_time_micro() - self.last_frame_micro) as f64 / 1000000f64;
+            self.delta = self.clamp_delta(
+                (Self::current_time_micro() - self.last_frame_micro) as f64 / 1000000f64,
+            );
             self.last_frame_micro = Self::current_time_micro();
             true
         }
@@ -57,4 +70,17 @@ impl FrameManager {
     pub fn get_delta(&self) -> f64 {
         self.delta
     }
+    /// Sets the longest delta in seconds that will be reported, or None to never clamp.
+    pub fn set_max_delta(&mut self, max_delta: Option<f64>) {
+        self.max_delta = max_delta;
+    }
+    pub fn get_max_delta(&self) -> Option<f64> {
+        self.max_delta
+    }
+    fn clamp_delta(&self, delta: f64) -> f64 {
+        match self.max_delta {
+            Some(max_delta) => delta.min(max_delta),
+            None => delta,
+        }
+    }
 }
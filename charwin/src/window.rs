@@ -2,14 +2,20 @@
 pub mod opengl_window;
 #[cfg(target_family = "wasm")]
 pub mod webgl_window;
+#[cfg(all(not(target_family = "wasm"), feature = "winit"))]
+pub mod winit_window;
 
 #[cfg(target_family = "wasm")]
 use wasm_bindgen::prelude::*;
 
-use crate::data::GPUTexture;
+use crate::char_panic;
+use crate::data::{GPUTexture, TextureLoadOptions, DEFAULT_PLACEHOLDER_COLOR};
+use crate::error::CharWinError;
 use crate::input::{Key, MouseButton};
-use crate::platform::{Context, Window};
+use crate::platform::{Context, Texture2D, Window};
+use crate::state::DEFAULT_MAX_DELTA;
 use charmath::linear::vector::{Vec2, Vec2F};
+use image::ImageFormat;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
@@ -32,6 +38,9 @@ pub struct WindowCreateArgs {
     pub width: u32,
     pub height: u32,
     pub mode: WindowSizeMode,
+    gl_version: Option<(u32, u32)>,
+    core_profile: bool,
+    max_delta: Option<f64>,
 }
 #[cfg_attr(target_family = "wasm", wasm_bindgen)]
 impl WindowCreateArgs {
@@ -47,9 +56,38 @@ impl WindowCreateArgs {
             width,
             height,
             mode,
+            gl_version: None,
+            core_profile: false,
+            max_delta: Some(DEFAULT_MAX_DELTA),
         }
     }
 }
+impl WindowCreateArgs {
+    /// Requests an OpenGL context of at least `major.minor`, optionally with the core
+    /// profile (needed for e.g. `#version 330 core` on macOS). Without this the
+    /// platform's default context is used. Web always uses WebGL2 and ignores it.
+    pub fn with_gl_version(mut self, major: u32, minor: u32, core_profile: bool) -> Self {
+        self.gl_version = Some((major, minor));
+        self.core_profile = core_profile;
+        self
+    }
+    /// The requested `(major, minor)` context version, if any.
+    pub fn gl_version(&self) -> Option<(u32, u32)> {
+        self.gl_version
+    }
+    pub fn core_profile(&self) -> bool {
+        self.core_profile
+    }
+    /// Sets the longest delta in seconds passed to `State::update`, or `None` to never
+    /// clamp it. Defaults to `DEFAULT_MAX_DELTA`.
+    pub fn with_max_delta(mut self, max_delta: Option<f64>) -> Self {
+        self.max_delta = max_delta;
+        self
+    }
+    pub fn max_delta(&self) -> Option<f64> {
+        self.max_delta
+    }
+}
 
 /// A universal set of window events each platform's events gets translated into.
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -70,6 +108,61 @@ pub enum WindowEvent {
     None,
 }
 
+/// A batch of window events with typed filters, for apps that process raw events
+/// themselves instead of through an `EventManager`.
+#[derive(Debug, Clone, Copy)]
+pub struct Events<'a> {
+    events: &'a [WindowEvent],
+}
+impl<'a> Events<'a> {
+    pub fn new(events: &'a [WindowEvent]) -> Self {
+        Self { events }
+    }
+    pub fn iter(&self) -> std::slice::Iter<'a, WindowEvent> {
+        self.events.iter()
+    }
+    /// Keys pressed down, in order. Held key repeats are not included.
+    pub fn key_presses(&self) -> impl Iterator<Item = Key> + 'a {
+        self.iter().filter_map(|e| match e {
+            WindowEvent::KeyDown(key, _) => Some(*key),
+            _ => None,
+        })
+    }
+    pub fn key_releases(&self) -> impl Iterator<Item = Key> + 'a {
+        self.iter().filter_map(|e| match e {
+            WindowEvent::KeyUp(key, _) => Some(*key),
+            _ => None,
+        })
+    }
+    pub fn mouse_presses(&self) -> impl Iterator<Item = MouseButton> + 'a {
+        self.iter().filter_map(|e| match e {
+            WindowEvent::MouseButtonDown(button) => Some(*button),
+            _ => None,
+        })
+    }
+    /// New window sizes, in order.
+    pub fn resizes(&self) -> impl Iterator<Item = (i32, i32)> + 'a {
+        self.iter().filter_map(|e| match e {
+            WindowEvent::Size(w, h) => Some((*w, *h)),
+            _ => None,
+        })
+    }
+    /// Scroll offsets `(x, y)`, in order.
+    pub fn scrolls(&self) -> impl Iterator<Item = (f64, f64)> + 'a {
+        self.iter().filter_map(|e| match e {
+            WindowEvent::Scroll(x, y) => Some((*x, *y)),
+            _ => None,
+        })
+    }
+}
+impl<'a> IntoIterator for Events<'a> {
+    type Item = &'a WindowEvent;
+    type IntoIter = std::slice::Iter<'a, WindowEvent>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.events.iter()
+    }
+}
+
 /// Takes raw window events and provides useful ways to operate on them.
 pub trait EventManager: 'static {
     fn mouse_pos(&self) -> (f64, f64);
@@ -82,6 +175,14 @@ pub trait EventManager: 'static {
     fn process_events(&mut self, events: &Vec<WindowEvent>);
     fn cursor_on_window(&self) -> bool;
 
+    /// Advances the manager's clock by the frame delta in seconds.
+    /// Called by the render loop before each batch of events is processed.
+    fn advance_time(&mut self, _delta: f64) {}
+    /// Returns how many seconds the key has been held, or None if it is up.
+    fn key_held_duration(&self, _k: Key) -> Option<f64> {
+        None
+    }
+
     /// Whether the left mouse button is pressed
     ///
     /// Example code
@@ -165,6 +266,8 @@ pub trait EventManager: 'static {
 pub struct DefaultEventManager {
     mouse_pos: (f64, f64),
     keys: HashMap<Key, bool>,
+    key_down_times: HashMap<Key, f64>,
+    time: f64,
     mouse_buttons: HashMap<MouseButton, bool>,
     win_size: ((i32, i32), bool),
     win_pos: ((i32, i32), bool),
@@ -180,6 +283,8 @@ impl DefaultEventManager {
         DefaultEventManager {
             mouse_pos: (0.0, 0.0),
             keys: HashMap::new(),
+            key_down_times: HashMap::new(),
+            time: 0.0,
             mouse_buttons: HashMap::new(),
             win_size: ((0, 0), false),
             win_pos: ((0, 0), false),
@@ -189,6 +294,22 @@ impl DefaultEventManager {
             total_scroll: (0.0, 0.0),
         }
     }
+    /// Forgets held keys and mouse buttons and zeroes the scroll totals, e.g. on a scene
+    /// change so input held during the transition does not carry over. Window size,
+    /// position, focus and the cursor position are kept.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = reset))]
+    pub fn reset(&mut self) {
+        self.clear_held_keys();
+        self.mouse_buttons.clear();
+        self.scroll_diff = (0.0, 0.0);
+        self.total_scroll = (0.0, 0.0);
+    }
+    /// Releases every held key without touching mouse or scroll state.
+    #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = clearHeldKeys))]
+    pub fn clear_held_keys(&mut self) {
+        self.keys.clear();
+        self.key_down_times.clear();
+    }
 }
 impl EventManager for DefaultEventManager {
     fn screen_size_changed(&self) -> ((i32, i32), bool) {
@@ -216,6 +337,12 @@ impl EventManager for DefaultEventManager {
             false
         }
     }
+    fn advance_time(&mut self, delta: f64) {
+        self.time += delta;
+    }
+    fn key_held_duration(&self, k: Key) -> Option<f64> {
+        self.key_down_times.get(&k).map(|down| self.time - down)
+    }
     fn mouse_pressed(&self, button: MouseButton) -> bool {
         if self.mouse_buttons.contains_key(&button) {
             self.mouse_buttons[&button]
@@ -226,15 +353,14 @@ impl EventManager for DefaultEventManager {
     fn process_events(&mut self, events: &Vec<WindowEvent>) {
         self.scroll_diff = (0.0, 0.0);
         self.win_pos.1 = false;
-        self.win_size.1 = false;
+        let prev_size = self.win_size.0;
         let mut mouse_changed = false;
         let mut mouse_pos = (0.0, 0.0);
         for event in events {
             match event {
-                WindowEvent::Size(w, h) => {
-                    if self.win_size.0 != (*w, *h) {
-                        self.win_size = ((*w, *h), true);
-                    }
+                // Only the latest size matters, and minimized windows report zero sizes
+                WindowEvent::Size(w, h) if *w > 0 && *h > 0 => {
+                    self.win_size.0 = (*w, *h);
                 }
                 WindowEvent::CursorPosition(x, y) => {
                     mouse_pos = (*x, *y);
@@ -248,9 +374,11 @@ impl EventManager for DefaultEventManager {
                 }
                 WindowEvent::KeyUp(key, _) => {
                     self.keys.insert(*key, false);
+                    self.key_down_times.remove(key);
                 }
                 WindowEvent::KeyDown(key, _) => {
                     self.keys.insert(*key, true);
+                    self.key_down_times.entry(*key).or_insert(self.time);
                 }
                 WindowEvent::CursorEnter(cursor_on_window) => {
                     self.cursor_on_window = *cursor_on_window;
@@ -269,6 +397,7 @@ impl EventManager for DefaultEventManager {
                 _ => {}
             }
         }
+        self.win_size.1 = self.win_size.0 != prev_size;
         if mouse_changed && self.win_size.0 .0 > 0 && self.win_size.0 .1 > 0 {
             self.mouse_pos = (
                 mouse_pos.0 / self.win_size.0 .0 as f64,
@@ -292,6 +421,10 @@ impl DefaultEventManager {
     pub fn wkey_pressed(&self, k: Key) -> bool {
         self.key_pressed(k)
     }
+    #[wasm_bindgen(js_name = keyHeldDuration)]
+    pub fn wkey_held_duration(&self, k: Key) -> Option<f64> {
+        self.key_held_duration(k)
+    }
     #[wasm_bindgen(js_name = focused)]
     pub fn wfocused(&self) -> bool {
         self.focused()
@@ -348,14 +481,65 @@ pub trait AbstractWindow {
     fn poll_events(&mut self);
     fn get_events(&mut self) -> Vec<WindowEvent>;
     fn swap_buffers(&mut self);
+    /// Sets how many screen refreshes `swap_buffers` waits for: 0 swaps immediately,
+    /// 1 is vsync and -1 is adaptive vsync, which swaps immediately when a frame is
+    /// late instead of waiting for the next refresh. Only the GLFW backend applies it;
+    /// the browser always syncs to the display and glutin fixes vsync at creation.
+    fn set_swap_interval(&mut self, interval: i32);
+    /// The swap interval in effect, as passed to `set_swap_interval`.
+    fn swap_interval(&self) -> i32;
+    fn set_vsync(&mut self, vsync: bool) {
+        self.set_swap_interval(vsync as i32);
+    }
+    /// Falls back to plain vsync on drivers without the swap control tear extension.
+    fn set_adaptive_vsync(&mut self) {
+        self.set_swap_interval(-1);
+    }
     fn close(&mut self);
     fn get_size(&self) -> (i32, i32);
     fn get_pos(&self) -> (i32, i32);
     fn get_gl_context(&mut self) -> Context;
-    fn load_texture_rgba(&mut self, path: &str, mips: Option<u32>) -> Arc<Mutex<GPUTexture>>;
+    /// Loads a texture asynchronously. Images are uploaded top row first by default, which
+    /// appears upside down with OpenGL's bottom-left texture origin; see
+    /// [`TextureLoadOptions`] to flip the rows or premultiply alpha on load. The texture
+    /// is a 1x1 [`DEFAULT_PLACEHOLDER_COLOR`] until the image arrives.
+    fn load_texture_rgba(
+        &mut self,
+        path: &str,
+        options: TextureLoadOptions,
+    ) -> Arc<Mutex<GPUTexture>> {
+        self.load_texture_rgba_with_placeholder(path, options, DEFAULT_PLACEHOLDER_COLOR)
+    }
+    /// As [`load_texture_rgba`](Self::load_texture_rgba), but the texture starts as a 1x1
+    /// `placeholder` colour, e.g.
+    /// [`TRANSPARENT_PLACEHOLDER_COLOR`](crate::data::TRANSPARENT_PLACEHOLDER_COLOR).
+    fn load_texture_rgba_with_placeholder(
+        &mut self,
+        path: &str,
+        options: TextureLoadOptions,
+        placeholder: [u8; 4],
+    ) -> Arc<Mutex<GPUTexture>>;
+    /// Decodes an in-memory image and uploads it synchronously.
+    fn try_load_texture_from_bytes(
+        &mut self,
+        bytes: &[u8],
+        format_hint: Option<ImageFormat>,
+        options: TextureLoadOptions,
+    ) -> Result<Arc<Mutex<GPUTexture>>, CharWinError>;
     // async fn load_texture_rgba(&mut self, path: &str, mips: Option<u32>) -> GPUTexture;
     // fn load_string(&mut self, path: &str) -> String;
 
+    fn load_texture_from_bytes(
+        &mut self,
+        bytes: &[u8],
+        format_hint: Option<ImageFormat>,
+        options: TextureLoadOptions,
+    ) -> Arc<Mutex<GPUTexture>> {
+        self.try_load_texture_from_bytes(bytes, format_hint, options)
+            .unwrap_or_else(|e| {
+                char_panic!("{}", e);
+            })
+    }
     fn clear_colour(&mut self) {
         self.clear(&[GlClearMask::Color]);
     }
@@ -382,14 +566,52 @@ pub trait AbstractWindowFactory {
     fn create(args: &WindowCreateArgs) -> Self;
 }
 
+#[repr(i32)]
 #[cfg_attr(target_family = "wasm", wasm_bindgen)]
-pub struct VertexAttrib(pub u32, pub u32, pub usize, pub usize);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GlVertexAttribType {
+    Byte = 0x1,
+    UnsignedByte = 0x2,
+    Short = 0x4,
+    UnsignedShort = 0x8,
+    Int = 0x10,
+    UnsignedInt = 0x20,
+    HalfFloat = 0x40,
+    Float = 0x80,
+}
+
+/// Vertex attribute index, component count, stride and offset in bytes, component
+/// type, and whether integer components are normalized to `[0, 1]` or `[-1, 1]`.
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+pub struct VertexAttrib(
+    pub u32,
+    pub u32,
+    pub usize,
+    pub usize,
+    pub GlVertexAttribType,
+    pub bool,
+);
+impl VertexAttrib {
+    /// A float attribute.
+    pub fn new(index: u32, size: u32, step: usize, offset: usize) -> Self {
+        VertexAttrib(index, size, step, offset, GlVertexAttribType::Float, false)
+    }
+    pub fn with_type(mut self, tp: GlVertexAttribType, normalized: bool) -> Self {
+        self.4 = tp;
+        self.5 = normalized;
+        self
+    }
+}
 #[cfg(target_family = "wasm")]
 #[wasm_bindgen]
 impl VertexAttrib {
     #[wasm_bindgen(constructor)]
     pub fn wnew(index: i32, size: i32, step: i32, offset: i32) -> Self {
-        VertexAttrib(index as u32, size as u32, step as usize, offset as usize)
+        VertexAttrib::new(index as u32, size as u32, step as usize, offset as usize)
+    }
+    #[wasm_bindgen(js_name = withType)]
+    pub fn wwith_type(self, tp: GlVertexAttribType, normalized: bool) -> Self {
+        self.with_type(tp, normalized)
     }
 }
 
@@ -405,6 +627,33 @@ pub enum GlDrawMode {
     TriangleStrip = 0x20,
     TriangleFan = 0x40,
 }
+impl GlDrawMode {
+    /// Number of vertices needed to draw `primitives` points, lines or triangles.
+    pub fn vertex_count(&self, primitives: i32) -> i32 {
+        use GlDrawMode::*;
+        if primitives <= 0 {
+            return 0;
+        }
+        match self {
+            Points | LineLoop => primitives,
+            LineStrip => primitives + 1,
+            Lines => primitives * 2,
+            Triangles => primitives * 3,
+            TriangleStrip | TriangleFan => primitives + 2,
+        }
+    }
+    pub fn is_triangles(&self) -> bool {
+        use GlDrawMode::*;
+        matches!(self, Triangles | TriangleStrip | TriangleFan)
+    }
+}
+#[repr(i32)]
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GlIndexType {
+    UnsignedShort = 0x1,
+    UnsignedInt = 0x2,
+}
 #[repr(i32)]
 #[cfg_attr(target_family = "wasm", wasm_bindgen)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -424,12 +673,30 @@ pub enum GlBufferType {
     TransformFeedbackBuffer = 0x1000,
     UniformBuffer = 0x2000,
 }
+/// Query targets. WebGL2 only supports the any-samples-passed and transform feedback
+/// targets, plus `TimeElapsed` where `EXT_disjoint_timer_query_webgl2` is available.
+#[repr(i32)]
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GlQueryTarget {
+    /// Elapsed GPU time in nanoseconds.
+    TimeElapsed = 0x1,
+    SamplesPassed = 0x2,
+    AnySamplesPassed = 0x4,
+    AnySamplesPassedConservative = 0x8,
+    PrimitivesGenerated = 0x10,
+    TransformFeedbackPrimitivesWritten = 0x20,
+}
 #[repr(i32)]
 #[cfg_attr(target_family = "wasm", wasm_bindgen)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum GlStorageMode {
+    /// Uploaded once and drawn many times.
     Static = 0x1,
+    /// Updated occasionally and drawn many times.
     Dynamic = 0x2,
+    /// Rewritten about as often as it is drawn, e.g. every frame.
+    Stream = 0x4,
 }
 #[repr(i32)]
 #[cfg_attr(target_family = "wasm", wasm_bindgen)]
@@ -452,6 +719,99 @@ pub enum GlClearMask {
     Stencil = 0x8,
 }
 
+#[repr(i32)]
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GlCompareFunc {
+    Never = 0x1,
+    Less = 0x2,
+    Equal = 0x4,
+    LEqual = 0x8,
+    Greater = 0x10,
+    NotEqual = 0x20,
+    GEqual = 0x40,
+    Always = 0x80,
+}
+#[repr(i32)]
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GlStencilOp {
+    Keep = 0x1,
+    Zero = 0x2,
+    Replace = 0x4,
+    Incr = 0x8,
+    IncrWrap = 0x10,
+    Decr = 0x20,
+    DecrWrap = 0x40,
+    Invert = 0x80,
+}
+#[repr(i32)]
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GlBlendFactor {
+    Zero = 0x1,
+    One = 0x2,
+    SrcColor = 0x4,
+    OneMinusSrcColor = 0x8,
+    DstColor = 0x10,
+    OneMinusDstColor = 0x20,
+    SrcAlpha = 0x40,
+    OneMinusSrcAlpha = 0x80,
+    DstAlpha = 0x100,
+    OneMinusDstAlpha = 0x200,
+}
+impl GlBlendFactor {
+    pub const ALL: [GlBlendFactor; 10] = [
+        GlBlendFactor::Zero,
+        GlBlendFactor::One,
+        GlBlendFactor::SrcColor,
+        GlBlendFactor::OneMinusSrcColor,
+        GlBlendFactor::DstColor,
+        GlBlendFactor::OneMinusDstColor,
+        GlBlendFactor::SrcAlpha,
+        GlBlendFactor::OneMinusSrcAlpha,
+        GlBlendFactor::DstAlpha,
+        GlBlendFactor::OneMinusDstAlpha,
+    ];
+}
+
+#[repr(i32)]
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GlTextureWrap {
+    Repeat = 0x1,
+    MirroredRepeat = 0x2,
+    ClampToEdge = 0x4,
+    /// Not supported by WebGL, which falls back to `ClampToEdge`.
+    ClampToBorder = 0x8,
+}
+#[repr(i32)]
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GlTextureFilter {
+    Nearest = 0x1,
+    Linear = 0x2,
+}
+/// How a shader may use a texture bound to an image unit. Image units are not
+/// available in WebGL2.
+#[cfg(not(target_family = "wasm"))]
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GlImageAccess {
+    ReadOnly = 0x1,
+    WriteOnly = 0x2,
+    ReadWrite = 0x4,
+}
+#[repr(i32)]
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum GlFramebufferAttachment {
+    Color0 = 0x1,
+    Depth = 0x2,
+    Stencil = 0x4,
+    DepthStencil = 0x8,
+}
+
 #[repr(i64)]
 #[cfg_attr(target_family = "wasm", wasm_bindgen)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -575,6 +935,9 @@ pub enum GlInternalTextureFormat {
     CompressedRGBA,
     CompressedSRGB,
     CompressedSRGBAlpha,
+    DepthComponent16,
+    DepthComponent24,
+    DepthComponent32F,
 }
 #[repr(i32)]
 #[cfg_attr(target_family = "wasm", wasm_bindgen)]
@@ -615,26 +978,106 @@ pub trait GlBindable: Sized + Drop {
     fn bind(&self);
     fn unbind(&self);
 }
-pub trait GlShaderLoc: Sized {}
+pub trait GlShaderLoc: Sized {
+    /// Whether the location refers to an active uniform in the program.
+    fn exists(&self) -> bool;
+}
 pub trait GlBuffer: GlBindable {
     fn new(w: &Window, tp: GlBufferType) -> Self;
     fn buffer_data(&self, size: usize, data: *const f32, mode: GlStorageMode);
+    /// Reallocates the buffer's storage without uploading any data, orphaning the old storage.
+    fn orphan_data(&self, size: usize, mode: GlStorageMode);
     fn buffer_sub_data(&self, start: usize, size: usize, data: *const f32);
     fn get_buffer_sub_data(&self, start: usize, size: usize, recv: *mut f32);
+    /// Size of the buffer's storage in bytes. Buffer must be bound.
+    fn byte_size(&self) -> usize;
     fn get_type(&self) -> GlBufferType;
+    /// Binds the buffer to an indexed binding point such as an atomic counter or uniform
+    /// block binding.
+    fn bind_base(&self, index: u32);
+
+    /// Reads the whole buffer back as a slice of `T`, which must be plain data valid for
+    /// any bit pattern. Panics if the buffer size is not a multiple of `size_of::<T>()`.
+    /// WebGL reads whole floats, so element sizes there must be a multiple of 4 bytes.
+    fn read_typed<T: Copy>(&self) -> Vec<T> {
+        self.bind();
+        let bytes = self.byte_size();
+        let elem_size = std::mem::size_of::<T>();
+        if elem_size == 0 || bytes % elem_size != 0 {
+            char_panic!(
+                "GlBuffer.read_typed: {} bytes is not a whole number of {} byte elements.",
+                bytes,
+                elem_size
+            );
+        }
+        // Read through f32 storage so T's alignment does not matter
+        let mut raw = vec![0f32; bytes.div_ceil(4)];
+        self.get_buffer_sub_data(0, bytes, raw.as_mut_ptr());
+        self.unbind();
+        let len = bytes / elem_size;
+        let mut ret = Vec::<T>::with_capacity(len);
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                raw.as_ptr() as *const u8,
+                ret.as_mut_ptr() as *mut u8,
+                bytes,
+            );
+            ret.set_len(len);
+        }
+        ret
+    }
+}
+#[allow(drop_bounds)]
+pub trait GlQuery: Sized + Drop {
+    fn new(w: &Window, target: GlQueryTarget) -> Self;
+    /// Starts the query. Only one query per target may be active at a time.
+    fn begin(&self);
+    fn end(&self);
+    /// Whether the result of the last query is ready without stalling.
+    fn result_available(&self) -> bool;
+    /// Result of the last query, waiting for the GPU if it is not yet available.
+    fn result(&self) -> u64;
+    fn get_target(&self) -> GlQueryTarget;
 }
 pub trait GlVertexArray: GlBindable {
     fn new(w: &Window) -> Self;
+    /// Points a float shader input at the attribute, converting or normalizing its
+    /// components as given by its type. VAO and buffer must be bound.
     fn attrib_ptr(&self, v: &VertexAttrib);
+    /// Points an integer (`int`/`ivec`/`uint`/`uvec`) shader input at the attribute.
+    /// The attribute type must be an integer type. VAO and buffer must be bound.
+    fn attrib_ptr_i(&self, v: &VertexAttrib);
     fn remove_attrib_ptr(&self, v: &VertexAttrib);
 }
+/// An active uniform or vertex attribute of a linked program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniformInfo {
+    /// Array uniforms are reported with a `[0]` suffix.
+    pub name: String,
+    /// Number of array elements, or 1.
+    pub size: i32,
+    /// The GL type enum, e.g. `0x8B51` for `vec3`. Values are the same on both backends.
+    pub gl_type: u32,
+}
 pub trait GlProgram: GlBindable {
     type ShaderLoc: GlShaderLoc;
     type Shader: GlShader;
     fn new(w: &Window) -> Self;
     fn draw_arrays(&self, mode: GlDrawMode, start: i32, len: i32);
+    /// Draws `count` indices from the bound element array buffer, starting `offset` bytes in.
+    fn draw_elements(&self, mode: GlDrawMode, count: i32, index_type: GlIndexType, offset: usize);
     fn shader_loc(&self, name: &str) -> Self::ShaderLoc;
     fn attach_shader(&self, shader: &Self::Shader);
+    /// Assigns vertex attribute `name` to location `index`, for shaders without
+    /// `layout(location = N)` qualifiers. Takes effect the next time the program is linked.
+    fn bind_attrib_location(&self, index: u32, name: &str);
+    /// Location of the active vertex attribute `name` in the linked program, or -1.
+    fn attrib_location(&self, name: &str) -> i32;
+    /// Assigns the active uniform block `name` to uniform buffer binding point `binding`.
+    /// Returns false if the linked program has no such block.
+    fn uniform_block_binding(&self, name: &str, binding: u32) -> bool;
+    fn active_uniforms(&self) -> Vec<UniformInfo>;
+    fn active_attributes(&self) -> Vec<UniformInfo>;
     fn link_program(&self);
     fn get_link_status(&self) -> Option<String>;
     fn uniform_4f(&self, loc: &Self::ShaderLoc, v: (f32, f32, f32, f32));
@@ -683,17 +1126,96 @@ pub trait GlTexture2D: GlBindable {
         pixel_byte_size: usize,
     );
     fn set_slot(&mut self, slot: u32);
+    /// Byte alignment of each row of data passed to `set_texture`: 1, 2, 4 or 8. The
+    /// default of 1 suits the tightly packed rows from `image`, such as RGB8 images
+    /// whose width is not a multiple of 4, which GL's own default of 4 would skew.
+    fn set_unpack_alignment(&mut self, alignment: u32);
+    /// Sets the wrap mode along each texture axis. Texture must be bound.
+    fn set_wrap(&mut self, s: GlTextureWrap, t: GlTextureWrap);
+    /// Sets the colour sampled outside a `ClampToBorder` texture. Texture must be bound.
+    /// Has no effect on WebGL.
+    fn set_border_color(&mut self, r: f32, g: f32, b: f32, a: f32);
+    /// Limits sampling to mip levels `base..=max`. Texture must be bound.
+    fn set_mip_range(&mut self, base: u32, max: u32);
+    /// Offsets the mip level chosen when sampling. Texture must be bound.
+    /// Has no effect on WebGL, which only supports a bias in the shader `texture` call.
+    fn set_lod_bias(&mut self, bias: f32);
+    /// Sets the minification and magnification filters, disabling mipmapped sampling.
+    /// Texture must be bound.
+    fn set_filter(&mut self, min: GlTextureFilter, mag: GlTextureFilter);
 }
+pub trait GlFramebuffer: GlBindable {
+    fn new(w: &Window) -> Self;
+    /// Attaches level 0 of a texture. Framebuffer must be bound.
+    fn attach_texture_2d(&self, attachment: GlFramebufferAttachment, tex: &Texture2D);
+    /// Disables the colour draw and read buffers for depth-only rendering.
+    /// Framebuffer must be bound.
+    fn set_depth_only(&self);
+    /// Whether the attachments form a complete framebuffer. Framebuffer must be bound.
+    fn is_complete(&self) -> bool;
+}
+/// Version and driver information reported by the active GL context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlInfo {
+    pub version: String,
+    pub vendor: String,
+    pub renderer: String,
+    pub glsl_version: String,
+}
+
 pub trait GlContext: Sized {
     fn new(w: &mut Window) -> Self;
 
     fn clear(&self, mask: &[GlClearMask]);
     fn clear_color(&self, r: f32, g: f32, b: f32, a: f32);
     fn viewport(&self, x: i32, y: i32, w: u32, h: u32);
+    /// Returns the active viewport as `(x, y, width, height)`, e.g. to restore it
+    /// after rendering to a texture.
+    fn get_viewport(&self) -> (i32, i32, u32, u32);
     fn enable(&mut self, feature: GlFeature);
     fn disable(&mut self, feature: GlFeature);
+    /// Features enabled through this context handle. Handles do not share this record,
+    /// see `query_enabled` for the driver's state.
     fn get_enabled_features(&self) -> Vec<GlFeature>;
+    fn is_enabled(&self, feature: GlFeature) -> bool;
+    /// Asks the driver whether `feature` is enabled, regardless of which handle enabled it.
+    fn query_enabled(&self, feature: GlFeature) -> bool;
+    /// Sets the source and destination colour factors used while `GlFeature::Blend` is
+    /// enabled.
+    fn blend_func(&self, src: GlBlendFactor, dst: GlBlendFactor);
+    /// Returns the current source and destination colour blend factors.
+    fn get_blend_func(&self) -> (GlBlendFactor, GlBlendFactor);
     fn default_depth_func(&self);
+    /// Sets the comparison used for depth testing. Use `GlCompareFunc::Greater`
+    /// with a reverse-Z projection.
+    fn depth_func(&self, func: GlCompareFunc);
+    /// Sets the value the depth buffer is cleared to, `1.0` by default.
+    /// Use `0.0` with a reverse-Z projection.
+    fn clear_depth(&self, depth: f32);
+    /// Maps normalized device depth onto `[near, far]` of the depth buffer. Both values
+    /// must lie in `[0, 1]`, and `near` may be greater than `far`.
+    fn depth_range(&self, near: f32, far: f32);
+    /// Enables or disables writing to the depth buffer. Depth testing with
+    /// `GlFeature::DepthTest` still occurs while writes are disabled.
+    fn depth_mask(&self, write: bool);
+    /// Sets the depth offset applied while `GlFeature::PolygonOffsetFill` is enabled,
+    /// used to draw decals over coplanar geometry without z-fighting.
+    fn polygon_offset(&self, factor: f32, units: f32);
+    /// Enables or disables writing to each colour channel. The mask also applies to
+    /// `clear`, so masked channels keep their value when the colour buffer is cleared.
+    fn color_mask(&self, r: bool, g: bool, b: bool, a: bool);
+    /// Sets the stencil test function, reference value and mask. Requires `GlFeature::StencilTest`.
+    fn stencil_func(&self, func: GlCompareFunc, reference: i32, mask: u32);
+    /// Sets the actions taken on stencil fail, depth fail and depth pass.
+    fn stencil_op(&self, sfail: GlStencilOp, dpfail: GlStencilOp, dppass: GlStencilOp);
+    /// Sets which bits of the stencil buffer can be written to.
+    fn stencil_mask(&self, mask: u32);
+    /// Queries the GL version, vendor, renderer and GLSL version strings.
+    fn info(&self) -> GlInfo;
+    /// Reads the depth buffer value in `[0, 1]` at window pixel `(x, y)`, with the
+    /// origin at the bottom left. WebGL2 cannot read the default framebuffer's depth,
+    /// so the web backend always returns the far plane depth `1.0`.
+    fn read_depth(&self, x: i32, y: i32) -> f32;
 
     fn enable_features(&mut self, features: &[GlFeature]) {
         for i in 0..features.len() {
@@ -705,4 +1227,226 @@ pub trait GlContext: Sized {
             self.disable(features[i]);
         }
     }
+    fn set_feature(&mut self, feature: GlFeature, enabled: bool) {
+        if enabled {
+            self.enable(feature);
+        } else {
+            self.disable(feature);
+        }
+    }
+}
+
+/// Scope guard overriding depth testing, blending and face culling for the draws made
+/// while it lives. Only the state it changes is captured, and it is restored on drop.
+///
+/// Example code
+/// {
+///     let _s = RenderState::new(&mut ctx).no_depth().blend_alpha();
+///     shader.draw(n);
+/// }
+pub struct RenderState<'a> {
+    ctx: &'a mut Context,
+    features: Vec<(GlFeature, bool)>,
+    blend_func: Option<(GlBlendFactor, GlBlendFactor)>,
+}
+impl<'a> RenderState<'a> {
+    pub fn new(ctx: &'a mut Context) -> Self {
+        Self {
+            ctx,
+            features: Vec::with_capacity(3),
+            blend_func: None,
+        }
+    }
+    fn set(mut self, feature: GlFeature, enabled: bool) -> Self {
+        if !self.features.iter().any(|(f, _)| *f == feature) {
+            let previous = self.ctx.query_enabled(feature);
+            self.features.push((feature, previous));
+        }
+        self.ctx.set_feature(feature, enabled);
+        self
+    }
+    pub fn depth(self) -> Self {
+        self.set(GlFeature::DepthTest, true)
+    }
+    pub fn no_depth(self) -> Self {
+        self.set(GlFeature::DepthTest, false)
+    }
+    pub fn cull(self) -> Self {
+        self.set(GlFeature::CullFace, true)
+    }
+    pub fn no_cull(self) -> Self {
+        self.set(GlFeature::CullFace, false)
+    }
+    pub fn no_blend(self) -> Self {
+        self.set(GlFeature::Blend, false)
+    }
+    /// Enables blending with the given colour factors.
+    pub fn blend(mut self, src: GlBlendFactor, dst: GlBlendFactor) -> Self {
+        if self.blend_func.is_none() {
+            self.blend_func = Some(self.ctx.get_blend_func());
+        }
+        self.ctx.blend_func(src, dst);
+        self.set(GlFeature::Blend, true)
+    }
+    /// Standard transparency blending, `src * a + dst * (1 - a)`.
+    pub fn blend_alpha(self) -> Self {
+        self.blend(GlBlendFactor::SrcAlpha, GlBlendFactor::OneMinusSrcAlpha)
+    }
+}
+impl<'a> Drop for RenderState<'a> {
+    fn drop(&mut self) {
+        for (feature, enabled) in self.features.drain(..) {
+            self.ctx.set_feature(feature, enabled);
+        }
+        if let Some((src, dst)) = self.blend_func {
+            self.ctx.blend_func(src, dst);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_args_max_delta_defaults_and_overrides() {
+        let args = WindowCreateArgs::new("test".into(), 1, 1, WindowSizeMode::Windowed);
+        assert_eq!(args.max_delta(), Some(DEFAULT_MAX_DELTA));
+        assert_eq!(args.with_max_delta(None).max_delta(), None);
+    }
+
+    #[test]
+    fn key_held_duration_counts_from_the_first_key_down() {
+        let mut manager = DefaultEventManager::new();
+        manager.advance_time(1.0);
+        manager.process_events(&vec![WindowEvent::KeyDown(Key::W, 0)]);
+        manager.advance_time(0.25);
+        // Repeats while held keep the original press time
+        manager.process_events(&vec![WindowEvent::KeyDown(Key::W, 0)]);
+        manager.advance_time(0.5);
+        assert_eq!(manager.key_held_duration(Key::W), Some(0.75));
+        assert_eq!(manager.key_held_duration(Key::S), None);
+        manager.process_events(&vec![WindowEvent::KeyUp(Key::W, 0)]);
+        assert_eq!(manager.key_held_duration(Key::W), None);
+    }
+
+    #[test]
+    fn zero_sizes_are_ignored() {
+        let mut manager = DefaultEventManager::new();
+        manager.process_events(&vec![WindowEvent::Size(0, 0), WindowEvent::Size(640, 480)]);
+        assert_eq!(manager.screen_size_changed(), ((640, 480), true));
+        manager.process_events(&vec![
+            WindowEvent::Size(640, 480),
+            WindowEvent::Size(0, 480),
+        ]);
+        assert_eq!(manager.screen_size_changed(), ((640, 480), false));
+    }
+
+    #[test]
+    fn draw_mode_vertex_counts() {
+        assert_eq!(GlDrawMode::Lines.vertex_count(4), 8);
+        assert_eq!(GlDrawMode::Triangles.vertex_count(2), 6);
+        assert_eq!(GlDrawMode::Points.vertex_count(5), 5);
+        assert_eq!(GlDrawMode::LineStrip.vertex_count(3), 4);
+        assert_eq!(GlDrawMode::TriangleFan.vertex_count(3), 5);
+        assert_eq!(GlDrawMode::Triangles.vertex_count(-1), 0);
+    }
+
+    #[test]
+    fn events_filter_by_variant() {
+        let batch = [
+            WindowEvent::KeyDown(Key::A, 0),
+            WindowEvent::Size(320, 240),
+            WindowEvent::KeyHeld(Key::A, 0),
+            WindowEvent::KeyUp(Key::A, 0),
+            WindowEvent::Scroll(0.0, -1.0),
+            WindowEvent::KeyDown(Key::Space, 0),
+        ];
+        let events = Events::new(&batch);
+        assert_eq!(
+            events.key_presses().collect::<Vec<_>>(),
+            [Key::A, Key::Space]
+        );
+        assert_eq!(events.key_releases().collect::<Vec<_>>(), [Key::A]);
+        assert_eq!(events.resizes().collect::<Vec<_>>(), [(320, 240)]);
+        assert_eq!(events.scrolls().collect::<Vec<_>>(), [(0.0, -1.0)]);
+        assert_eq!(events.into_iter().count(), batch.len());
+    }
+
+    #[test]
+    fn reset_and_clear_held_keys_drop_stale_input() {
+        let mut manager = DefaultEventManager::new();
+        let press = vec![
+            WindowEvent::KeyDown(Key::W, 0),
+            WindowEvent::MouseButtonDown(MouseButton::Button1),
+            WindowEvent::Scroll(0.0, 2.0),
+        ];
+        manager.process_events(&press);
+        manager.clear_held_keys();
+        assert!(!manager.key_pressed(Key::W));
+        assert_eq!(manager.key_held_duration(Key::W), None);
+        assert!(manager.mouse_pressed(MouseButton::Button1));
+        manager.process_events(&press);
+        manager.reset();
+        assert!(!manager.key_pressed(Key::W));
+        assert!(!manager.mouse_pressed(MouseButton::Button1));
+        assert_eq!(manager.scroll_change(), (0.0, 0.0));
+        assert_eq!(manager.total_scroll(), (0.0, 0.0));
+    }
+
+    /// Buffer kept in CPU memory, for testing the provided `GlBuffer` methods.
+    struct MemoryBuffer(std::cell::RefCell<Vec<u8>>);
+    impl Drop for MemoryBuffer {
+        fn drop(&mut self) {}
+    }
+    impl GlBindable for MemoryBuffer {
+        fn bind(&self) {}
+        fn unbind(&self) {}
+    }
+    impl GlBuffer for MemoryBuffer {
+        fn new(_: &Window, _: GlBufferType) -> Self {
+            MemoryBuffer(Default::default())
+        }
+        fn buffer_data(&self, size: usize, data: *const f32, _: GlStorageMode) {
+            let data = unsafe { std::slice::from_raw_parts(data as *const u8, size) };
+            *self.0.borrow_mut() = data.to_vec();
+        }
+        fn orphan_data(&self, size: usize, _: GlStorageMode) {
+            *self.0.borrow_mut() = vec![0; size];
+        }
+        fn buffer_sub_data(&self, start: usize, size: usize, data: *const f32) {
+            let data = unsafe { std::slice::from_raw_parts(data as *const u8, size) };
+            self.0.borrow_mut()[start..start + size].copy_from_slice(data);
+        }
+        fn get_buffer_sub_data(&self, start: usize, size: usize, recv: *mut f32) {
+            let recv = unsafe { std::slice::from_raw_parts_mut(recv as *mut u8, size) };
+            recv.copy_from_slice(&self.0.borrow()[start..start + size]);
+        }
+        fn byte_size(&self) -> usize {
+            self.0.borrow().len()
+        }
+        fn get_type(&self) -> GlBufferType {
+            GlBufferType::ArrayBuffer
+        }
+        fn bind_base(&self, _: u32) {}
+    }
+
+    fn memory_buffer(bytes: &[u8]) -> MemoryBuffer {
+        MemoryBuffer(std::cell::RefCell::new(bytes.to_vec()))
+    }
+
+    #[test]
+    fn read_typed_reinterprets_the_buffer_bytes() {
+        let shorts: [u16; 3] = [1, 0xbeef, 65535];
+        let bytes: Vec<u8> = shorts.iter().flat_map(|s| s.to_ne_bytes()).collect();
+        // 6 bytes is not a whole number of floats, so the f32 storage is rounded up
+        assert_eq!(memory_buffer(&bytes).read_typed::<u16>(), shorts);
+        assert_eq!(memory_buffer(&bytes).read_typed::<[u8; 3]>().len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "not a whole number")]
+    fn read_typed_rejects_partial_elements() {
+        memory_buffer(&[0; 6]).read_typed::<u32>();
+    }
 }
@@ -1,3 +1,4 @@
+#[cfg(all(feature = "glfw", not(feature = "winit")))]
 use glfw::{
     Context as GlfwContext, Glfw, Window as GlfwWindow, WindowEvent as GlWindowEvent,
     WindowMode as GlWindowMode,
@@ -5,37 +6,39 @@ use glfw::{
 use std::ffi::CString;
 use std::ptr;
 extern crate gl;
-use crate::data::{CPUBuffer, DynamicImageColorable, GPUTexture};
+use crate::data::{image_from_bytes, GPUBuffer, GPUTexture, TextureLoadOptions};
+#[cfg(all(feature = "glfw", not(feature = "winit")))]
+use crate::data::{CPUBuffer, DynamicImageColorable};
+use crate::error::CharWinError;
+#[cfg(all(feature = "glfw", not(feature = "winit")))]
 use crate::input::{Key, MouseButton};
-use crate::platform::{Context, Window};
+#[cfg(all(feature = "glfw", not(feature = "winit")))]
+use crate::platform::Context;
+use crate::platform::Window;
+#[cfg(all(feature = "glfw", not(feature = "winit")))]
 use crate::state::{FrameManager, State};
 use crate::window::*;
 use gl::types::{GLbitfield, GLchar, GLenum, GLint, GLintptr, GLsizei, GLsizeiptr, GLuint, GLvoid};
 use image::io::Reader as ImageReader;
-use image::DynamicImage;
+use image::{DynamicImage, ImageFormat};
 use std::collections::{HashMap, HashSet};
 use std::sync::mpsc::{channel, Receiver, TryRecvError};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::thread::JoinHandle;
 
+#[cfg(all(feature = "glfw", not(feature = "winit")))]
 pub struct NativeGlWindow {
     glfw: Glfw,
     window: GlfwWindow,
     gl_events: Receiver<(f64, GlWindowEvent)>,
     events: Vec<WindowEvent>,
-    image_load_threads: HashMap<
-        u32,
-        (
-            Arc<Mutex<GPUTexture>>,
-            JoinHandle<DynamicImage>,
-            Receiver<()>,
-            Option<u32>,
-        ),
-    >,
-    image_thread_count: u32,
+    image_threads: ImageLoadThreads,
+    swap_interval: i32,
+    max_delta: Option<f64>,
 }
 
+#[cfg(all(feature = "glfw", not(feature = "winit")))]
 impl NativeGlWindow {
     pub fn render_loop<S: State, E: EventManager>(mut self, mut state: S, mut manager: E) {
         {
@@ -48,12 +51,14 @@ impl NativeGlWindow {
             manager.process_events(&init_events);
         }
         let mut fm = FrameManager::new(None);
+        fm.set_max_delta(self.max_delta);
         let mut state_res = state.initialize(&mut self, &mut manager);
-        self.get_gl_errors();
+        get_gl_errors();
         if state_res == 0 {
             loop {
                 if fm.next_frame_ready() {
                     self.poll_events();
+                    manager.advance_time(fm.get_delta());
                     manager.process_events(&self.get_events());
                     state_res = state.update(&mut self, &mut manager, fm.get_delta());
                     if state_res != 0 || self.should_close() {
@@ -63,53 +68,101 @@ impl NativeGlWindow {
             }
         }
         state.destroy(&mut self, &mut manager, state_res);
-        self.get_gl_errors();
+        get_gl_errors();
     }
-    fn get_gl_errors(&self) {
-        unsafe {
-            let mut err = gl::GetError();
-            while err != gl::NO_ERROR {
-                match err {
-                    gl::INVALID_ENUM => {
-                        panic!("OpenGL: GL_INVALID_ENUM (0x0500). Enumeration parameter is not legal for function.");
-                    }
-                    gl::INVALID_VALUE => {
-                        panic!("OpenGL: GL_INVALID_VALUE (0x0501). Value parameter is not legal for function.");
-                    }
-                    gl::INVALID_OPERATION => {
-                        panic!(
-                            "OpenGL: GL_INVALID_OPERATION (0x0502). State is invalid for function."
-                        );
-                    }
-                    gl::STACK_OVERFLOW => {
-                        panic!("OpenGL: GL_STACK_OVERFLOW (0x0503). Stack pushing operation cannot be done due to stack size.");
-                    }
-                    gl::STACK_UNDERFLOW => {
-                        panic!("OpenGL: GL_STACK_UNDERFLOW (0x0504). Stack pop operation cannot be done due to stack size.");
-                    }
-                    gl::OUT_OF_MEMORY => {
-                        panic!(
-                            "OpenGL: GL_OUT_OF_MEMORY (0x0505). Cannot allocate more heap memory."
-                        );
-                    }
-                    gl::INVALID_FRAMEBUFFER_OPERATION => {
-                        panic!("OpenGL: GL_INVALID_FRAMEBUFFER_OPERATION (0x0506)");
-                    }
-                    gl::CONTEXT_LOST => {
-                        panic!("OpenGL: GL_CONTEXT_LOST (0x0507)");
-                    }
-                    _ => {
-                        println!("OpenGL: Error code: {}.", err);
-                    }
+}
+
+/// Panics on any pending OpenGL error.
+pub(crate) fn get_gl_errors() {
+    unsafe {
+        let mut err = gl::GetError();
+        while err != gl::NO_ERROR {
+            match err {
+                gl::INVALID_ENUM => {
+                    panic!("OpenGL: GL_INVALID_ENUM (0x0500). Enumeration parameter is not legal for function.");
+                }
+                gl::INVALID_VALUE => {
+                    panic!("OpenGL: GL_INVALID_VALUE (0x0501). Value parameter is not legal for function.");
+                }
+                gl::INVALID_OPERATION => {
+                    panic!(
+                        "OpenGL: GL_INVALID_OPERATION (0x0502). State is invalid for function."
+                    );
+                }
+                gl::STACK_OVERFLOW => {
+                    panic!("OpenGL: GL_STACK_OVERFLOW (0x0503). Stack pushing operation cannot be done due to stack size.");
+                }
+                gl::STACK_UNDERFLOW => {
+                    panic!("OpenGL: GL_STACK_UNDERFLOW (0x0504). Stack pop operation cannot be done due to stack size.");
+                }
+                gl::OUT_OF_MEMORY => {
+                    panic!(
+                        "OpenGL: GL_OUT_OF_MEMORY (0x0505). Cannot allocate more heap memory."
+                    );
+                }
+                gl::INVALID_FRAMEBUFFER_OPERATION => {
+                    panic!("OpenGL: GL_INVALID_FRAMEBUFFER_OPERATION (0x0506)");
+                }
+                gl::CONTEXT_LOST => {
+                    panic!("OpenGL: GL_CONTEXT_LOST (0x0507)");
+                }
+                _ => {
+                    println!("OpenGL: Error code: {}.", err);
                 }
-                err = gl::GetError();
             }
+            err = gl::GetError();
         }
     }
-    fn poll_image_threads(&mut self) {
-        let mut completed_threads =
-            Vec::<(bool, u32)>::with_capacity(self.image_load_threads.len());
-        for (index, thread) in self.image_load_threads.iter() {
+}
+
+/// A texture waiting on its decode thread, with the thread's completion signal and the
+/// requested mip levels.
+type PendingImage = (
+    Arc<Mutex<GPUTexture>>,
+    JoinHandle<DynamicImage>,
+    Receiver<()>,
+    Option<u32>,
+);
+
+/// Images decoding on background threads, uploaded to their textures once finished.
+/// Shared by the native window backends.
+#[derive(Default)]
+pub(crate) struct ImageLoadThreads {
+    threads: HashMap<u32, PendingImage>,
+    count: u32,
+}
+impl ImageLoadThreads {
+    pub fn spawn(
+        &mut self,
+        tex: Arc<Mutex<GPUTexture>>,
+        path: &str,
+        options: TextureLoadOptions,
+    ) {
+        let (sender, reciever) = channel();
+        let image_src: String = path.into();
+        let handle = thread::spawn(move || {
+            let ret = ImageReader::open(&image_src)
+                .unwrap_or_else(|e| {
+                    panic!("Could not load image \"{}\": {:?}.", image_src, e);
+                })
+                .decode()
+                .unwrap_or_else(|e| {
+                    panic!("Could not decode image \"{}\": {:?}.", image_src, e);
+                });
+            let ret = options.prepare(ret);
+            sender.send(()).unwrap_or_else(|e| {
+                panic!("Could not send message: {:?}", e);
+            });
+            ret
+        });
+        self.threads
+            .insert(self.count, (tex, handle, reciever, options.mips));
+        self.count += 1;
+    }
+    /// Uploads the images of any finished threads. Must be called on the GL thread.
+    pub fn poll(&mut self) {
+        let mut completed_threads = Vec::<(bool, u32)>::with_capacity(self.threads.len());
+        for (index, thread) in self.threads.iter() {
             match thread.2.try_recv() {
                 Ok(_) => {
                     completed_threads.push((true, *index));
@@ -123,7 +176,7 @@ impl NativeGlWindow {
         }
         for (success, index) in completed_threads {
             if success {
-                let (tex, thread, _, mips) = self.image_load_threads.remove(&index).unwrap();
+                let (tex, thread, _, mips) = self.threads.remove(&index).unwrap();
                 match thread.join() {
                     Ok(data) => {
                         tex.lock().unwrap().set_data_mips(&data, mips);
@@ -133,12 +186,27 @@ impl NativeGlWindow {
                     }
                 }
             } else {
-                self.image_load_threads.remove(&index);
+                self.threads.remove(&index);
             }
         }
     }
 }
 
+/// Decodes an in-memory image and uploads it synchronously. Shared by the native window
+/// backends.
+pub(crate) fn texture_from_bytes(
+    win: &mut Window,
+    bytes: &[u8],
+    format_hint: Option<ImageFormat>,
+    options: TextureLoadOptions,
+) -> Result<Arc<Mutex<GPUTexture>>, CharWinError> {
+    let image = options.prepare(image_from_bytes(bytes, format_hint)?);
+    let mut tex = GPUTexture::new(win);
+    tex.set_data_mips(&image, options.mips);
+    Ok(Arc::new(Mutex::new(tex)))
+}
+
+#[cfg(all(feature = "glfw", not(feature = "winit")))]
 impl AbstractWindow for NativeGlWindow {
     fn get_gl_context(&mut self) -> Context {
         Context::new(self)
@@ -166,7 +234,7 @@ impl AbstractWindow for NativeGlWindow {
                 self.events.push(event);
             }
         }
-        self.poll_image_threads();
+        self.image_threads.poll();
     }
     fn get_events(&mut self) -> Vec<WindowEvent> {
         let mut events = Vec::new();
@@ -178,6 +246,17 @@ impl AbstractWindow for NativeGlWindow {
     fn swap_buffers(&mut self) {
         self.window.swap_buffers();
     }
+    fn set_swap_interval(&mut self, interval: i32) {
+        self.glfw.set_swap_interval(match interval {
+            0 => glfw::SwapInterval::None,
+            i if i < 0 => glfw::SwapInterval::Adaptive,
+            i => glfw::SwapInterval::Sync(i as u32),
+        });
+        self.swap_interval = interval.max(-1);
+    }
+    fn swap_interval(&self) -> i32 {
+        self.swap_interval
+    }
     fn close(&mut self) {
         self.window.set_should_close(true);
     }
@@ -187,38 +266,42 @@ impl AbstractWindow for NativeGlWindow {
     fn get_pos(&self) -> (i32, i32) {
         self.window.get_pos()
     }
-    fn load_texture_rgba(&mut self, path: &str, mips: Option<u32>) -> Arc<Mutex<GPUTexture>> {
+    fn load_texture_rgba_with_placeholder(
+        &mut self,
+        path: &str,
+        options: TextureLoadOptions,
+        placeholder: [u8; 4],
+    ) -> Arc<Mutex<GPUTexture>> {
         let tex = Arc::new(Mutex::new(
-            DynamicImage::solid_color([0xff, 0x80, 0xff, 0xff]).to_gpu_buffer(self),
+            DynamicImage::solid_color(placeholder).to_gpu_buffer(self),
         ));
-        let (sender, reciever) = channel();
-        let image_src: String = path.into();
-        let handle = thread::spawn(move || {
-            let ret = ImageReader::open(&image_src)
-                .unwrap_or_else(|e| {
-                    panic!("Could not load image \"{}\": {:?}.", image_src, e);
-                })
-                .decode()
-                .unwrap_or_else(|e| {
-                    panic!("Could not decode image \"{}\": {:?}.", image_src, e);
-                });
-            sender.send(()).unwrap_or_else(|e| {
-                panic!("Could not send message: {:?}", e);
-            });
-            ret
-        });
-        self.image_load_threads.insert(
-            self.image_thread_count,
-            (Arc::clone(&tex), handle, reciever, mips),
-        );
-        self.image_thread_count += 1;
+        self.image_threads.spawn(Arc::clone(&tex), path, options);
         tex
     }
+    fn try_load_texture_from_bytes(
+        &mut self,
+        bytes: &[u8],
+        format_hint: Option<ImageFormat>,
+        options: TextureLoadOptions,
+    ) -> Result<Arc<Mutex<GPUTexture>>, CharWinError> {
+        texture_from_bytes(self, bytes, format_hint, options)
+    }
 }
 
+#[cfg(all(feature = "glfw", not(feature = "winit")))]
 impl AbstractWindowFactory for NativeGlWindow {
     fn create(args: &WindowCreateArgs) -> Self {
         let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).expect("GLFW: Initialization failed.");
+        if let Some((major, minor)) = args.gl_version {
+            glfw.window_hint(glfw::WindowHint::ContextVersion(major, minor));
+            if args.core_profile {
+                glfw.window_hint(glfw::WindowHint::OpenGlProfile(
+                    glfw::OpenGlProfileHint::Core,
+                ));
+                // macOS only provides core contexts when they are forward compatible
+                glfw.window_hint(glfw::WindowHint::OpenGlForwardCompat(true));
+            }
+        }
         let (mut glfw_window, glfw_events) = glfw
             .create_window(args.width, args.height, &args.title, GlWindowMode::Windowed)
             .expect("GLFW: Failed to create a window.");
@@ -246,12 +329,14 @@ impl AbstractWindowFactory for NativeGlWindow {
             window: glfw_window,
             gl_events: glfw_events,
             events: Vec::new(),
-            image_load_threads: HashMap::new(),
-            image_thread_count: 0,
+            image_threads: ImageLoadThreads::default(),
+            swap_interval: 0,
+            max_delta: args.max_delta(),
         }
     }
 }
 
+#[cfg(all(feature = "glfw", not(feature = "winit")))]
 fn gl_event_to_window_event(gl_event: GlWindowEvent) -> Option<WindowEvent> {
     let event = match gl_event {
         GlWindowEvent::Pos(x, y) => WindowEvent::Position(x, y),
@@ -323,6 +408,69 @@ impl NativeGlContext {
             }
         }
     }
+    fn gl_string(name: GLenum) -> String {
+        unsafe {
+            let ptr = gl::GetString(name);
+            if ptr.is_null() {
+                String::new()
+            } else {
+                std::ffi::CStr::from_ptr(ptr as *const std::os::raw::c_char)
+                    .to_string_lossy()
+                    .into_owned()
+            }
+        }
+    }
+    fn gl_compare_func(f: &GlCompareFunc) -> GLenum {
+        use GlCompareFunc::*;
+        match f {
+            Never => gl::NEVER,
+            Less => gl::LESS,
+            Equal => gl::EQUAL,
+            LEqual => gl::LEQUAL,
+            Greater => gl::GREATER,
+            NotEqual => gl::NOTEQUAL,
+            GEqual => gl::GEQUAL,
+            Always => gl::ALWAYS,
+        }
+    }
+    fn gl_stencil_op(o: &GlStencilOp) -> GLenum {
+        use GlStencilOp::*;
+        match o {
+            Keep => gl::KEEP,
+            Zero => gl::ZERO,
+            Replace => gl::REPLACE,
+            Incr => gl::INCR,
+            IncrWrap => gl::INCR_WRAP,
+            Decr => gl::DECR,
+            DecrWrap => gl::DECR_WRAP,
+            Invert => gl::INVERT,
+        }
+    }
+    fn gl_blend_factor(f: &GlBlendFactor) -> GLenum {
+        use GlBlendFactor::*;
+        match f {
+            Zero => gl::ZERO,
+            One => gl::ONE,
+            SrcColor => gl::SRC_COLOR,
+            OneMinusSrcColor => gl::ONE_MINUS_SRC_COLOR,
+            DstColor => gl::DST_COLOR,
+            OneMinusDstColor => gl::ONE_MINUS_DST_COLOR,
+            SrcAlpha => gl::SRC_ALPHA,
+            OneMinusSrcAlpha => gl::ONE_MINUS_SRC_ALPHA,
+            DstAlpha => gl::DST_ALPHA,
+            OneMinusDstAlpha => gl::ONE_MINUS_DST_ALPHA,
+        }
+    }
+    fn query_blend_factor(name: GLenum) -> GlBlendFactor {
+        let mut value: GLint = 0;
+        unsafe {
+            gl::GetIntegerv(name, &mut value);
+        }
+        *GlBlendFactor::ALL
+            .iter()
+            .find(|f| Self::gl_blend_factor(f) == value as GLenum)
+            .unwrap_or(&GlBlendFactor::One)
+    }
 }
 impl GlContext for NativeGlContext {
     fn new(_: &mut Window) -> Self {
@@ -355,6 +503,13 @@ impl GlContext for NativeGlContext {
             gl::Viewport(x, y, w as i32, h as i32);
         }
     }
+    fn get_viewport(&self) -> (i32, i32, u32, u32) {
+        let mut rect = [0i32; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, rect.as_mut_ptr());
+        }
+        (rect[0], rect[1], rect[2] as u32, rect[3] as u32)
+    }
     fn enable(&mut self, feature: GlFeature) {
         self.features.insert(feature);
         unsafe { gl::Enable(Self::gl_feature(&feature)) }
@@ -366,11 +521,108 @@ impl GlContext for NativeGlContext {
     fn get_enabled_features(&self) -> Vec<GlFeature> {
         self.features.iter().map(|x| *x).collect()
     }
+    fn is_enabled(&self, feature: GlFeature) -> bool {
+        self.features.contains(&feature)
+    }
+    fn query_enabled(&self, feature: GlFeature) -> bool {
+        unsafe { gl::IsEnabled(Self::gl_feature(&feature)) == gl::TRUE }
+    }
+    fn blend_func(&self, src: GlBlendFactor, dst: GlBlendFactor) {
+        unsafe {
+            gl::BlendFunc(Self::gl_blend_factor(&src), Self::gl_blend_factor(&dst));
+        }
+    }
+    fn get_blend_func(&self) -> (GlBlendFactor, GlBlendFactor) {
+        (
+            Self::query_blend_factor(gl::BLEND_SRC_RGB),
+            Self::query_blend_factor(gl::BLEND_DST_RGB),
+        )
+    }
     fn default_depth_func(&self) {
         unsafe {
             gl::DepthFunc(gl::LEQUAL);
         }
     }
+    fn depth_func(&self, func: GlCompareFunc) {
+        unsafe {
+            gl::DepthFunc(Self::gl_compare_func(&func));
+        }
+    }
+    fn clear_depth(&self, depth: f32) {
+        unsafe {
+            gl::ClearDepth(depth as f64);
+        }
+    }
+    fn depth_range(&self, near: f32, far: f32) {
+        if !(0.0..=1.0).contains(&near) || !(0.0..=1.0).contains(&far) {
+            char_panic!(
+                "OpenGL: Depth range ({}, {}) must lie within [0, 1].",
+                near,
+                far
+            );
+        }
+        unsafe {
+            gl::DepthRange(near as f64, far as f64);
+        }
+    }
+    fn depth_mask(&self, write: bool) {
+        unsafe {
+            gl::DepthMask(if write { gl::TRUE } else { gl::FALSE });
+        }
+    }
+    fn polygon_offset(&self, factor: f32, units: f32) {
+        unsafe {
+            gl::PolygonOffset(factor, units);
+        }
+    }
+    fn color_mask(&self, r: bool, g: bool, b: bool, a: bool) {
+        let gl_bool = |b: bool| if b { gl::TRUE } else { gl::FALSE };
+        unsafe {
+            gl::ColorMask(gl_bool(r), gl_bool(g), gl_bool(b), gl_bool(a));
+        }
+    }
+    fn stencil_func(&self, func: GlCompareFunc, reference: i32, mask: u32) {
+        unsafe {
+            gl::StencilFunc(Self::gl_compare_func(&func), reference, mask);
+        }
+    }
+    fn stencil_op(&self, sfail: GlStencilOp, dpfail: GlStencilOp, dppass: GlStencilOp) {
+        unsafe {
+            gl::StencilOp(
+                Self::gl_stencil_op(&sfail),
+                Self::gl_stencil_op(&dpfail),
+                Self::gl_stencil_op(&dppass),
+            );
+        }
+    }
+    fn stencil_mask(&self, mask: u32) {
+        unsafe {
+            gl::StencilMask(mask);
+        }
+    }
+    fn read_depth(&self, x: i32, y: i32) -> f32 {
+        let mut depth: f32 = 1.0;
+        unsafe {
+            gl::ReadPixels(
+                x,
+                y,
+                1,
+                1,
+                gl::DEPTH_COMPONENT,
+                gl::FLOAT,
+                &mut depth as *mut f32 as *mut GLvoid,
+            );
+        }
+        depth
+    }
+    fn info(&self) -> GlInfo {
+        GlInfo {
+            version: Self::gl_string(gl::VERSION),
+            vendor: Self::gl_string(gl::VENDOR),
+            renderer: Self::gl_string(gl::RENDERER),
+            glsl_version: Self::gl_string(gl::SHADING_LANGUAGE_VERSION),
+        }
+    }
 }
 
 pub struct NativeGlBuffer {
@@ -403,6 +655,7 @@ impl NativeGlBuffer {
         match t {
             Static => gl::STATIC_DRAW,
             Dynamic => gl::DYNAMIC_DRAW,
+            Stream => gl::STREAM_DRAW,
         }
     }
 }
@@ -433,6 +686,11 @@ impl GlBuffer for NativeGlBuffer {
     fn get_type(&self) -> GlBufferType {
         self.buff_type
     }
+    fn bind_base(&self, index: u32) {
+        unsafe {
+            gl::BindBufferBase(self.gl_buff_type, index as GLuint, self.vbo);
+        }
+    }
     fn buffer_data(&self, size: usize, data: *const f32, mode: GlStorageMode) {
         unsafe {
             gl::BufferData(
@@ -443,6 +701,16 @@ impl GlBuffer for NativeGlBuffer {
             );
         }
     }
+    fn orphan_data(&self, size: usize, mode: GlStorageMode) {
+        unsafe {
+            gl::BufferData(
+                self.gl_buff_type,
+                size as isize,
+                ptr::null(),
+                Self::storage_mode(&mode),
+            );
+        }
+    }
     fn buffer_sub_data(&self, start: usize, size: usize, data: *const f32) {
         unsafe {
             gl::BufferSubData(
@@ -463,6 +731,13 @@ impl GlBuffer for NativeGlBuffer {
             );
         }
     }
+    fn byte_size(&self) -> usize {
+        let mut size: GLint = 0;
+        unsafe {
+            gl::GetBufferParameteriv(self.gl_buff_type, gl::BUFFER_SIZE, &mut size);
+        }
+        size as usize
+    }
 }
 impl Drop for NativeGlBuffer {
     fn drop(&mut self) {
@@ -472,9 +747,90 @@ impl Drop for NativeGlBuffer {
     }
 }
 
+pub struct NativeGlQuery {
+    pub query: GLuint,
+    target: GlQueryTarget,
+    gl_target: GLenum,
+}
+impl NativeGlQuery {
+    fn query_target(t: &GlQueryTarget) -> GLenum {
+        use GlQueryTarget::*;
+        match t {
+            TimeElapsed => gl::TIME_ELAPSED,
+            SamplesPassed => gl::SAMPLES_PASSED,
+            AnySamplesPassed => gl::ANY_SAMPLES_PASSED,
+            AnySamplesPassedConservative => gl::ANY_SAMPLES_PASSED_CONSERVATIVE,
+            PrimitivesGenerated => gl::PRIMITIVES_GENERATED,
+            TransformFeedbackPrimitivesWritten => gl::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN,
+        }
+    }
+}
+impl GlQuery for NativeGlQuery {
+    fn new(_: &Window, target: GlQueryTarget) -> Self {
+        let mut q = gl::NONE;
+        unsafe {
+            gl::GenQueries(1, &mut q);
+        }
+        Self {
+            query: q,
+            target,
+            gl_target: Self::query_target(&target),
+        }
+    }
+    fn begin(&self) {
+        unsafe {
+            gl::BeginQuery(self.gl_target, self.query);
+        }
+    }
+    fn end(&self) {
+        unsafe {
+            gl::EndQuery(self.gl_target);
+        }
+    }
+    fn result_available(&self) -> bool {
+        let mut available: GLint = 0;
+        unsafe {
+            gl::GetQueryObjectiv(self.query, gl::QUERY_RESULT_AVAILABLE, &mut available);
+        }
+        available != 0
+    }
+    fn result(&self) -> u64 {
+        let mut result: u64 = 0;
+        unsafe {
+            gl::GetQueryObjectui64v(self.query, gl::QUERY_RESULT, &mut result);
+        }
+        result
+    }
+    fn get_target(&self) -> GlQueryTarget {
+        self.target
+    }
+}
+impl Drop for NativeGlQuery {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteQueries(1, &self.query);
+        }
+    }
+}
+
 pub struct NativeGlVertexArray {
     pub vao: GLuint,
 }
+impl NativeGlVertexArray {
+    pub fn gl_attrib_type(t: &GlVertexAttribType) -> GLenum {
+        use GlVertexAttribType::*;
+        match t {
+            Byte => gl::BYTE,
+            UnsignedByte => gl::UNSIGNED_BYTE,
+            Short => gl::SHORT,
+            UnsignedShort => gl::UNSIGNED_SHORT,
+            Int => gl::INT,
+            UnsignedInt => gl::UNSIGNED_INT,
+            HalfFloat => gl::HALF_FLOAT,
+            Float => gl::FLOAT,
+        }
+    }
+}
 impl GlBindable for NativeGlVertexArray {
     fn bind(&self) {
         unsafe {
@@ -500,8 +856,23 @@ impl GlVertexArray for NativeGlVertexArray {
             gl::VertexAttribPointer(
                 (a.0) as GLuint,
                 (a.1) as GLint,
-                gl::FLOAT,
-                gl::FALSE,
+                Self::gl_attrib_type(&a.4),
+                if a.5 { gl::TRUE } else { gl::FALSE },
+                (a.2) as GLsizei,
+                ((a.3) as GLuint) as *const GLvoid,
+            );
+            gl::EnableVertexAttribArray((a.0) as GLuint);
+        }
+    }
+    fn attrib_ptr_i(&self, a: &VertexAttrib) {
+        if let GlVertexAttribType::HalfFloat | GlVertexAttribType::Float = a.4 {
+            panic!("OpenGL: Integer vertex attribute cannot have type {:?}.", a.4);
+        }
+        unsafe {
+            gl::VertexAttribIPointer(
+                (a.0) as GLuint,
+                (a.1) as GLint,
+                Self::gl_attrib_type(&a.4),
                 (a.2) as GLsizei,
                 ((a.3) as GLuint) as *const GLvoid,
             );
@@ -603,11 +974,58 @@ impl Drop for NativeGlShader {
 pub struct NativeGlShaderLoc {
     pub loc: GLint,
 }
-impl GlShaderLoc for NativeGlShaderLoc {}
+impl GlShaderLoc for NativeGlShaderLoc {
+    fn exists(&self) -> bool {
+        self.loc != -1
+    }
+}
 pub struct NativeGlProgram {
     pub program: GLuint,
 }
+type GetActiveFn =
+    unsafe fn(GLuint, GLuint, GLsizei, *mut GLsizei, *mut GLint, *mut GLenum, *mut GLchar);
 impl NativeGlProgram {
+    fn active_info(
+        &self,
+        count: GLenum,
+        max_len: GLenum,
+        get_active: GetActiveFn,
+    ) -> Vec<UniformInfo> {
+        unsafe {
+            let mut n = 0;
+            let mut buf_len = 0;
+            gl::GetProgramiv(self.program, count, &mut n);
+            gl::GetProgramiv(self.program, max_len, &mut buf_len);
+            let mut buf = vec![0u8; buf_len.max(1) as usize];
+            (0..n as GLuint)
+                .map(|i| {
+                    let mut len = 0;
+                    let mut size = 0;
+                    let mut gl_type = 0;
+                    get_active(
+                        self.program,
+                        i,
+                        buf.len() as GLsizei,
+                        &mut len,
+                        &mut size,
+                        &mut gl_type,
+                        buf.as_mut_ptr() as *mut GLchar,
+                    );
+                    UniformInfo {
+                        name: String::from_utf8_lossy(&buf[..len as usize]).into_owned(),
+                        size,
+                        gl_type,
+                    }
+                })
+                .collect()
+        }
+    }
+    fn index_type(t: &GlIndexType) -> GLenum {
+        match t {
+            GlIndexType::UnsignedShort => gl::UNSIGNED_SHORT,
+            GlIndexType::UnsignedInt => gl::UNSIGNED_INT,
+        }
+    }
     fn draw_mode(m: &GlDrawMode) -> GLenum {
         use GlDrawMode::*;
         match m {
@@ -646,6 +1064,16 @@ impl GlProgram for NativeGlProgram {
             gl::DrawArrays(Self::draw_mode(&mode), start, len);
         }
     }
+    fn draw_elements(&self, mode: GlDrawMode, count: i32, index_type: GlIndexType, offset: usize) {
+        unsafe {
+            gl::DrawElements(
+                Self::draw_mode(&mode),
+                count,
+                Self::index_type(&index_type),
+                offset as *const GLvoid,
+            );
+        }
+    }
     fn shader_loc(&self, name: &str) -> Self::ShaderLoc {
         Self::ShaderLoc {
             loc: unsafe {
@@ -659,6 +1087,43 @@ impl GlProgram for NativeGlProgram {
             gl::AttachShader(self.program, shader.shader);
         }
     }
+    fn bind_attrib_location(&self, index: u32, name: &str) {
+        unsafe {
+            let c_str = CString::new(name.as_bytes()).unwrap();
+            gl::BindAttribLocation(self.program, index, c_str.as_ptr() as *const GLchar);
+        }
+    }
+    fn attrib_location(&self, name: &str) -> i32 {
+        unsafe {
+            let c_str = CString::new(name.as_bytes()).unwrap();
+            gl::GetAttribLocation(self.program, c_str.as_ptr() as *const GLchar)
+        }
+    }
+    fn uniform_block_binding(&self, name: &str, binding: u32) -> bool {
+        unsafe {
+            let c_str = CString::new(name.as_bytes()).unwrap();
+            let index = gl::GetUniformBlockIndex(self.program, c_str.as_ptr() as *const GLchar);
+            if index == gl::INVALID_INDEX {
+                return false;
+            }
+            gl::UniformBlockBinding(self.program, index, binding);
+            true
+        }
+    }
+    fn active_uniforms(&self) -> Vec<UniformInfo> {
+        self.active_info(
+            gl::ACTIVE_UNIFORMS,
+            gl::ACTIVE_UNIFORM_MAX_LENGTH,
+            gl::GetActiveUniform,
+        )
+    }
+    fn active_attributes(&self) -> Vec<UniformInfo> {
+        self.active_info(
+            gl::ACTIVE_ATTRIBUTES,
+            gl::ACTIVE_ATTRIBUTE_MAX_LENGTH,
+            gl::GetActiveAttrib,
+        )
+    }
     fn link_program(&self) {
         unsafe {
             gl::LinkProgram(self.program);
@@ -760,8 +1225,68 @@ impl Drop for NativeGlProgram {
 pub struct NativeGlTexture2D {
     slot: u32,
     tex: GLuint,
+    wrap: (GlTextureWrap, GlTextureWrap),
+    border_color: [f32; 4],
+    unpack_alignment: u32,
 }
 impl NativeGlTexture2D {
+    pub fn gl_texture_filter(f: &GlTextureFilter) -> GLenum {
+        match f {
+            GlTextureFilter::Nearest => gl::NEAREST,
+            GlTextureFilter::Linear => gl::LINEAR,
+        }
+    }
+    pub fn gl_image_access(a: &GlImageAccess) -> GLenum {
+        use GlImageAccess::*;
+        match a {
+            ReadOnly => gl::READ_ONLY,
+            WriteOnly => gl::WRITE_ONLY,
+            ReadWrite => gl::READ_WRITE,
+        }
+    }
+    /// Binds level 0 of the texture to image `unit` for `imageLoad`/`imageStore`.
+    /// `format` must match the image format declared in the shader. Requires GL 4.2.
+    pub fn bind_image(&self, unit: u32, access: GlImageAccess, format: GlInternalTextureFormat) {
+        unsafe {
+            gl::BindImageTexture(
+                unit,
+                self.tex,
+                0,
+                gl::FALSE,
+                0,
+                Self::gl_image_access(&access),
+                Self::gl_internal_fmt(&format),
+            );
+        }
+    }
+    pub fn gl_texture_wrap(w: &GlTextureWrap) -> GLenum {
+        use GlTextureWrap::*;
+        match w {
+            Repeat => gl::REPEAT,
+            MirroredRepeat => gl::MIRRORED_REPEAT,
+            ClampToEdge => gl::CLAMP_TO_EDGE,
+            ClampToBorder => gl::CLAMP_TO_BORDER,
+        }
+    }
+    fn apply_wrap(&self) {
+        unsafe {
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_S,
+                Self::gl_texture_wrap(&self.wrap.0) as i32,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_WRAP_T,
+                Self::gl_texture_wrap(&self.wrap.1) as i32,
+            );
+            gl::TexParameterfv(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_BORDER_COLOR,
+                self.border_color.as_ptr(),
+            );
+        }
+    }
     pub fn gl_texture_type(t: &GlTextureType) -> u32 {
         use GlTextureType::*;
         match t {
@@ -824,6 +1349,9 @@ impl NativeGlTexture2D {
             RGBA8I => gl::RGBA8I,
             RGBA16I => gl::RGBA16I,
             RGBA32I => gl::RGBA32I,
+            DepthComponent16 => gl::DEPTH_COMPONENT16,
+            DepthComponent24 => gl::DEPTH_COMPONENT24,
+            DepthComponent32F => gl::DEPTH_COMPONENT32F,
             _ => {
                 panic!(
                     "OpenGL: GlInternalTextureFormat {:?} not supported natively.",
@@ -864,9 +1392,8 @@ impl NativeGlTexture2D {
         }
     }
     pub fn set_params(&self, mips: Option<u32>) {
+        self.apply_wrap();
         unsafe {
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::REPEAT as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::REPEAT as i32);
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
             if mips == Some(0) {
                 gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
@@ -904,6 +1431,9 @@ impl GlTexture2D for NativeGlTexture2D {
         Self {
             tex,
             slot: gl::TEXTURE0,
+            wrap: (GlTextureWrap::Repeat, GlTextureWrap::Repeat),
+            border_color: [0.0, 0.0, 0.0, 0.0],
+            unpack_alignment: 1,
         }
     }
     fn set_texture(
@@ -931,6 +1461,7 @@ impl GlTexture2D for NativeGlTexture2D {
                 }
                 None
             };
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, self.unpack_alignment as i32);
             gl::TexImage2D(
                 gl::TEXTURE_2D,
                 mips.unwrap_or_else(|| 0) as i32,
@@ -942,12 +1473,53 @@ impl GlTexture2D for NativeGlTexture2D {
                 Self::gl_px_fmt(&px_type),
                 tex_ptr as *const GLvoid,
             );
+            // Restore the GL default for any other uploads
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
             self.set_params(mipmaps);
         }
     }
     fn set_slot(&mut self, slot: u32) {
         self.slot = gl::TEXTURE0 + slot;
     }
+    fn set_unpack_alignment(&mut self, alignment: u32) {
+        if ![1, 2, 4, 8].contains(&alignment) {
+            char_panic!("Unpack alignment must be 1, 2, 4 or 8, got {}.", alignment);
+        }
+        self.unpack_alignment = alignment;
+    }
+    fn set_wrap(&mut self, s: GlTextureWrap, t: GlTextureWrap) {
+        self.wrap = (s, t);
+        self.apply_wrap();
+    }
+    fn set_border_color(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        self.border_color = [r, g, b, a];
+        self.apply_wrap();
+    }
+    fn set_mip_range(&mut self, base: u32, max: u32) {
+        unsafe {
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_BASE_LEVEL, base as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, max as i32);
+        }
+    }
+    fn set_lod_bias(&mut self, bias: f32) {
+        unsafe {
+            gl::TexParameterf(gl::TEXTURE_2D, gl::TEXTURE_LOD_BIAS, bias);
+        }
+    }
+    fn set_filter(&mut self, min: GlTextureFilter, mag: GlTextureFilter) {
+        unsafe {
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_MIN_FILTER,
+                Self::gl_texture_filter(&min) as i32,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D,
+                gl::TEXTURE_MAG_FILTER,
+                Self::gl_texture_filter(&mag) as i32,
+            );
+        }
+    }
 }
 impl Drop for NativeGlTexture2D {
     fn drop(&mut self) {
@@ -956,3 +1528,82 @@ impl Drop for NativeGlTexture2D {
         }
     }
 }
+
+pub struct NativeGlFramebuffer {
+    fbo: GLuint,
+}
+impl NativeGlFramebuffer {
+    pub fn gl_attachment(a: &GlFramebufferAttachment) -> GLenum {
+        use GlFramebufferAttachment::*;
+        match a {
+            Color0 => gl::COLOR_ATTACHMENT0,
+            Depth => gl::DEPTH_ATTACHMENT,
+            Stencil => gl::STENCIL_ATTACHMENT,
+            DepthStencil => gl::DEPTH_STENCIL_ATTACHMENT,
+        }
+    }
+}
+impl GlBindable for NativeGlFramebuffer {
+    fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+        }
+    }
+    fn unbind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+    }
+}
+impl GlFramebuffer for NativeGlFramebuffer {
+    fn new(_: &Window) -> Self {
+        let mut fbo = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut fbo);
+        }
+        Self { fbo }
+    }
+    fn attach_texture_2d(&self, attachment: GlFramebufferAttachment, tex: &NativeGlTexture2D) {
+        unsafe {
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                Self::gl_attachment(&attachment),
+                gl::TEXTURE_2D,
+                tex.tex,
+                0,
+            );
+        }
+    }
+    fn set_depth_only(&self) {
+        unsafe {
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+        }
+    }
+    fn is_complete(&self) -> bool {
+        unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) == gl::FRAMEBUFFER_COMPLETE }
+    }
+}
+impl Drop for NativeGlFramebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.fbo);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn texture_wraps_map_to_their_gl_constants() {
+        use GlTextureWrap::*;
+        let wraps = [Repeat, MirroredRepeat, ClampToEdge, ClampToBorder]
+            .map(|w| NativeGlTexture2D::gl_texture_wrap(&w));
+        assert_eq!(
+            wraps,
+            [gl::REPEAT, gl::MIRRORED_REPEAT, gl::CLAMP_TO_EDGE, gl::CLAMP_TO_BORDER]
+        );
+    }
+}
@@ -1,22 +1,26 @@
 use crate::char_panic;
-use crate::data::{CPUBuffer, DynamicImageColorable, GPUTexture};
+use crate::data::{
+    image_from_bytes, CPUBuffer, DynamicImageColorable, GPUBuffer, GPUTexture, TextureLoadOptions,
+};
+use crate::error::CharWinError;
 use crate::input::{Key, MouseButton};
 use crate::platform::{Context, Window};
-use crate::state::State;
+use crate::state::{State, CRASH_EXIT_CODE};
 use crate::window::*;
-use image::DynamicImage;
-use js_sys::{Float32Array, Uint8Array};
+use image::{DynamicImage, ImageFormat};
+use js_sys::{Float32Array, Int32Array, Uint8Array};
 use std::cell::RefCell;
 use std::collections::HashSet;
 use std::mem::size_of;
 use std::rc::Rc;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use web_sys::{
     Event, HtmlCanvasElement, HtmlImageElement, KeyboardEvent, MouseEvent, WebGl2RenderingContext,
-    WebGlBuffer as JsSysWebGlBuffer, WebGlProgram as JsSysWebGlProgram,
-    WebGlShader as JsSysWebGlShader, WebGlTexture as JsSysWebGlTexture,
+    WebGlActiveInfo, WebGlBuffer as JsSysWebGlBuffer, WebGlFramebuffer as JsSysWebGlFramebuffer,
+    WebGlProgram as JsSysWebGlProgram,
+    WebGlQuery as JsSysWebGlQuery, WebGlShader as JsSysWebGlShader, WebGlTexture as JsSysWebGlTexture,
     WebGlUniformLocation as JsSysWebGlUniformLocation,
     WebGlVertexArrayObject as JsSysWebGlVertexArray, WheelEvent,
 };
@@ -33,6 +37,25 @@ extern "C" {
     fn alert(a: &str);
 }
 
+/// Locks the WebGL context, returning `CharWinError::ContextPoisoned` (and logging it) if
+/// an earlier panic left the mutex poisoned. Panics on wasm abort without unwinding, so
+/// they leave the mutex locked instead; the context is only used from the main thread, so
+/// a lock that is already held is reported the same way.
+pub fn try_lock_context<T>(context: &Mutex<T>) -> Result<MutexGuard<'_, T>, CharWinError> {
+    context.try_lock().map_err(|_| {
+        let err = CharWinError::ContextPoisoned;
+        js_err_string(&format!("{}", err));
+        err
+    })
+}
+
+/// Locks the WebGL context for a GL call. The context is only a handle, so a panic cannot
+/// leave it half updated; a poisoned lock is recovered instead of making every later call
+/// panic too. The render loop reports the poisoning through `try_lock_context`.
+fn lock_context(context: &Mutex<WebGl2RenderingContext>) -> MutexGuard<'_, WebGl2RenderingContext> {
+    context.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
 #[wasm_bindgen]
 pub struct WebWindowEventSet {
     events: Vec<WindowEvent>,
@@ -91,6 +114,7 @@ pub struct WebGlWindow {
     should_close: bool,
     event_listener: Option<Arc<Mutex<WebEventListener>>>,
     events: Vec<WindowEvent>,
+    max_delta: Option<f64>,
 }
 
 #[wasm_bindgen]
@@ -192,6 +216,12 @@ impl AbstractWindow for WebGlWindow {
     fn swap_buffers(&mut self) {
         // Does nothing on WASM
     }
+    fn set_swap_interval(&mut self, _interval: i32) {
+        // The browser presents frames in sync with the display
+    }
+    fn swap_interval(&self) -> i32 {
+        1
+    }
     fn get_size(&self) -> (i32, i32) {
         let bounding_rect = self.canvas.lock().unwrap().get_bounding_client_rect();
         (bounding_rect.width() as i32, bounding_rect.height() as i32)
@@ -200,9 +230,19 @@ impl AbstractWindow for WebGlWindow {
         let bounding_rect = self.canvas.lock().unwrap().get_bounding_client_rect();
         (bounding_rect.x() as i32, bounding_rect.y() as i32)
     }
-    fn load_texture_rgba(&mut self, path: &str, mipmaps: Option<u32>) -> Arc<Mutex<GPUTexture>> {
+    fn load_texture_rgba_with_placeholder(
+        &mut self,
+        path: &str,
+        options: TextureLoadOptions,
+        placeholder: [u8; 4],
+    ) -> Arc<Mutex<GPUTexture>> {
+        let TextureLoadOptions {
+            mips: mipmaps,
+            flip_y,
+            premultiply_alpha,
+        } = options;
         let tex = Arc::new(Mutex::new(
-            DynamicImage::solid_color([0xff, 0x80, 0xff, 0xff]).to_gpu_buffer(self),
+            DynamicImage::solid_color(placeholder).to_gpu_buffer(self),
         ));
         let image = Arc::new(Mutex::new(HtmlImageElement::new().unwrap_or_else(|e| {
             char_panic!("Could not create new HtmlImageElement: {:?}.", e);
@@ -229,9 +269,13 @@ impl AbstractWindow for WebGlWindow {
                         }
                         None
                     };
-                    context_arc
-                        .lock()
-                        .unwrap()
+                    lock_context(&context_arc)
+                        .pixel_storei(WebGl2RenderingContext::UNPACK_FLIP_Y_WEBGL, flip_y as i32);
+                    lock_context(&context_arc).pixel_storei(
+                        WebGl2RenderingContext::UNPACK_PREMULTIPLY_ALPHA_WEBGL,
+                        premultiply_alpha as i32,
+                    );
+                    lock_context(&context_arc)
                         .tex_image_2d_with_u32_and_u32_and_html_image_element(
                             WebGl2RenderingContext::TEXTURE_2D,
                             mips.unwrap_or_else(|| 0) as i32,
@@ -243,6 +287,10 @@ impl AbstractWindow for WebGlWindow {
                         .unwrap_or_else(|e| {
                             js_err_string(&format!("WebGL: Could not texture image: {:?}", e));
                         });
+                    lock_context(&context_arc)
+                        .pixel_storei(WebGl2RenderingContext::UNPACK_FLIP_Y_WEBGL, 0);
+                    lock_context(&context_arc)
+                        .pixel_storei(WebGl2RenderingContext::UNPACK_PREMULTIPLY_ALPHA_WEBGL, 0);
                     tex.tex.set_params(mips);
                     tex.tex.unbind();
                 } else {
@@ -260,6 +308,17 @@ impl AbstractWindow for WebGlWindow {
         image.lock().unwrap().set_src(path);
         tex
     }
+    fn try_load_texture_from_bytes(
+        &mut self,
+        bytes: &[u8],
+        format_hint: Option<ImageFormat>,
+        options: TextureLoadOptions,
+    ) -> Result<Arc<Mutex<GPUTexture>>, CharWinError> {
+        let image = options.prepare(image_from_bytes(bytes, format_hint)?);
+        let mut tex = GPUTexture::new(self);
+        tex.set_data_mips(&image, options.mips);
+        Ok(Arc::new(Mutex::new(tex)))
+    }
 }
 
 impl AbstractWindowFactory for WebGlWindow {
@@ -276,6 +335,7 @@ impl AbstractWindowFactory for WebGlWindow {
                         should_close: false,
                         event_listener: None,
                         events: Vec::new(),
+                        max_delta: args.max_delta(),
                     };
                     window.add_event_listeners();
                     window
@@ -308,7 +368,16 @@ impl WebGlWindow {
             let g: Rc<RefCell<Option<Closure<_>>>> = f.clone();
             let mut state_initialized = false;
             let mut last_frame = js_sys::Date::now();
+            let max_delta = self.max_delta;
             *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+                let delta = (js_sys::Date::now() - last_frame) / 1000f64;
+                let delta = max_delta.map_or(delta, |max_delta| delta.min(max_delta));
+                if try_lock_context(&self.context).is_err() {
+                    // A panic in an earlier frame broke the context lock, so stop cleanly
+                    state.destroy(&mut self, &mut manager, CRASH_EXIT_CODE);
+                    let _ = f.borrow_mut().take();
+                    return;
+                }
                 if !state_initialized {
                     let rect = self.canvas.lock().unwrap().get_bounding_client_rect();
                     self.events
@@ -318,13 +387,10 @@ impl WebGlWindow {
                     state_initialized = true;
                 } else {
                     self.poll_events();
+                    manager.advance_time(delta);
                     manager.process_events(&self.get_events());
                 }
-                let update_res = state.update(
-                    &mut self,
-                    &mut manager,
-                    (js_sys::Date::now() - last_frame) / 1000f64,
-                );
+                let update_res = state.update(&mut self, &mut manager, delta);
                 if update_res == 0 && !self.should_close {
                     last_frame = js_sys::Date::now();
                     let _ = web_sys::window().unwrap().request_animation_frame(
@@ -354,11 +420,8 @@ impl Drop for WebGlWindow {
                 char_panic!("DOM: Cannot remove event listeners: mutex poisoned.");
             }
         }
-        if let Ok(mut _context) = self.context.lock() {
-            // Drop context
-        } else {
-            char_panic!("DOM: Cannot drop canvas context: mutex poisoned.");
-        }
+        // Drop context. A poisoned or held lock was already reported by the render loop.
+        let _context = self.context.try_lock();
     }
 }
 
@@ -657,8 +720,22 @@ impl WebGlWindow {
     }
 }
 
+/// Maps the physical key of a keyboard event, matching GLFW's layout-independent keys.
+/// Falls back to the legacy `keyCode` when `code` is empty or unrecognised.
 #[wasm_bindgen(js_name = keyboardEventToKey)]
 pub fn js_key_to_key(k: &KeyboardEvent) -> Key {
+    match js_code_to_key(&k.code()) {
+        Key::Unknown => js_key_code_to_key(k),
+        key => key,
+    }
+}
+/// Maps a `KeyboardEvent.code` physical key name such as `"KeyW"` or `"ArrowUp"`.
+#[wasm_bindgen(js_name = codeToKey)]
+pub fn js_code_to_key(code: &str) -> Key {
+    Key::from_js_code(code)
+}
+/// Legacy mapping from the deprecated, layout-dependent `keyCode`.
+fn js_key_code_to_key(k: &KeyboardEvent) -> Key {
     let code = k.key_code() as i32;
     match code {
         32 => Key::Space,
@@ -695,6 +772,12 @@ pub fn js_key_to_key(k: &KeyboardEvent) -> Key {
         44 => Key::PrintScreen,
         19 => Key::Pause,
         112..=136 => Key::from_i32((code - 112) + 290),
+        96..=105 => Key::from_i32((code - 96) + Key::Kp0 as i32),
+        106 => Key::KpMultiply,
+        107 => Key::KpAdd,
+        109 => Key::KpSubtract,
+        110 => Key::KpDecimal,
+        111 => Key::KpDivide,
         16 => match k.code().as_str() {
             "ShiftLeft" => Key::LeftShift,
             "ShiftRight" => Key::RightShift,
@@ -752,6 +835,64 @@ impl WebGlContext {
             }
         }
     }
+    fn gl_string(context: &WebGl2RenderingContext, name: u32) -> String {
+        match context.get_parameter(name) {
+            Ok(v) => v.as_string().unwrap_or_default(),
+            Err(_) => String::new(),
+        }
+    }
+    fn gl_compare_func(f: &GlCompareFunc) -> u32 {
+        use GlCompareFunc::*;
+        match f {
+            Never => WebGl2RenderingContext::NEVER,
+            Less => WebGl2RenderingContext::LESS,
+            Equal => WebGl2RenderingContext::EQUAL,
+            LEqual => WebGl2RenderingContext::LEQUAL,
+            Greater => WebGl2RenderingContext::GREATER,
+            NotEqual => WebGl2RenderingContext::NOTEQUAL,
+            GEqual => WebGl2RenderingContext::GEQUAL,
+            Always => WebGl2RenderingContext::ALWAYS,
+        }
+    }
+    fn gl_stencil_op(o: &GlStencilOp) -> u32 {
+        use GlStencilOp::*;
+        match o {
+            Keep => WebGl2RenderingContext::KEEP,
+            Zero => WebGl2RenderingContext::ZERO,
+            Replace => WebGl2RenderingContext::REPLACE,
+            Incr => WebGl2RenderingContext::INCR,
+            IncrWrap => WebGl2RenderingContext::INCR_WRAP,
+            Decr => WebGl2RenderingContext::DECR,
+            DecrWrap => WebGl2RenderingContext::DECR_WRAP,
+            Invert => WebGl2RenderingContext::INVERT,
+        }
+    }
+    fn gl_blend_factor(f: &GlBlendFactor) -> u32 {
+        use GlBlendFactor::*;
+        match f {
+            Zero => WebGl2RenderingContext::ZERO,
+            One => WebGl2RenderingContext::ONE,
+            SrcColor => WebGl2RenderingContext::SRC_COLOR,
+            OneMinusSrcColor => WebGl2RenderingContext::ONE_MINUS_SRC_COLOR,
+            DstColor => WebGl2RenderingContext::DST_COLOR,
+            OneMinusDstColor => WebGl2RenderingContext::ONE_MINUS_DST_COLOR,
+            SrcAlpha => WebGl2RenderingContext::SRC_ALPHA,
+            OneMinusSrcAlpha => WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA,
+            DstAlpha => WebGl2RenderingContext::DST_ALPHA,
+            OneMinusDstAlpha => WebGl2RenderingContext::ONE_MINUS_DST_ALPHA,
+        }
+    }
+    fn query_blend_factor(&self, name: u32) -> GlBlendFactor {
+        let value = lock_context(&self.context)
+            .get_parameter(name)
+            .ok()
+            .and_then(|v| v.as_f64())
+            .unwrap_or(WebGl2RenderingContext::ONE as f64) as u32;
+        *GlBlendFactor::ALL
+            .iter()
+            .find(|f| Self::gl_blend_factor(f) == value)
+            .unwrap_or(&GlBlendFactor::One)
+    }
 }
 impl GlContext for WebGlContext {
     fn new(w: &mut Window) -> Self {
@@ -772,39 +913,122 @@ impl GlContext for WebGlContext {
                 _ => 0,
             };
         }
-        self.context.lock().unwrap().clear(gl_mask);
+        lock_context(&self.context).clear(gl_mask);
     }
     fn clear_color(&self, r: f32, g: f32, b: f32, a: f32) {
-        self.context.lock().unwrap().clear_color(r, g, b, a);
+        lock_context(&self.context).clear_color(r, g, b, a);
     }
     fn viewport(&self, x: i32, y: i32, w: u32, h: u32) {
-        self.context
-            .lock()
-            .unwrap()
-            .viewport(x, y, w as i32, h as i32);
+        lock_context(&self.context).viewport(x, y, w as i32, h as i32);
+    }
+    fn get_viewport(&self) -> (i32, i32, u32, u32) {
+        let context = lock_context(&self.context);
+        match context.get_parameter(WebGl2RenderingContext::VIEWPORT) {
+            Ok(v) if !v.is_null() => {
+                let rect = Int32Array::from(v).to_vec();
+                (rect[0], rect[1], rect[2] as u32, rect[3] as u32)
+            }
+            _ => (0, 0, 0, 0),
+        }
     }
     fn enable(&mut self, feature: GlFeature) {
         self.features.insert(feature);
-        self.context
-            .lock()
-            .unwrap()
-            .enable(Self::gl_feature(&feature));
+        lock_context(&self.context).enable(Self::gl_feature(&feature));
     }
     fn disable(&mut self, feature: GlFeature) {
         self.features.remove(&feature);
-        self.context
-            .lock()
-            .unwrap()
-            .disable(Self::gl_feature(&feature));
+        lock_context(&self.context).disable(Self::gl_feature(&feature));
     }
     fn get_enabled_features(&self) -> Vec<GlFeature> {
         self.features.iter().map(|x| *x).collect()
     }
+    fn is_enabled(&self, feature: GlFeature) -> bool {
+        self.features.contains(&feature)
+    }
+    fn query_enabled(&self, feature: GlFeature) -> bool {
+        lock_context(&self.context).is_enabled(Self::gl_feature(&feature))
+    }
+    fn blend_func(&self, src: GlBlendFactor, dst: GlBlendFactor) {
+        lock_context(&self.context)
+            .blend_func(Self::gl_blend_factor(&src), Self::gl_blend_factor(&dst));
+    }
+    fn get_blend_func(&self) -> (GlBlendFactor, GlBlendFactor) {
+        (
+            self.query_blend_factor(WebGl2RenderingContext::BLEND_SRC_RGB),
+            self.query_blend_factor(WebGl2RenderingContext::BLEND_DST_RGB),
+        )
+    }
     fn default_depth_func(&self) {
-        self.context
-            .lock()
-            .unwrap()
-            .depth_func(WebGl2RenderingContext::LEQUAL);
+        lock_context(&self.context).depth_func(WebGl2RenderingContext::LEQUAL);
+    }
+    fn depth_mask(&self, write: bool) {
+        lock_context(&self.context).depth_mask(write);
+    }
+    fn polygon_offset(&self, factor: f32, units: f32) {
+        lock_context(&self.context).polygon_offset(factor, units);
+    }
+    fn color_mask(&self, r: bool, g: bool, b: bool, a: bool) {
+        lock_context(&self.context).color_mask(r, g, b, a);
+    }
+    fn depth_func(&self, func: GlCompareFunc) {
+        lock_context(&self.context).depth_func(Self::gl_compare_func(&func));
+    }
+    fn clear_depth(&self, depth: f32) {
+        lock_context(&self.context).clear_depth(depth);
+    }
+    fn depth_range(&self, near: f32, far: f32) {
+        if !(0.0..=1.0).contains(&near) || !(0.0..=1.0).contains(&far) {
+            char_panic!(
+                "WebGL: Depth range ({}, {}) must lie within [0, 1].",
+                near,
+                far
+            );
+        }
+        lock_context(&self.context).depth_range(near, far);
+    }
+    fn stencil_func(&self, func: GlCompareFunc, reference: i32, mask: u32) {
+        lock_context(&self.context).stencil_func(Self::gl_compare_func(&func), reference, mask);
+    }
+    fn stencil_op(&self, sfail: GlStencilOp, dpfail: GlStencilOp, dppass: GlStencilOp) {
+        lock_context(&self.context).stencil_op(
+            Self::gl_stencil_op(&sfail),
+            Self::gl_stencil_op(&dpfail),
+            Self::gl_stencil_op(&dppass),
+        );
+    }
+    fn stencil_mask(&self, mask: u32) {
+        lock_context(&self.context).stencil_mask(mask);
+    }
+    fn read_depth(&self, _: i32, _: i32) -> f32 {
+        // Depth of the default framebuffer cannot be read back in WebGL2
+        1.0
+    }
+    fn info(&self) -> GlInfo {
+        // Values from WEBGL_debug_renderer_info
+        const UNMASKED_VENDOR_WEBGL: u32 = 0x9245;
+        const UNMASKED_RENDERER_WEBGL: u32 = 0x9246;
+        let context = lock_context(&self.context);
+        let debug_info = matches!(
+            context.get_extension("WEBGL_debug_renderer_info"),
+            Ok(Some(_))
+        );
+        let (vendor, renderer) = if debug_info {
+            (
+                Self::gl_string(&context, UNMASKED_VENDOR_WEBGL),
+                Self::gl_string(&context, UNMASKED_RENDERER_WEBGL),
+            )
+        } else {
+            (
+                Self::gl_string(&context, WebGl2RenderingContext::VENDOR),
+                Self::gl_string(&context, WebGl2RenderingContext::RENDERER),
+            )
+        };
+        GlInfo {
+            version: Self::gl_string(&context, WebGl2RenderingContext::VERSION),
+            vendor,
+            renderer,
+            glsl_version: Self::gl_string(&context, WebGl2RenderingContext::SHADING_LANGUAGE_VERSION),
+        }
     }
 }
 
@@ -837,24 +1061,22 @@ impl WebGlBuffer {
         match t {
             Static => WebGl2RenderingContext::STATIC_DRAW,
             Dynamic => WebGl2RenderingContext::DYNAMIC_DRAW,
+            Stream => WebGl2RenderingContext::STREAM_DRAW,
         }
     }
 }
 impl GlBindable for WebGlBuffer {
     fn bind(&self) {
-        self.context
-            .lock()
-            .unwrap()
-            .bind_buffer(self.gl_buff, self.buff.as_ref());
+        lock_context(&self.context).bind_buffer(self.gl_buff, self.buff.as_ref());
     }
     fn unbind(&self) {
-        self.context.lock().unwrap().bind_buffer(self.gl_buff, None);
+        lock_context(&self.context).bind_buffer(self.gl_buff, None);
     }
 }
 impl GlBuffer for WebGlBuffer {
     fn new(w: &Window, tp: GlBufferType) -> Self {
         Self {
-            buff: w.get_context_arc().lock().unwrap().create_buffer(),
+            buff: lock_context(&w.get_context_arc()).create_buffer(),
             context: w.get_context_arc(),
             buff_type: tp,
             gl_buff: Self::buff_type(&tp),
@@ -864,28 +1086,29 @@ impl GlBuffer for WebGlBuffer {
         unsafe {
             let positions_array_buf_view =
                 Float32Array::view_mut_raw(data as *mut f32, size / size_of::<f32>());
-            self.context
-                .lock()
-                .unwrap()
-                .buffer_data_with_array_buffer_view(
-                    self.gl_buff,
-                    &positions_array_buf_view,
-                    Self::storage_mode(&mode),
-                );
+            lock_context(&self.context).buffer_data_with_array_buffer_view(
+                self.gl_buff,
+                &positions_array_buf_view,
+                Self::storage_mode(&mode),
+            );
         }
     }
+    fn orphan_data(&self, size: usize, mode: GlStorageMode) {
+        lock_context(&self.context).buffer_data_with_i32(
+            self.gl_buff,
+            size as i32,
+            Self::storage_mode(&mode),
+        );
+    }
     fn buffer_sub_data(&self, start: usize, size: usize, data: *const f32) {
         unsafe {
             let positions_array_buf_view =
                 Float32Array::view_mut_raw(data as *mut f32, size / size_of::<f32>());
-            self.context
-                .lock()
-                .unwrap()
-                .buffer_sub_data_with_i32_and_array_buffer_view(
-                    self.gl_buff,
-                    start as i32,
-                    &positions_array_buf_view,
-                );
+            lock_context(&self.context).buffer_sub_data_with_i32_and_array_buffer_view(
+                self.gl_buff,
+                start as i32,
+                &positions_array_buf_view,
+            );
         }
     }
     fn get_buffer_sub_data(&self, start: usize, size: usize, recv: *mut f32) {
@@ -893,26 +1116,112 @@ impl GlBuffer for WebGlBuffer {
         let start = start / size_of::<f32>();
         unsafe {
             let positions_array_buf_view = Float32Array::view_mut_raw(recv, size);
-            self.context
-                .lock()
-                .unwrap()
-                .get_buffer_sub_data_with_i32_and_array_buffer_view(
-                    self.gl_buff,
-                    start as i32,
-                    &positions_array_buf_view,
-                );
+            lock_context(&self.context).get_buffer_sub_data_with_i32_and_array_buffer_view(
+                self.gl_buff,
+                start as i32,
+                &positions_array_buf_view,
+            );
         }
     }
+    fn byte_size(&self) -> usize {
+        lock_context(&self.context)
+            .get_buffer_parameter(self.gl_buff, WebGl2RenderingContext::BUFFER_SIZE)
+            .as_f64()
+            .unwrap_or(0.0) as usize
+    }
     fn get_type(&self) -> GlBufferType {
         self.buff_type
     }
+    fn bind_base(&self, index: u32) {
+        lock_context(&self.context).bind_buffer_base(self.gl_buff, index, self.buff.as_ref());
+    }
 }
 impl Drop for WebGlBuffer {
     fn drop(&mut self) {
-        self.context
-            .lock()
-            .unwrap()
-            .delete_buffer(self.buff.as_ref());
+        lock_context(&self.context).delete_buffer(self.buff.as_ref());
+    }
+}
+
+#[wasm_bindgen]
+pub struct WebGlQuery {
+    query: Option<JsSysWebGlQuery>,
+    context: Arc<Mutex<WebGl2RenderingContext>>,
+    target: GlQueryTarget,
+    gl_target: u32,
+}
+impl WebGlQuery {
+    fn query_target(context: &WebGl2RenderingContext, t: &GlQueryTarget) -> u32 {
+        // Value from EXT_disjoint_timer_query_webgl2
+        const TIME_ELAPSED_EXT: u32 = 0x88BF;
+        use GlQueryTarget::*;
+        match t {
+            AnySamplesPassed => WebGl2RenderingContext::ANY_SAMPLES_PASSED,
+            AnySamplesPassedConservative => {
+                WebGl2RenderingContext::ANY_SAMPLES_PASSED_CONSERVATIVE
+            }
+            TransformFeedbackPrimitivesWritten => {
+                WebGl2RenderingContext::TRANSFORM_FEEDBACK_PRIMITIVES_WRITTEN
+            }
+            TimeElapsed
+                if matches!(
+                    context.get_extension("EXT_disjoint_timer_query_webgl2"),
+                    Ok(Some(_))
+                ) =>
+            {
+                TIME_ELAPSED_EXT
+            }
+            _ => {
+                char_panic!("WebGL: Query target \"{:?}\" not supported.", t);
+            }
+        }
+    }
+}
+impl GlQuery for WebGlQuery {
+    fn new(w: &Window, target: GlQueryTarget) -> Self {
+        let context = w.get_context_arc();
+        let gl_target = Self::query_target(&lock_context(&context), &target);
+        let query = lock_context(&context).create_query();
+        Self {
+            query,
+            context,
+            target,
+            gl_target,
+        }
+    }
+    fn begin(&self) {
+        if let Some(query) = self.query.as_ref() {
+            lock_context(&self.context).begin_query(self.gl_target, query);
+        }
+    }
+    fn end(&self) {
+        lock_context(&self.context).end_query(self.gl_target);
+    }
+    fn result_available(&self) -> bool {
+        match self.query.as_ref() {
+            Some(query) => lock_context(&self.context)
+                .get_query_parameter(query, WebGl2RenderingContext::QUERY_RESULT_AVAILABLE)
+                .as_bool()
+                .unwrap_or(false),
+            None => false,
+        }
+    }
+    fn result(&self) -> u64 {
+        // WebGL never blocks on query results, so this is zero until the result is available
+        match self.query.as_ref() {
+            Some(query) => lock_context(&self.context)
+                .get_query_parameter(query, WebGl2RenderingContext::QUERY_RESULT)
+                .as_f64()
+                .unwrap_or(0.0) as u64,
+            None => 0,
+        }
+    }
+    fn get_target(&self) -> GlQueryTarget {
+        self.target
+    }
+}
+impl Drop for WebGlQuery {
+    fn drop(&mut self) {
+        lock_context(&self.context).delete_query(self.query.as_ref());
     }
 }
 
@@ -941,10 +1250,7 @@ impl GlShader for WebGlShader {
     fn new(w: &Window, st: GlShaderType) -> Self {
         let shader_type_u32 = Self::shader_type(&st);
         Self {
-            shader: w
-                .get_context_arc()
-                .lock()
-                .unwrap()
+            shader: lock_context(&w.get_context_arc())
                 .create_shader(shader_type_u32)
                 .unwrap_or_else(|| {
                     char_panic!("WebGL: Could not create shader.");
@@ -954,16 +1260,13 @@ impl GlShader for WebGlShader {
         }
     }
     fn shader_source(&self, src: &str) {
-        self.context
-            .lock()
-            .unwrap()
-            .shader_source(&self.shader, src);
+        lock_context(&self.context).shader_source(&self.shader, src);
     }
     fn compile(&self) {
-        self.context.lock().unwrap().compile_shader(&self.shader);
+        lock_context(&self.context).compile_shader(&self.shader);
     }
     fn get_compile_status(&self) -> Option<String> {
-        let gl = self.context.lock().unwrap();
+        let gl = lock_context(&self.context);
         if !gl
             .get_shader_parameter(&self.shader, WebGl2RenderingContext::COMPILE_STATUS)
             .as_bool()
@@ -987,10 +1290,7 @@ impl GlShader for WebGlShader {
 }
 impl Drop for WebGlShader {
     fn drop(&mut self) {
-        self.context
-            .lock()
-            .unwrap()
-            .delete_shader(Some(&self.shader));
+        lock_context(&self.context).delete_shader(Some(&self.shader));
     }
 }
 
@@ -999,48 +1299,70 @@ pub struct WebGlVertexArray {
     vao: Option<JsSysWebGlVertexArray>,
     context: Arc<Mutex<WebGl2RenderingContext>>,
 }
+impl WebGlVertexArray {
+    pub fn gl_attrib_type(t: &GlVertexAttribType) -> u32 {
+        use GlVertexAttribType::*;
+        match t {
+            Byte => WebGl2RenderingContext::BYTE,
+            UnsignedByte => WebGl2RenderingContext::UNSIGNED_BYTE,
+            Short => WebGl2RenderingContext::SHORT,
+            UnsignedShort => WebGl2RenderingContext::UNSIGNED_SHORT,
+            Int => WebGl2RenderingContext::INT,
+            UnsignedInt => WebGl2RenderingContext::UNSIGNED_INT,
+            HalfFloat => WebGl2RenderingContext::HALF_FLOAT,
+            Float => WebGl2RenderingContext::FLOAT,
+        }
+    }
+}
 impl GlBindable for WebGlVertexArray {
     fn bind(&self) {
-        self.context
-            .lock()
-            .unwrap()
-            .bind_vertex_array(self.vao.as_ref());
+        lock_context(&self.context).bind_vertex_array(self.vao.as_ref());
     }
     fn unbind(&self) {
-        self.context.lock().unwrap().bind_vertex_array(None);
+        lock_context(&self.context).bind_vertex_array(None);
     }
 }
 impl GlVertexArray for WebGlVertexArray {
     fn new(w: &Window) -> Self {
         Self {
-            vao: w.get_context_arc().lock().unwrap().create_vertex_array(),
+            vao: lock_context(&w.get_context_arc()).create_vertex_array(),
             context: w.get_context_arc(),
         }
     }
     fn attrib_ptr(&self, v: &VertexAttrib) {
-        self.context.lock().unwrap().vertex_attrib_pointer_with_i32(
+        lock_context(&self.context).vertex_attrib_pointer_with_i32(
             v.0,
             v.1 as i32,
-            WebGl2RenderingContext::FLOAT,
-            false,
+            Self::gl_attrib_type(&v.4),
+            v.5,
             v.2 as i32,
             v.3 as i32,
         );
-        self.context.lock().unwrap().enable_vertex_attrib_array(v.0);
+        lock_context(&self.context).enable_vertex_attrib_array(v.0);
+    }
+    fn attrib_ptr_i(&self, v: &VertexAttrib) {
+        if let GlVertexAttribType::HalfFloat | GlVertexAttribType::Float = v.4 {
+            char_panic!(
+                "WebGL: Integer vertex attribute cannot have type {:?}.",
+                v.4
+            );
+        }
+        lock_context(&self.context).vertex_attrib_i_pointer_with_i32(
+            v.0,
+            v.1 as i32,
+            Self::gl_attrib_type(&v.4),
+            v.2 as i32,
+            v.3 as i32,
+        );
+        lock_context(&self.context).enable_vertex_attrib_array(v.0);
     }
     fn remove_attrib_ptr(&self, v: &VertexAttrib) {
-        self.context
-            .lock()
-            .unwrap()
-            .disable_vertex_attrib_array(v.0);
+        lock_context(&self.context).disable_vertex_attrib_array(v.0);
     }
 }
 impl Drop for WebGlVertexArray {
     fn drop(&mut self) {
-        self.context
-            .lock()
-            .unwrap()
-            .delete_vertex_array(self.vao.as_ref());
+        lock_context(&self.context).delete_vertex_array(self.vao.as_ref());
     }
 }
 
@@ -1053,7 +1375,11 @@ impl WebGlShaderLoc {
         self.loc.as_ref()
     }
 }
-impl GlShaderLoc for WebGlShaderLoc {}
+impl GlShaderLoc for WebGlShaderLoc {
+    fn exists(&self) -> bool {
+        self.loc.is_some()
+    }
+}
 
 #[wasm_bindgen]
 pub struct WebGlProgram {
@@ -1061,6 +1387,32 @@ pub struct WebGlProgram {
     context: Arc<Mutex<WebGl2RenderingContext>>,
 }
 impl WebGlProgram {
+    fn active_info(
+        &self,
+        count: u32,
+        get_active: fn(&WebGl2RenderingContext, &JsSysWebGlProgram, u32) -> Option<WebGlActiveInfo>,
+    ) -> Vec<UniformInfo> {
+        let context = lock_context(&self.context);
+        let program = self.program.as_ref().unwrap();
+        let n = context
+            .get_program_parameter(program, count)
+            .as_f64()
+            .unwrap_or(0.0) as u32;
+        (0..n)
+            .filter_map(|i| get_active(&context, program, i))
+            .map(|info| UniformInfo {
+                name: info.name(),
+                size: info.size(),
+                gl_type: info.type_(),
+            })
+            .collect()
+    }
+    fn index_type(t: &GlIndexType) -> u32 {
+        match t {
+            GlIndexType::UnsignedShort => WebGl2RenderingContext::UNSIGNED_SHORT,
+            GlIndexType::UnsignedInt => WebGl2RenderingContext::UNSIGNED_INT,
+        }
+    }
     fn draw_mode(m: &GlDrawMode) -> u32 {
         use GlDrawMode::*;
         match m {
@@ -1076,13 +1428,10 @@ impl WebGlProgram {
 }
 impl GlBindable for WebGlProgram {
     fn bind(&self) {
-        self.context
-            .lock()
-            .unwrap()
-            .use_program(self.program.as_ref());
+        lock_context(&self.context).use_program(self.program.as_ref());
     }
     fn unbind(&self) {
-        self.context.lock().unwrap().use_program(None);
+        lock_context(&self.context).use_program(None);
     }
 }
 impl GlProgram for WebGlProgram {
@@ -1091,39 +1440,68 @@ impl GlProgram for WebGlProgram {
 
     fn new(w: &Window) -> Self {
         Self {
-            program: w.get_context_arc().lock().unwrap().create_program(),
+            program: lock_context(&w.get_context_arc()).create_program(),
             context: w.get_context_arc(),
         }
     }
     fn draw_arrays(&self, mode: GlDrawMode, start: i32, len: i32) {
-        self.context
-            .lock()
-            .unwrap()
-            .draw_arrays(Self::draw_mode(&mode), start, len);
+        lock_context(&self.context).draw_arrays(Self::draw_mode(&mode), start, len);
+    }
+    fn draw_elements(&self, mode: GlDrawMode, count: i32, index_type: GlIndexType, offset: usize) {
+        lock_context(&self.context).draw_elements_with_i32(
+            Self::draw_mode(&mode),
+            count,
+            Self::index_type(&index_type),
+            offset as i32,
+        );
     }
     fn shader_loc(&self, name: &str) -> Self::ShaderLoc {
         Self::ShaderLoc {
-            loc: self
-                .context
-                .lock()
-                .unwrap()
+            loc: lock_context(&self.context)
                 .get_uniform_location(self.program.as_ref().unwrap(), name),
         }
     }
     fn attach_shader(&self, shader: &Self::Shader) {
-        self.context
-            .lock()
-            .unwrap()
+        lock_context(&self.context)
             .attach_shader(self.program.as_ref().unwrap(), shader.get_shader_ref());
     }
+    fn bind_attrib_location(&self, index: u32, name: &str) {
+        lock_context(&self.context).bind_attrib_location(
+            self.program.as_ref().unwrap(),
+            index,
+            name,
+        );
+    }
+    fn attrib_location(&self, name: &str) -> i32 {
+        lock_context(&self.context).get_attrib_location(self.program.as_ref().unwrap(), name)
+    }
+    fn uniform_block_binding(&self, name: &str, binding: u32) -> bool {
+        let context = lock_context(&self.context);
+        let program = self.program.as_ref().unwrap();
+        let index = context.get_uniform_block_index(program, name);
+        if index == WebGl2RenderingContext::INVALID_INDEX {
+            return false;
+        }
+        context.uniform_block_binding(program, index, binding);
+        true
+    }
+    fn active_uniforms(&self) -> Vec<UniformInfo> {
+        self.active_info(
+            WebGl2RenderingContext::ACTIVE_UNIFORMS,
+            WebGl2RenderingContext::get_active_uniform,
+        )
+    }
+    fn active_attributes(&self) -> Vec<UniformInfo> {
+        self.active_info(
+            WebGl2RenderingContext::ACTIVE_ATTRIBUTES,
+            WebGl2RenderingContext::get_active_attrib,
+        )
+    }
     fn link_program(&self) {
-        self.context
-            .lock()
-            .unwrap()
-            .link_program(self.program.as_ref().unwrap());
+        lock_context(&self.context).link_program(self.program.as_ref().unwrap());
     }
     fn get_link_status(&self) -> Option<String> {
-        let gl = self.context.lock().unwrap();
+        let gl = lock_context(&self.context);
         if !gl
             .get_program_parameter(
                 self.program.as_ref().unwrap(),
@@ -1145,72 +1523,42 @@ impl GlProgram for WebGlProgram {
         }
     }
     fn uniform_4f(&self, loc: &Self::ShaderLoc, v: (f32, f32, f32, f32)) {
-        self.context
-            .lock()
-            .unwrap()
-            .uniform4f(loc.loc_ref(), v.0, v.1, v.2, v.3);
+        lock_context(&self.context).uniform4f(loc.loc_ref(), v.0, v.1, v.2, v.3);
     }
     fn uniform_3f(&self, loc: &Self::ShaderLoc, v: (f32, f32, f32)) {
-        self.context
-            .lock()
-            .unwrap()
-            .uniform3f(loc.loc_ref(), v.0, v.1, v.2);
+        lock_context(&self.context).uniform3f(loc.loc_ref(), v.0, v.1, v.2);
     }
     fn uniform_2f(&self, loc: &Self::ShaderLoc, v: (f32, f32)) {
-        self.context
-            .lock()
-            .unwrap()
-            .uniform2f(loc.loc_ref(), v.0, v.1);
+        lock_context(&self.context).uniform2f(loc.loc_ref(), v.0, v.1);
     }
     fn uniform_1f(&self, loc: &Self::ShaderLoc, v: f32) {
-        self.context.lock().unwrap().uniform1f(loc.loc.as_ref(), v);
+        lock_context(&self.context).uniform1f(loc.loc.as_ref(), v);
     }
     fn uniform_4i(&self, loc: &Self::ShaderLoc, v: (i32, i32, i32, i32)) {
-        self.context
-            .lock()
-            .unwrap()
-            .uniform4i(loc.loc_ref(), v.0, v.1, v.2, v.3);
+        lock_context(&self.context).uniform4i(loc.loc_ref(), v.0, v.1, v.2, v.3);
     }
     fn uniform_3i(&self, loc: &Self::ShaderLoc, v: (i32, i32, i32)) {
-        self.context
-            .lock()
-            .unwrap()
-            .uniform3i(loc.loc_ref(), v.0, v.1, v.2);
+        lock_context(&self.context).uniform3i(loc.loc_ref(), v.0, v.1, v.2);
     }
     fn uniform_2i(&self, loc: &Self::ShaderLoc, v: (i32, i32)) {
-        self.context
-            .lock()
-            .unwrap()
-            .uniform2i(loc.loc_ref(), v.0, v.1);
+        lock_context(&self.context).uniform2i(loc.loc_ref(), v.0, v.1);
     }
     fn uniform_1i(&self, loc: &Self::ShaderLoc, v: i32) {
-        self.context.lock().unwrap().uniform1i(loc.loc_ref(), v);
+        lock_context(&self.context).uniform1i(loc.loc_ref(), v);
     }
     fn uniform_mat4f(&self, loc: &Self::ShaderLoc, v: &[f32]) {
-        self.context
-            .lock()
-            .unwrap()
-            .uniform_matrix4fv_with_f32_array(loc.loc_ref(), false, v);
+        lock_context(&self.context).uniform_matrix4fv_with_f32_array(loc.loc_ref(), false, v);
     }
     fn uniform_mat3f(&self, loc: &Self::ShaderLoc, v: &[f32]) {
-        self.context
-            .lock()
-            .unwrap()
-            .uniform_matrix3fv_with_f32_array(loc.loc_ref(), false, v);
+        lock_context(&self.context).uniform_matrix3fv_with_f32_array(loc.loc_ref(), false, v);
     }
     fn uniform_mat2f(&self, loc: &Self::ShaderLoc, v: &[f32]) {
-        self.context
-            .lock()
-            .unwrap()
-            .uniform_matrix2fv_with_f32_array(loc.loc_ref(), false, v);
+        lock_context(&self.context).uniform_matrix2fv_with_f32_array(loc.loc_ref(), false, v);
     }
 }
 impl Drop for WebGlProgram {
     fn drop(&mut self) {
-        self.context
-            .lock()
-            .unwrap()
-            .delete_program(self.program.as_ref());
+        lock_context(&self.context).delete_program(self.program.as_ref());
     }
 }
 
@@ -1218,8 +1566,37 @@ pub struct WebGlTexture2D {
     tex: Option<JsSysWebGlTexture>,
     context: Arc<Mutex<WebGl2RenderingContext>>,
     slot: u32,
+    wrap: (GlTextureWrap, GlTextureWrap),
+    unpack_alignment: u32,
 }
 impl WebGlTexture2D {
+    pub fn gl_texture_filter(f: &GlTextureFilter) -> u32 {
+        match f {
+            GlTextureFilter::Nearest => WebGl2RenderingContext::NEAREST,
+            GlTextureFilter::Linear => WebGl2RenderingContext::LINEAR,
+        }
+    }
+    /// WebGL has no border clamping, so `ClampToBorder` is treated as `ClampToEdge`.
+    pub fn gl_texture_wrap(w: &GlTextureWrap) -> u32 {
+        use GlTextureWrap::*;
+        match w {
+            Repeat => WebGl2RenderingContext::REPEAT,
+            MirroredRepeat => WebGl2RenderingContext::MIRRORED_REPEAT,
+            ClampToEdge | ClampToBorder => WebGl2RenderingContext::CLAMP_TO_EDGE,
+        }
+    }
+    fn apply_wrap(&self, gl: &WebGl2RenderingContext) {
+        gl.tex_parameteri(
+            WebGl2RenderingContext::TEXTURE_2D,
+            WebGl2RenderingContext::TEXTURE_WRAP_S,
+            Self::gl_texture_wrap(&self.wrap.0) as i32,
+        );
+        gl.tex_parameteri(
+            WebGl2RenderingContext::TEXTURE_2D,
+            WebGl2RenderingContext::TEXTURE_WRAP_T,
+            Self::gl_texture_wrap(&self.wrap.1) as i32,
+        );
+    }
     pub fn gl_texture_type(t: &GlTextureType) -> u32 {
         use GlTextureType::*;
         match t {
@@ -1279,6 +1656,9 @@ impl WebGlTexture2D {
             RGBA8I => WebGl2RenderingContext::RGBA8I,
             RGBA16I => WebGl2RenderingContext::RGBA16I,
             RGBA32I => WebGl2RenderingContext::RGBA32I,
+            DepthComponent16 => WebGl2RenderingContext::DEPTH_COMPONENT16,
+            DepthComponent24 => WebGl2RenderingContext::DEPTH_COMPONENT24,
+            DepthComponent32F => WebGl2RenderingContext::DEPTH_COMPONENT32F,
             _ => {
                 char_panic!(
                     "WebGL: GlInternalTextureFormat {:?} not supported on web.",
@@ -1319,17 +1699,8 @@ impl WebGlTexture2D {
         }
     }
     pub fn set_params(&self, mips: Option<u32>) {
-        let gl = self.context.lock().unwrap();
-        gl.tex_parameteri(
-            WebGl2RenderingContext::TEXTURE_2D,
-            WebGl2RenderingContext::TEXTURE_WRAP_S,
-            WebGl2RenderingContext::REPEAT as i32,
-        );
-        gl.tex_parameteri(
-            WebGl2RenderingContext::TEXTURE_2D,
-            WebGl2RenderingContext::TEXTURE_WRAP_T,
-            WebGl2RenderingContext::REPEAT as i32,
-        );
+        let gl = lock_context(&self.context);
+        self.apply_wrap(&gl);
         gl.tex_parameteri(
             WebGl2RenderingContext::TEXTURE_2D,
             WebGl2RenderingContext::TEXTURE_MAG_FILTER,
@@ -1353,26 +1724,23 @@ impl WebGlTexture2D {
 }
 impl GlBindable for WebGlTexture2D {
     fn bind(&self) {
-        self.context.lock().unwrap().active_texture(self.slot);
-        self.context
-            .lock()
-            .unwrap()
+        lock_context(&self.context).active_texture(self.slot);
+        lock_context(&self.context)
             .bind_texture(WebGl2RenderingContext::TEXTURE_2D, self.tex.as_ref());
     }
     fn unbind(&self) {
-        self.context.lock().unwrap().active_texture(self.slot);
-        self.context
-            .lock()
-            .unwrap()
-            .bind_texture(WebGl2RenderingContext::TEXTURE_2D, None);
+        lock_context(&self.context).active_texture(self.slot);
+        lock_context(&self.context).bind_texture(WebGl2RenderingContext::TEXTURE_2D, None);
     }
 }
 impl GlTexture2D for WebGlTexture2D {
     fn new(w: &mut Window) -> Self {
         Self {
             context: w.get_context_arc(),
-            tex: w.get_context_arc().lock().unwrap().create_texture(),
+            tex: lock_context(&w.get_context_arc()).create_texture(),
             slot: WebGl2RenderingContext::TEXTURE0,
+            wrap: (GlTextureWrap::Repeat, GlTextureWrap::Repeat),
+            unpack_alignment: 1,
         }
     }
     fn set_texture(
@@ -1387,10 +1755,6 @@ impl GlTexture2D for WebGlTexture2D {
         px_byte_size: usize,
     ) {
         unsafe {
-            let array_buff_view = Uint8Array::view_mut_raw(
-                tex_ptr as *mut u8,
-                (width * height) as usize * px_byte_size,
-            );
             let mips = if u32::is_power_of_two(width) && u32::is_power_of_two(height) {
                 mipmaps
             } else {
@@ -1404,8 +1768,34 @@ impl GlTexture2D for WebGlTexture2D {
                 }
                 None
             };
-            self.context.lock().unwrap()
-                .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_array_buffer_view_and_src_offset(
+            let gl = lock_context(&self.context);
+            gl.pixel_storei(
+                WebGl2RenderingContext::UNPACK_ALIGNMENT,
+                self.unpack_alignment as i32,
+            );
+            // A null pointer allocates storage without uploading, e.g. for render targets
+            let res = if tex_ptr.is_null() {
+                gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                    WebGl2RenderingContext::TEXTURE_2D,
+                    mips.unwrap_or_else(|| 0) as i32,
+                    Self::gl_internal_fmt(&internal_fmt) as i32,
+                    width as i32,
+                    height as i32,
+                    0,
+                    Self::gl_img_fmt(&img_fmt),
+                    Self::gl_px_fmt(&px_type),
+                    None,
+                )
+            } else {
+                // Every row but the last is padded to the unpack alignment
+                let row = width as usize * px_byte_size;
+                let align = self.unpack_alignment as usize;
+                let padded_row = (row + align - 1) / align * align;
+                let array_buff_view = Uint8Array::view_mut_raw(
+                    tex_ptr as *mut u8,
+                    padded_row * (height as usize).saturating_sub(1) + row,
+                );
+                gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_array_buffer_view_and_src_offset(
                     WebGl2RenderingContext::TEXTURE_2D,
                     mips.unwrap_or_else(|| 0) as i32,
                     Self::gl_internal_fmt(&internal_fmt) as i32,
@@ -1414,24 +1804,159 @@ impl GlTexture2D for WebGlTexture2D {
                     0,
                     Self::gl_img_fmt(&img_fmt),
                     Self::gl_px_fmt(&px_type),
-					&array_buff_view,
-					0,
+                    &array_buff_view,
+                    0,
                 )
-                .unwrap_or_else(|err| {
-                    char_panic!("WebGL: Error calling texImage2D: {:?}.", err);
-                });
+            };
+            // Restore the GL default for any other uploads
+            gl.pixel_storei(WebGl2RenderingContext::UNPACK_ALIGNMENT, 4);
+            res.unwrap_or_else(|err| {
+                char_panic!("WebGL: Error calling texImage2D: {:?}.", err);
+            });
+            drop(gl);
             self.set_params(mips);
         }
     }
     fn set_slot(&mut self, slot: u32) {
         self.slot = WebGl2RenderingContext::TEXTURE0 + slot;
     }
+    fn set_unpack_alignment(&mut self, alignment: u32) {
+        if ![1, 2, 4, 8].contains(&alignment) {
+            char_panic!("Unpack alignment must be 1, 2, 4 or 8, got {}.", alignment);
+        }
+        self.unpack_alignment = alignment;
+    }
+    fn set_wrap(&mut self, s: GlTextureWrap, t: GlTextureWrap) {
+        self.wrap = (s, t);
+        self.apply_wrap(&lock_context(&self.context));
+    }
+    fn set_border_color(&mut self, _: f32, _: f32, _: f32, _: f32) {
+        // Border colours are unsupported in WebGL
+    }
+    fn set_mip_range(&mut self, base: u32, max: u32) {
+        let gl = lock_context(&self.context);
+        gl.tex_parameteri(
+            WebGl2RenderingContext::TEXTURE_2D,
+            WebGl2RenderingContext::TEXTURE_BASE_LEVEL,
+            base as i32,
+        );
+        gl.tex_parameteri(
+            WebGl2RenderingContext::TEXTURE_2D,
+            WebGl2RenderingContext::TEXTURE_MAX_LEVEL,
+            max as i32,
+        );
+    }
+    fn set_lod_bias(&mut self, _: f32) {
+        // TEXTURE_LOD_BIAS is unsupported in WebGL
+    }
+    fn set_filter(&mut self, min: GlTextureFilter, mag: GlTextureFilter) {
+        let gl = lock_context(&self.context);
+        gl.tex_parameteri(
+            WebGl2RenderingContext::TEXTURE_2D,
+            WebGl2RenderingContext::TEXTURE_MIN_FILTER,
+            Self::gl_texture_filter(&min) as i32,
+        );
+        gl.tex_parameteri(
+            WebGl2RenderingContext::TEXTURE_2D,
+            WebGl2RenderingContext::TEXTURE_MAG_FILTER,
+            Self::gl_texture_filter(&mag) as i32,
+        );
+    }
 }
 impl Drop for WebGlTexture2D {
     fn drop(&mut self) {
-        self.context
-            .lock()
-            .unwrap()
-            .delete_texture(self.tex.as_ref());
+        lock_context(&self.context).delete_texture(self.tex.as_ref());
+    }
+}
+
+pub struct WebGlFramebuffer {
+    fbo: Option<JsSysWebGlFramebuffer>,
+    context: Arc<Mutex<WebGl2RenderingContext>>,
+}
+impl WebGlFramebuffer {
+    pub fn gl_attachment(a: &GlFramebufferAttachment) -> u32 {
+        use GlFramebufferAttachment::*;
+        match a {
+            Color0 => WebGl2RenderingContext::COLOR_ATTACHMENT0,
+            Depth => WebGl2RenderingContext::DEPTH_ATTACHMENT,
+            Stencil => WebGl2RenderingContext::STENCIL_ATTACHMENT,
+            DepthStencil => WebGl2RenderingContext::DEPTH_STENCIL_ATTACHMENT,
+        }
+    }
+}
+impl GlBindable for WebGlFramebuffer {
+    fn bind(&self) {
+        lock_context(&self.context)
+            .bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, self.fbo.as_ref());
+    }
+    fn unbind(&self) {
+        lock_context(&self.context).bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, None);
+    }
+}
+impl GlFramebuffer for WebGlFramebuffer {
+    fn new(w: &Window) -> Self {
+        Self {
+            fbo: lock_context(&w.get_context_arc()).create_framebuffer(),
+            context: w.get_context_arc(),
+        }
+    }
+    fn attach_texture_2d(&self, attachment: GlFramebufferAttachment, tex: &WebGlTexture2D) {
+        lock_context(&self.context).framebuffer_texture_2d(
+            WebGl2RenderingContext::FRAMEBUFFER,
+            Self::gl_attachment(&attachment),
+            WebGl2RenderingContext::TEXTURE_2D,
+            tex.tex.as_ref(),
+            0,
+        );
+    }
+    fn set_depth_only(&self) {
+        let gl = lock_context(&self.context);
+        gl.draw_buffers(&js_sys::Array::of1(&JsValue::from(
+            WebGl2RenderingContext::NONE,
+        )));
+        gl.read_buffer(WebGl2RenderingContext::NONE);
+    }
+    fn is_complete(&self) -> bool {
+        lock_context(&self.context).check_framebuffer_status(WebGl2RenderingContext::FRAMEBUFFER)
+            == WebGl2RenderingContext::FRAMEBUFFER_COMPLETE
+    }
+}
+impl Drop for WebGlFramebuffer {
+    fn drop(&mut self) {
+        lock_context(&self.context).delete_framebuffer(self.fbo.as_ref());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn try_lock_context_reports_a_lock_left_by_a_panic() {
+        let context = Mutex::new(0);
+        assert!(try_lock_context(&context).is_ok());
+        // A panic while locked aborts without dropping the guard
+        std::mem::forget(context.lock().unwrap());
+        assert!(matches!(
+            try_lock_context(&context),
+            Err(CharWinError::ContextPoisoned)
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    fn clamp_to_border_falls_back_to_clamp_to_edge() {
+        use GlTextureWrap::*;
+        let wraps = [Repeat, MirroredRepeat, ClampToEdge, ClampToBorder]
+            .map(|w| WebGlTexture2D::gl_texture_wrap(&w));
+        assert_eq!(
+            wraps,
+            [
+                WebGl2RenderingContext::REPEAT,
+                WebGl2RenderingContext::MIRRORED_REPEAT,
+                WebGl2RenderingContext::CLAMP_TO_EDGE,
+                WebGl2RenderingContext::CLAMP_TO_EDGE,
+            ]
+        );
     }
 }
@@ -0,0 +1,439 @@
+use crate::data::{CPUBuffer, DynamicImageColorable, GPUTexture, TextureLoadOptions};
+use crate::error::CharWinError;
+use crate::input::{Key, MouseButton};
+use crate::platform::Context;
+use crate::state::{FrameManager, State};
+use crate::window::opengl_window::{get_gl_errors, texture_from_bytes, ImageLoadThreads};
+use crate::window::*;
+use glutin::dpi::PhysicalSize;
+use glutin::event::{
+    ElementState, Event, MouseButton as WinitMouseButton, MouseScrollDelta, VirtualKeyCode,
+    WindowEvent as WinitWindowEvent,
+};
+use glutin::event_loop::{ControlFlow, EventLoop};
+use glutin::platform::run_return::EventLoopExtRunReturn;
+use glutin::window::{Fullscreen, WindowBuilder};
+use glutin::{Api, ContextBuilder, GlProfile, GlRequest, PossiblyCurrent, WindowedContext};
+use image::{DynamicImage, ImageFormat};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+pub struct WinitGlWindow {
+    event_loop: Option<EventLoop<()>>,
+    context: WindowedContext<PossiblyCurrent>,
+    events: Vec<WindowEvent>,
+    held_keys: HashSet<Key>,
+    should_close: bool,
+    image_threads: ImageLoadThreads,
+    max_delta: Option<f64>,
+}
+
+impl WinitGlWindow {
+    pub fn render_loop<S: State, E: EventManager>(mut self, mut state: S, mut manager: E) {
+        {
+            self.poll_events();
+            let mut init_events = self.get_events();
+            let size = self.get_size();
+            let pos = self.get_pos();
+            init_events.push(WindowEvent::Size(size.0, size.1));
+            init_events.push(WindowEvent::Position(pos.0, pos.1));
+            manager.process_events(&init_events);
+        }
+        let mut fm = FrameManager::new(None);
+        fm.set_max_delta(self.max_delta);
+        let mut state_res = state.initialize(&mut self, &mut manager);
+        get_gl_errors();
+        if state_res == 0 {
+            loop {
+                if fm.next_frame_ready() {
+                    self.poll_events();
+                    manager.advance_time(fm.get_delta());
+                    manager.process_events(&self.get_events());
+                    state_res = state.update(&mut self, &mut manager, fm.get_delta());
+                    if state_res != 0 || self.should_close() {
+                        break;
+                    }
+                }
+            }
+        }
+        state.destroy(&mut self, &mut manager, state_res);
+        get_gl_errors();
+    }
+    /// Records an event, reporting repeated key presses as held like GLFW does.
+    fn push_event(&mut self, event: WindowEvent) {
+        let event = match event {
+            WindowEvent::KeyDown(key, code) if !self.held_keys.insert(key) => {
+                WindowEvent::KeyHeld(key, code)
+            }
+            WindowEvent::KeyUp(key, _) => {
+                self.held_keys.remove(&key);
+                event
+            }
+            _ => event,
+        };
+        self.events.push(event);
+    }
+}
+
+impl AbstractWindow for WinitGlWindow {
+    fn get_gl_context(&mut self) -> Context {
+        Context::new(self)
+    }
+    fn set_fullscreen(&mut self) {
+        self.context
+            .window()
+            .set_fullscreen(Some(Fullscreen::Borderless(None)));
+    }
+    fn set_windowed(&mut self) {
+        self.context.window().set_fullscreen(None);
+    }
+    fn set_title(&mut self, name: &str) {
+        self.context.window().set_title(name);
+    }
+    fn set_size(&mut self, sz: (i32, i32)) {
+        self.context
+            .window()
+            .set_inner_size(PhysicalSize::new(sz.0 as u32, sz.1 as u32));
+    }
+    fn should_close(&mut self) -> bool {
+        self.should_close
+    }
+    fn poll_events(&mut self) {
+        self.events.clear();
+        let mut event_loop = self.event_loop.take().unwrap();
+        event_loop.run_return(|event, _, control_flow| match event {
+            Event::WindowEvent { event, .. } => {
+                match event {
+                    WinitWindowEvent::CloseRequested => self.should_close = true,
+                    WinitWindowEvent::Resized(size) => self.context.resize(size),
+                    _ => {}
+                }
+                if let Some(event) = winit_event_to_window_event(&event) {
+                    self.push_event(event);
+                }
+            }
+            Event::MainEventsCleared => *control_flow = ControlFlow::Exit,
+            _ => *control_flow = ControlFlow::Poll,
+        });
+        self.event_loop = Some(event_loop);
+        self.image_threads.poll();
+    }
+    fn get_events(&mut self) -> Vec<WindowEvent> {
+        self.events.clone()
+    }
+    fn swap_buffers(&mut self) {
+        self.context.swap_buffers().unwrap_or_else(|e| {
+            panic!("Glutin: Could not swap buffers: {:?}.", e);
+        });
+    }
+    fn set_swap_interval(&mut self, _interval: i32) {
+        // Glutin only takes vsync when the context is built, where it is left off
+    }
+    fn swap_interval(&self) -> i32 {
+        0
+    }
+    fn close(&mut self) {
+        self.should_close = true;
+    }
+    fn get_size(&self) -> (i32, i32) {
+        let size = self.context.window().inner_size();
+        (size.width as i32, size.height as i32)
+    }
+    fn get_pos(&self) -> (i32, i32) {
+        match self.context.window().outer_position() {
+            Ok(pos) => (pos.x, pos.y),
+            Err(_) => (0, 0),
+        }
+    }
+    fn load_texture_rgba_with_placeholder(
+        &mut self,
+        path: &str,
+        options: TextureLoadOptions,
+        placeholder: [u8; 4],
+    ) -> Arc<Mutex<GPUTexture>> {
+        let tex = Arc::new(Mutex::new(
+            DynamicImage::solid_color(placeholder).to_gpu_buffer(self),
+        ));
+        self.image_threads.spawn(Arc::clone(&tex), path, options);
+        tex
+    }
+    fn try_load_texture_from_bytes(
+        &mut self,
+        bytes: &[u8],
+        format_hint: Option<ImageFormat>,
+        options: TextureLoadOptions,
+    ) -> Result<Arc<Mutex<GPUTexture>>, CharWinError> {
+        texture_from_bytes(self, bytes, format_hint, options)
+    }
+}
+
+impl AbstractWindowFactory for WinitGlWindow {
+    fn create(args: &WindowCreateArgs) -> Self {
+        let event_loop = new_event_loop();
+        let mut builder = WindowBuilder::new()
+            .with_title(&args.title)
+            .with_inner_size(PhysicalSize::new(args.width, args.height));
+        if args.mode == WindowSizeMode::Fullscreen {
+            builder = builder.with_fullscreen(Some(Fullscreen::Borderless(None)));
+        }
+        let request = match args.gl_version {
+            Some((major, minor)) => GlRequest::Specific(Api::OpenGl, (major as u8, minor as u8)),
+            None => GlRequest::Latest,
+        };
+        let mut context = ContextBuilder::new().with_gl(request);
+        if args.core_profile {
+            context = context.with_gl_profile(GlProfile::Core);
+        }
+        let context = context
+            .with_depth_buffer(24)
+            .with_stencil_buffer(8)
+            .build_windowed(builder, &event_loop)
+            .expect("Glutin: Failed to create a window.");
+        let context = unsafe { context.make_current() }.unwrap_or_else(|(_, e)| {
+            panic!("Glutin: Could not make context current: {:?}.", e);
+        });
+        gl::load_with(|s| context.get_proc_address(s));
+        WinitGlWindow {
+            event_loop: Some(event_loop),
+            context,
+            events: Vec::new(),
+            held_keys: HashSet::new(),
+            should_close: false,
+            image_threads: ImageLoadThreads::default(),
+            max_delta: args.max_delta(),
+        }
+    }
+}
+
+/// Like GLFW on these platforms, the window may be created off the main thread.
+#[cfg(all(
+    unix,
+    not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+))]
+fn new_event_loop() -> EventLoop<()> {
+    glutin::platform::unix::EventLoopExtUnix::new_any_thread()
+}
+#[cfg(not(all(
+    unix,
+    not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+)))]
+fn new_event_loop() -> EventLoop<()> {
+    EventLoop::new()
+}
+
+/// Translates a winit event the same way as the GLFW backend. Repeated key presses are
+/// reported as `KeyDown`; the window turns them into `KeyHeld`.
+pub fn winit_event_to_window_event(event: &WinitWindowEvent) -> Option<WindowEvent> {
+    let event = match event {
+        WinitWindowEvent::Moved(pos) => WindowEvent::Position(pos.x, pos.y),
+        WinitWindowEvent::Resized(size) => {
+            WindowEvent::Size(size.width as i32, size.height as i32)
+        }
+        WinitWindowEvent::CloseRequested => WindowEvent::Close,
+        WinitWindowEvent::Focused(is) => WindowEvent::Focus(*is),
+        WinitWindowEvent::MouseInput { state, button, .. } => {
+            let button = winit_button_to_mouse_button(button);
+            match state {
+                ElementState::Pressed => WindowEvent::MouseButtonDown(button),
+                ElementState::Released => WindowEvent::MouseButtonUp(button),
+            }
+        }
+        WinitWindowEvent::CursorMoved { position, .. } => {
+            WindowEvent::CursorPosition(position.x, position.y)
+        }
+        WinitWindowEvent::CursorEntered { .. } => WindowEvent::CursorEnter(true),
+        WinitWindowEvent::CursorLeft { .. } => WindowEvent::CursorEnter(false),
+        WinitWindowEvent::MouseWheel { delta, .. } => match delta {
+            MouseScrollDelta::LineDelta(x, y) => WindowEvent::Scroll(*x as f64, *y as f64),
+            MouseScrollDelta::PixelDelta(pos) => WindowEvent::Scroll(pos.x, pos.y),
+        },
+        WinitWindowEvent::KeyboardInput { input, .. } => {
+            let key = input
+                .virtual_keycode
+                .map(winit_key_to_key)
+                .unwrap_or(Key::Unknown);
+            match input.state {
+                ElementState::Pressed => WindowEvent::KeyDown(key, input.scancode as i32),
+                ElementState::Released => WindowEvent::KeyUp(key, input.scancode as i32),
+            }
+        }
+        _ => WindowEvent::None,
+    };
+    if event == WindowEvent::None {
+        None
+    } else {
+        Some(event)
+    }
+}
+
+fn winit_button_to_mouse_button(button: &WinitMouseButton) -> MouseButton {
+    match button {
+        WinitMouseButton::Left => MouseButton::Button1,
+        WinitMouseButton::Right => MouseButton::Button2,
+        WinitMouseButton::Middle => MouseButton::Button3,
+        WinitMouseButton::Other(n) => MouseButton::from_i32(*n as i32),
+    }
+}
+
+pub fn winit_key_to_key(key: VirtualKeyCode) -> Key {
+    use VirtualKeyCode::*;
+    match key {
+        Key1 => Key::Num1,
+        Key2 => Key::Num2,
+        Key3 => Key::Num3,
+        Key4 => Key::Num4,
+        Key5 => Key::Num5,
+        Key6 => Key::Num6,
+        Key7 => Key::Num7,
+        Key8 => Key::Num8,
+        Key9 => Key::Num9,
+        Key0 => Key::Num0,
+        A => Key::A,
+        B => Key::B,
+        C => Key::C,
+        D => Key::D,
+        E => Key::E,
+        F => Key::F,
+        G => Key::G,
+        H => Key::H,
+        I => Key::I,
+        J => Key::J,
+        K => Key::K,
+        L => Key::L,
+        M => Key::M,
+        N => Key::N,
+        O => Key::O,
+        P => Key::P,
+        Q => Key::Q,
+        R => Key::R,
+        S => Key::S,
+        T => Key::T,
+        U => Key::U,
+        V => Key::V,
+        W => Key::W,
+        X => Key::X,
+        Y => Key::Y,
+        Z => Key::Z,
+        Escape => Key::Escape,
+        F1 => Key::F1,
+        F2 => Key::F2,
+        F3 => Key::F3,
+        F4 => Key::F4,
+        F5 => Key::F5,
+        F6 => Key::F6,
+        F7 => Key::F7,
+        F8 => Key::F8,
+        F9 => Key::F9,
+        F10 => Key::F10,
+        F11 => Key::F11,
+        F12 => Key::F12,
+        F13 => Key::F13,
+        F14 => Key::F14,
+        F15 => Key::F15,
+        F16 => Key::F16,
+        F17 => Key::F17,
+        F18 => Key::F18,
+        F19 => Key::F19,
+        F20 => Key::F20,
+        F21 => Key::F21,
+        F22 => Key::F22,
+        F23 => Key::F23,
+        F24 => Key::F24,
+        Snapshot => Key::PrintScreen,
+        Scroll => Key::ScrollLock,
+        Pause => Key::Pause,
+        Insert => Key::Insert,
+        Home => Key::Home,
+        Delete => Key::Delete,
+        End => Key::End,
+        PageDown => Key::PageDown,
+        PageUp => Key::PageUp,
+        Left => Key::Left,
+        Up => Key::Up,
+        Right => Key::Right,
+        Down => Key::Down,
+        Back => Key::Backspace,
+        Return => Key::Enter,
+        Space => Key::Space,
+        Numlock => Key::NumLock,
+        Numpad0 => Key::Kp0,
+        Numpad1 => Key::Kp1,
+        Numpad2 => Key::Kp2,
+        Numpad3 => Key::Kp3,
+        Numpad4 => Key::Kp4,
+        Numpad5 => Key::Kp5,
+        Numpad6 => Key::Kp6,
+        Numpad7 => Key::Kp7,
+        Numpad8 => Key::Kp8,
+        Numpad9 => Key::Kp9,
+        NumpadAdd => Key::KpAdd,
+        NumpadDivide => Key::KpDivide,
+        NumpadDecimal => Key::KpDecimal,
+        NumpadEnter => Key::KpEnter,
+        NumpadEquals => Key::KpEqual,
+        NumpadMultiply => Key::KpMultiply,
+        NumpadSubtract => Key::KpSubtract,
+        Apostrophe => Key::Apostrophe,
+        Apps => Key::Menu,
+        Backslash => Key::Backslash,
+        Capital => Key::CapsLock,
+        Comma => Key::Comma,
+        Equals => Key::Equal,
+        Grave => Key::GraveAccent,
+        LAlt => Key::LeftAlt,
+        LBracket => Key::LeftBracket,
+        LControl => Key::LeftControl,
+        LShift => Key::LeftShift,
+        LWin => Key::LeftSuper,
+        Minus => Key::Minus,
+        OEM102 => Key::World1,
+        Period => Key::Period,
+        RAlt => Key::RightAlt,
+        RBracket => Key::RightBracket,
+        RControl => Key::RightControl,
+        RShift => Key::RightShift,
+        RWin => Key::RightSuper,
+        Semicolon => Key::Semicolon,
+        Slash => Key::Slash,
+        Tab => Key::Tab,
+        _ => Key::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glutin::event::{DeviceId, KeyboardInput, ModifiersState};
+
+    #[allow(deprecated)]
+    fn keyboard_event(state: ElementState, key: VirtualKeyCode) -> WinitWindowEvent<'static> {
+        WinitWindowEvent::KeyboardInput {
+            device_id: unsafe { DeviceId::dummy() },
+            input: KeyboardInput {
+                scancode: 30,
+                state,
+                virtual_keycode: Some(key),
+                modifiers: ModifiersState::empty(),
+            },
+            is_synthetic: false,
+        }
+    }
+
+    #[test]
+    fn key_press_translates_to_key_down() {
+        let event = keyboard_event(ElementState::Pressed, VirtualKeyCode::A);
+        assert_eq!(
+            winit_event_to_window_event(&event),
+            Some(WindowEvent::KeyDown(Key::A, 30))
+        );
+    }
+
+    #[test]
+    fn key_release_translates_to_key_up() {
+        let event = keyboard_event(ElementState::Released, VirtualKeyCode::Space);
+        assert_eq!(
+            winit_event_to_window_event(&event),
+            Some(WindowEvent::KeyUp(Key::Space, 30))
+        );
+    }
+}
@@ -3,11 +3,12 @@ pub mod world;
 #[cfg(any(test, target_family = "wasm"))]
 mod tests {
     use crate::world::*;
-    use charmath::linear::matrix::*;
+    use charmath::linear::matrix::matrices;
     use charmath::linear::vector::*;
 	use charmath::linear::quaternion::Quaternionf32;
     use charwin::cw_println;
     use charwin::data::*;
+    use charwin::error::CharWinError;
     use charwin::input::*;
     use charwin::platform::*;
     use charwin::state::*;
@@ -20,6 +21,7 @@ mod tests {
         shader: GPUShader,
         mshs: Vec<Object3D>,
         camera: PerspectiveCamera3D,
+        viewport: Viewport,
 		ctr: f32
     }
     pub struct App {
@@ -28,9 +30,7 @@ mod tests {
     impl State for App {
         fn initialize(&mut self, win: &mut Window, _manager: &mut dyn EventManager) -> i32 {
             win.set_clear_colour(0.2, 0.2, 0.2, 1.0);
-            let vs = "#version 300 es
-            precision highp float;
-
+            let vs = "
 			struct Camera {
 				mat4 projection;
 				mat4 view;
@@ -57,8 +57,7 @@ mod tests {
 				norm = (mesh.rotation * vec4(vNorm, 1.0)).xyz;
             }
             ";
-            let fs = "#version 300 es
-            precision mediump float;
+            let fs = "
             out vec4 FragColor;
 			
 			struct Material {
@@ -108,17 +107,27 @@ mod tests {
                     //     "./resource/dirt.jpg",
                     // ),
                 ],
-                camera: PerspectiveCamera3D {
-                    fov: 75.0,
-                    near: 0.1,
-                    far: 100.0,
-                    pos: Vec3f32::new(0.0, 0.0, 0.0),
-                    rot: Vec3f32::new(0.0, 0.0, 0.0),
-                },
+                camera: PerspectiveCamera3D::new(75.0, 0.1, 100.0, Vec3f32::new(0.0, 0.0, 0.0)),
+                viewport: Viewport::new(win.get_size()),
 				ctr: 0.0
             });
+            assert!(matches!(
+                GPUShader::try_from_sources(win, "void main() { broken }", fs),
+                Err(CharWinError::ShaderCompile(_))
+            ));
+            let shader = &self.data.as_ref().unwrap().shader;
+            assert!(shader.has_uniform("camera.view"));
+            assert!(!shader.has_uniform("camera.veiw"));
             let mut context = win.get_gl_context();
             context.enable(GlFeature::DepthTest);
+            assert!(context.is_enabled(GlFeature::DepthTest));
+            context.set_feature(GlFeature::CullFace, true);
+            assert!(context.is_enabled(GlFeature::CullFace));
+            context.set_feature(GlFeature::CullFace, false);
+            assert!(!context.is_enabled(GlFeature::CullFace));
+            check_stream_data(win);
+            check_model_uniform_buffer(win);
+            check_multi_buffer_mesh(win);
             context.default_depth_func();
             0
         }
@@ -134,18 +143,17 @@ mod tests {
                 win.clear(&[GlClearMask::Color, GlClearMask::Depth]);
                 data.shader.use_shader();
                 data.shader.set_int("tex", 0);
-                data.shader.set_mat4f("camera.view", &data.camera.view());
-                data.shader.set_mat4f(
-                    "camera.projection",
-                    &data.camera.projection(eng.win_aspect_y()),
+                CameraUniforms::new(&data.camera).upload(
+                    &data.shader,
+                    "camera",
+                    data.viewport.aspect(),
                 );
                 for mesh in &data.mshs {
                     mesh.render(&data.shader);
                 }
                 win.swap_buffers();
-                if let (sz, true) = eng.screen_size_changed() {
-                    win.set_size(sz);
-                    win.set_resolution(sz);
+                if data.viewport.update(win, eng) {
+                    win.set_size(data.viewport.size());
                 }
             }
             0
@@ -156,6 +164,49 @@ mod tests {
         }
     }
 
+    /// Streams of different sizes read back exactly what was last written.
+    fn check_stream_data(win: &mut Window) {
+        let mut buffer = TriGPUBuffer::<VertexVTN>::new(win);
+        for n_tris in [4, 64, 2] {
+            let tris: Vec<Triangle<VertexVTN>> = (0..n_tris)
+                .map(|i| {
+                    let mut tri = Triangle::<VertexVTN>::new();
+                    tri.v[0].v = Vec3f32::new(i as f32, 0.0, 0.0);
+                    tri
+                })
+                .collect();
+            buffer.stream_data(&tris);
+            assert_eq!(buffer.n_tris(), n_tris);
+            let read_back = buffer.get_data();
+            assert_eq!(read_back.len(), tris.len());
+            assert_eq!(read_back[tris.len() - 1].v[0].v.get_x(), (n_tris - 1) as f32);
+        }
+    }
+
+    /// Model matrices uploaded to the uniform buffer read back unchanged.
+    fn check_model_uniform_buffer(win: &Window) {
+        let models = ModelUniformBuffer::new(win, 1);
+        let transform = matrices::translation_3d(&Vec3f32::new(1.0, -2.0, 3.0));
+        let rotation = matrices::rotation_euler(&Vec3f32::new(0.1, 0.2, 0.3));
+        models.upload(&transform, &rotation);
+        let (read_transform, read_rotation) = models.get_matrices();
+        assert_eq!(read_transform.as_slice(), transform.as_slice());
+        assert_eq!(read_rotation.as_slice(), rotation.as_slice());
+    }
+
+    /// Updating the positions of a multi-buffer mesh leaves its UVs untouched.
+    fn check_multi_buffer_mesh(win: &mut Window) {
+        let positions = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let uvs = [0.0, 0.0, 1.0, 0.0, 0.0, 1.0];
+        let mut mesh = MultiBufferMesh::new(win);
+        mesh.add_attribute(win, 0, 3, &positions, GlStorageMode::Dynamic);
+        mesh.add_attribute(win, 1, 2, &uvs, GlStorageMode::Static);
+        let moved = positions.map(|p| p + 0.5);
+        mesh.update_attribute(0, &moved);
+        assert_eq!(mesh.get_attribute(0), moved);
+        assert_eq!(mesh.get_attribute(1), uvs);
+    }
+
     #[cfg_attr(not(target_family = "wasm"), test)]
     #[cfg_attr(target_family = "wasm", wasm_bindgen(js_name = start))]
     pub fn native_window_tests() {
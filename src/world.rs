@@ -1,16 +1,26 @@
+use charmath::geometry::{Aabb, Rect, Sphere};
 use charmath::linear::matrix::{matrices, Mat4, Matrix, SquareMatrix, Mat4f32};
-use charmath::linear::vector::{Vec2, Vec2f32, Vec3, Vec3f32, Vec4, Vec4f32, Vector};
+use charmath::linear::vector::{Vec2, Vec2f32, Vec3, Vec3f32, Vec4, Vec4f32, Vector, VectorBase};
+use charwin::color::Color;
 use charwin::cw_panic;
 use charwin::window::*;
 use charwin::data::{
-    DataBuffer, GPUBuffer, TriGPUBuffer, Triangle, TriangleBase, VertexBase, VertexVTN,
-	GPUTexture, GPUShader,
+    CPUBuffer, DataBuffer, GPUBuffer, IndexedTriGPUBuffer, TriCPUBuffer, TriGPUBuffer, Triangle, TriangleBase, VertexBase,
+    VertexV, VertexVTN, GPUTexture, GPUShader, TextureLoadOptions, UniformGPUBuffer, QuantizedVertex,
 };
 use charwin::input::Key;
-use charwin::platform::Window;
+use charwin::platform::{Buffer, Framebuffer, VertexArray, Window};
 use charmath::linear::quaternion::Quaternionf32;
+use charmath::random::Pcg32;
+use charmath::{CharMathCopy, Lerp};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
+#[cfg(target_family = "wasm")]
+use wasm_bindgen::prelude::*;
+
 pub trait MeshBase<V: VertexBase>: Sized {
     fn new(win: &mut Window) -> Self;
     fn n_tris(&self) -> i32;
@@ -28,8 +38,6 @@ pub trait MeshBase<V: VertexBase>: Sized {
         let mut tris = Vec::new();
 
         let lines: Vec<&str> = data.lines().collect();
-        let has_normals = data.contains(&"vn");
-        let has_uv = data.contains(&"vt");
         for line in lines {
             let line_bytes = line.as_bytes();
             match line_bytes[0] as char {
@@ -55,50 +63,292 @@ pub trait MeshBase<V: VertexBase>: Sized {
                     }
                 }
                 'f' => {
-                    let params = 1 + has_normals as usize + has_uv as usize;
-                    let mut vals = Vec::with_capacity(9);
-                    let seg: Vec<String> = line
-                        .replace("f", "")
-                        .as_str()
-                        .split(|ch| ch == ' ' || ch == '/')
-                        .map(|s| s.trim().to_string())
-                        .filter(|s| !s.is_empty())
-                        .collect();
-
-                    for i in 0..seg.len() {
-                        vals.push(seg[i].as_str().parse::<usize>().unwrap_or_else(|_| {
-                            cw_panic!("Could not parse index: \"{}\". Line: \"{}\"", seg[i], line);
-                        }));
+                    let corners: Vec<&str> = line.split_whitespace().skip(1).collect();
+                    if corners.len() < 3 {
+                        cw_panic!("Face has fewer than three vertices. Line: \"{}\"", line);
                     }
-
-                    let mut push = Triangle::<VertexVTN>::new();
-                    for k in 0..3 {
-                        push.v[k].v = verts[vals[params * k] - 1];
-                        if has_uv {
-                            push.v[k].t = texs[vals[(params * k) + 1] - 1];
-                        } else if has_normals {
-                            push.v[k].n = normals[vals[(params * k) + 1] - 1];
-                        }
-                        if has_normals && has_uv {
-                            push.v[k].n = normals[vals[(params * k) + 2] - 1];
-                        }
+                    let parse_index = |index: &str| {
+                        index.parse::<usize>().unwrap_or_else(|_| {
+                            cw_panic!("Could not parse index: \"{}\". Line: \"{}\"", index, line);
+                        })
+                    };
+                    let face: Vec<VertexVTN> = corners
+                        .iter()
+                        .map(|corner| {
+                            // Each corner is v, v/vt, v//vn or v/vt/vn
+                            let mut vert = VertexVTN::new();
+                            let mut indices = corner.split('/');
+                            vert.v = verts[parse_index(indices.next().unwrap()) - 1];
+                            if let Some(t) = indices.next().filter(|t| !t.is_empty()) {
+                                vert.t = texs[parse_index(t) - 1];
+                            }
+                            if let Some(n) = indices.next().filter(|n| !n.is_empty()) {
+                                vert.n = normals[parse_index(n) - 1];
+                            }
+                            vert
+                        })
+                        .collect();
+                    // Polygons are split into a fan of triangles around the first corner
+                    for i in 1..face.len() - 1 {
+                        tris.push(Triangle::from_verts(&[face[0], face[i], face[i + 1]]));
                     }
-                    tris.push(push);
                 }
                 _ => {}
             }
         }
         tris
     }
+    /// Loads obj data into an indexed buffer, merging vertices that land on the same
+    /// `QuantizedVertex` grid cell of size `epsilon`. Indices are stored as `u16` when
+    /// there are few enough unique vertices, otherwise as `u32`.
+    fn indexed_from_obj_data(
+        win: &mut Window,
+        data: &str,
+        epsilon: f32,
+    ) -> IndexedTriGPUBuffer<VertexVTN> {
+        let (verts, indices) = Self::indexed_obj_data(data, epsilon);
+        IndexedTriGPUBuffer::<VertexVTN>::from_data_smallest(win, &verts, &indices)
+    }
+    /// The unique vertices and triangle indices `indexed_from_obj_data` uploads.
+    fn indexed_obj_data(data: &str, epsilon: f32) -> (Vec<VertexVTN>, Vec<u32>) {
+        let tris = Self::tris_from_obj_data(data);
+        let mut verts = Vec::new();
+        let mut indices = Vec::with_capacity(tris.len() * 3);
+        let mut seen = HashMap::<QuantizedVertex, u32>::new();
+        for tri in tris.iter() {
+            for vert in tri.v.iter() {
+                let key = QuantizedVertex::new(vert, epsilon);
+                let index = *seen.entry(key).or_insert_with(|| {
+                    verts.push(*vert);
+                    (verts.len() - 1) as u32
+                });
+                indices.push(index);
+            }
+        }
+        (verts, indices)
+    }
+}
+
+/// Corrections applied to obj data after parsing, for meshes exported with a different
+/// winding order, unit scale, origin or texture coordinate convention.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ObjImportOptions {
+    /// Swaps the second and third vertex of each triangle, reversing its orientation.
+    pub flip_winding: bool,
+    /// Multiplies every position, applied after recentering.
+    pub scale: f32,
+    /// Shifts positions so the vertex centroid sits at the origin.
+    pub recenter: bool,
+    /// Replaces each texture `v` coordinate with `1 - v`.
+    pub flip_uv_y: bool,
+}
+impl Default for ObjImportOptions {
+    fn default() -> Self {
+        Self {
+            flip_winding: false,
+            scale: 1.0,
+            recenter: false,
+            flip_uv_y: false,
+        }
+    }
+}
+impl ObjImportOptions {
+    pub fn apply(&self, tris: &mut [Triangle<VertexVTN>]) {
+        let mut centroid = Vec3f32::new(0.0, 0.0, 0.0);
+        if self.recenter && !tris.is_empty() {
+            for tri in tris.iter() {
+                for vert in tri.v.iter() {
+                    centroid.add_eq_vec(&vert.v);
+                }
+            }
+            centroid.div_eq_num((tris.len() * 3) as f32);
+        }
+        for tri in tris.iter_mut() {
+            if self.flip_winding {
+                tri.v.swap(1, 2);
+            }
+            for vert in tri.v.iter_mut() {
+                vert.v = vert.v.sub_vec(&centroid).mul_num(self.scale);
+                if self.flip_uv_y {
+                    vert.t = Vec2f32::new(vert.t.get_x(), 1.0 - vert.t.get_y());
+                }
+            }
+        }
+    }
 }
 
 pub struct Mesh3D<V: VertexBase> {
     pub buffer: TriGPUBuffer<V>,
+    cpu_copy: Option<TriCPUBuffer<V>>,
+}
+impl<V: VertexBase> Mesh3D<V> {
+    /// Bakes each transform into its triangles and uploads them all as a single mesh.
+    /// Normals are transformed by the inverse-transpose of each transform.
+    pub fn merge(win: &mut Window, parts: &[(&[Triangle<V>], Mat4f32)]) -> Self {
+        let mut tris = Vec::new();
+        for (data, transform) in parts.iter() {
+            let normal_transform = Mat4f32::from_matrix(&matrices::normal_matrix(transform));
+            for tri in data.iter() {
+                let mut merged = *tri;
+                for vert in merged.v.iter_mut() {
+                    *vert = vert.transformed(transform, &normal_transform);
+                }
+                tris.push(merged);
+            }
+        }
+        Self::from_data(win, &tris)
+    }
+    /// Keeps a CPU-side mirror of the triangles so queries such as `bounding_sphere` read
+    /// RAM instead of the GPU buffer. Enabling it reads the buffer back once.
+    pub fn set_keep_cpu_copy(&mut self, keep: bool) {
+        if !keep {
+            self.cpu_copy = None;
+        } else if self.cpu_copy.is_none() {
+            self.cpu_copy = Some(TriCPUBuffer::from_data(&self.buffer.get_data()));
+        }
+    }
+    pub fn keeps_cpu_copy(&self) -> bool {
+        self.cpu_copy.is_some()
+    }
+    pub fn cpu_copy(&self) -> Option<&TriCPUBuffer<V>> {
+        self.cpu_copy.as_ref()
+    }
+    /// Replaces triangles from `start` on, keeping the CPU mirror in sync.
+    pub fn sub_data(&mut self, start: usize, data: &Vec<Triangle<V>>) {
+        self.buffer.sub_data(start, data.len(), data);
+        if let Some(cpu_copy) = self.cpu_copy.as_mut() {
+            cpu_copy.sub_data(start, data.len(), data);
+        }
+    }
+    /// The mesh triangles, borrowed from the CPU mirror if kept, otherwise read back from
+    /// the GPU buffer.
+    pub fn triangles(&self) -> Cow<'_, [Triangle<V>]> {
+        match self.cpu_copy.as_ref() {
+            Some(cpu_copy) => Cow::Borrowed(cpu_copy.as_slice()),
+            None => Cow::Owned(self.buffer.get_data()),
+        }
+    }
+    /// Approximate bounding sphere of the mesh vertices.
+    pub fn bounding_sphere(&self) -> Sphere {
+        let points: Vec<Vec3f32> = self
+            .triangles()
+            .iter()
+            .flat_map(|tri| tri.v.iter().map(|v| v.position()))
+            .collect();
+        Sphere::from_points(&points)
+    }
+    /// Axis-aligned bounds of the mesh vertices in model space.
+    pub fn bounding_box(&self) -> Aabb {
+        let points: Vec<Vec3f32> = self
+            .triangles()
+            .iter()
+            .flat_map(|tri| tri.v.iter().map(|v| v.position()))
+            .collect();
+        Aabb::from_points(&points)
+    }
+    /// Centre of mass of the mesh surface: the average of each triangle's centroid,
+    /// weighted by its area. Meshes with no area use the average vertex position, and
+    /// empty meshes give the origin.
+    pub fn centroid(&self) -> Vec3f32 {
+        Self::triangles_centroid(&self.triangles())
+    }
+    fn triangles_centroid(tris: &[Triangle<V>]) -> Vec3f32 {
+        let mut weighted = Vec3f32::new(0.0, 0.0, 0.0);
+        let mut vertex_sum = Vec3f32::new(0.0, 0.0, 0.0);
+        let mut total_area = 0.0;
+        for tri in tris.iter() {
+            let mut tri_sum = Vec3f32::new(0.0, 0.0, 0.0);
+            for vert in tri.v.iter() {
+                tri_sum.add_eq_vec(&vert.position());
+            }
+            let area = tri.area();
+            weighted.add_eq_vec(&tri_sum.mul_num(area / 3.0));
+            vertex_sum.add_eq_vec(&tri_sum);
+            total_area += area;
+        }
+        if total_area > 0.0 {
+            weighted.div_num(total_area)
+        } else if !tris.is_empty() {
+            vertex_sum.div_num((tris.len() * 3) as f32)
+        } else {
+            vertex_sum
+        }
+    }
+    /// Moves every vertex by `offset`, keeping the CPU mirror in sync.
+    pub fn translate(&mut self, offset: &Vec3f32) {
+        let transform = Mat4f32::from_matrix(&matrices::translation_3d(offset));
+        let normal_transform = Mat4f32::from_matrix(&matrices::identity::<f32>(4));
+        let tris: Vec<Triangle<V>> = self
+            .triangles()
+            .iter()
+            .map(|tri| {
+                let mut moved = *tri;
+                for vert in moved.v.iter_mut() {
+                    *vert = vert.transformed(&transform, &normal_transform);
+                }
+                moved
+            })
+            .collect();
+        self.set_data(&tris);
+    }
+    /// Sum of the areas of every triangle in the mesh.
+    pub fn surface_area(&self) -> f32 {
+        self.triangles().iter().map(|tri| tri.area()).sum()
+    }
+    /// Returns `n` points spread uniformly over the mesh surface. Triangles are chosen
+    /// weighted by area, then sampled with uniform barycentric coordinates.
+    /// Returns no points if the mesh has no area.
+    pub fn sample_points(&self, n: usize, rng: &mut Pcg32) -> Vec<Vec3f32> {
+        Self::sample_triangles(&self.triangles(), n, rng)
+    }
+    fn sample_triangles(tris: &[Triangle<V>], n: usize, rng: &mut Pcg32) -> Vec<Vec3f32> {
+        let mut cumulative = Vec::with_capacity(tris.len());
+        let mut total = 0.0;
+        for tri in tris.iter() {
+            total += tri.area();
+            cumulative.push(total);
+        }
+        if total <= 0.0 {
+            return Vec::new();
+        }
+        let mut points = Vec::with_capacity(n);
+        for _ in 0..n {
+            let target = rng.next_f32() * total;
+            let index = cumulative
+                .partition_point(|area| *area <= target)
+                .min(tris.len() - 1);
+            let tri = &tris[index];
+            let (mut u, mut v) = (rng.next_f32(), rng.next_f32());
+            if u + v > 1.0 {
+                u = 1.0 - u;
+                v = 1.0 - v;
+            }
+            let a = tri.v[0].position();
+            let ab = tri.v[1].position().sub_vec(&a);
+            let ac = tri.v[2].position().sub_vec(&a);
+            points.push(a.add_vec(&ab.mul_num(u)).add_vec(&ac.mul_num(v)));
+        }
+        points
+    }
+}
+impl Mesh3D<VertexVTN> {
+    /// Parses obj data and applies the import options before uploading it.
+    pub fn from_obj(win: &mut Window, data: &str, options: &ObjImportOptions) -> Self {
+        Self::from_data(win, &Self::parse_obj(data, options))
+    }
+    /// The triangles `from_obj` would upload. Needs no window or GL context, so the
+    /// import pipeline can be checked headless.
+    pub fn parse_obj(data: &str, options: &ObjImportOptions) -> Vec<Triangle<VertexVTN>> {
+        let mut tris = Self::tris_from_obj_data(data);
+        options.apply(&mut tris);
+        tris
+    }
 }
 impl<V: VertexBase> MeshBase<V> for Mesh3D<V> {
     fn new(win: &mut Window) -> Self {
         Self {
             buffer: TriGPUBuffer::<V>::new(win),
+            cpu_copy: None,
         }
     }
     fn n_tris(&self) -> i32 {
@@ -106,6 +356,469 @@ impl<V: VertexBase> MeshBase<V> for Mesh3D<V> {
     }
     fn set_data(&mut self, data: &Vec<Triangle<V>>) {
         self.buffer.set_data(data);
+        if let Some(cpu_copy) = self.cpu_copy.as_mut() {
+            cpu_copy.set_data(data);
+        }
+    }
+}
+
+/// Points drawn as a connected line strip or as separate points, for plotting
+/// trajectories and point clouds.
+pub struct LineMesh {
+    pub vbo: Buffer,
+    pub vao: VertexArray,
+    mode: GlDrawMode,
+    n_points: i32,
+}
+impl LineMesh {
+    /// An empty mesh drawn as a line strip.
+    pub fn new(win: &mut Window) -> Self {
+        Self::new_with_mode(win, GlDrawMode::LineStrip)
+    }
+    /// An empty mesh drawn as separate points.
+    pub fn new_points(win: &mut Window) -> Self {
+        Self::new_with_mode(win, GlDrawMode::Points)
+    }
+    pub fn new_with_mode(win: &mut Window, mode: GlDrawMode) -> Self {
+        Self {
+            vbo: Buffer::new(win, GlBufferType::ArrayBuffer),
+            vao: VertexArray::new(win),
+            mode,
+            n_points: 0,
+        }
+    }
+    /// Replaces the points, uploaded as `VertexV` at attribute location 0.
+    pub fn set_points(&mut self, points: &[Vec3f32]) {
+        let verts: Vec<VertexV> = points.iter().map(|p| VertexV { v: *p }).collect();
+        self.n_points = verts.len() as i32;
+        self.vao.bind();
+        self.vbo.bind();
+        for attrib in VertexV::get_attribs() {
+            self.vao.attrib_ptr(&attrib);
+        }
+        self.vbo.buffer_data(
+            verts.len() * std::mem::size_of::<VertexV>(),
+            verts.as_ptr() as *const f32,
+            GlStorageMode::Dynamic,
+        );
+        self.vbo.unbind();
+        self.vao.unbind();
+    }
+    pub fn mode(&self) -> GlDrawMode {
+        self.mode
+    }
+    pub fn set_mode(&mut self, mode: GlDrawMode) {
+        self.mode = mode;
+    }
+    pub fn n_points(&self) -> i32 {
+        self.n_points
+    }
+    /// Number of points or line segments the draw call covers. A line strip of N
+    /// points has N - 1 segments.
+    pub fn n_primitives(&self) -> i32 {
+        match self.mode {
+            GlDrawMode::LineStrip => (self.n_points - 1).max(0),
+            GlDrawMode::Lines => self.n_points / 2,
+            GlDrawMode::Triangles => self.n_points / 3,
+            GlDrawMode::TriangleStrip | GlDrawMode::TriangleFan => (self.n_points - 2).max(0),
+            GlDrawMode::Points | GlDrawMode::LineLoop => self.n_points,
+        }
+    }
+    /// Draws every point with the already bound `shader`.
+    pub fn render(&self, shader: &GPUShader) {
+        self.vao.bind();
+        shader.draw_primitives(self.mode, self.n_primitives());
+    }
+}
+
+/// Triangles with each float vertex attribute in its own buffer, so one attribute such
+/// as the positions can be rewritten every frame while the others stay static. Every
+/// attribute must describe the same number of vertices.
+pub struct MultiBufferMesh {
+    pub vao: VertexArray,
+    /// Location, components per vertex and buffer of each attribute.
+    attributes: Vec<(u32, u32, Buffer)>,
+    n_verts: i32,
+}
+impl MultiBufferMesh {
+    pub fn new(win: &mut Window) -> Self {
+        Self {
+            vao: VertexArray::new(win),
+            attributes: Vec::new(),
+            n_verts: 0,
+        }
+    }
+    /// Uploads `data` as an attribute of `size` floats per vertex, from 1 to 4, at
+    /// shader input `location`, hinted with `usage`.
+    pub fn add_attribute(
+        &mut self,
+        win: &mut Window,
+        location: u32,
+        size: u32,
+        data: &[f32],
+        usage: GlStorageMode,
+    ) {
+        if self.attributes.iter().any(|(l, _, _)| *l == location) {
+            cw_panic!("MultiBufferMesh already has an attribute at location {}.", location);
+        }
+        if !(1..=4).contains(&size) || data.len() % size as usize != 0 {
+            cw_panic!(
+                "MultiBufferMesh attribute {}: {} floats are not whole vertices of size {}.",
+                location,
+                data.len(),
+                size
+            );
+        }
+        let n_verts = (data.len() / size as usize) as i32;
+        if !self.attributes.is_empty() && n_verts != self.n_verts {
+            cw_panic!(
+                "MultiBufferMesh attribute {} has {} vertices, expected {}.",
+                location,
+                n_verts,
+                self.n_verts
+            );
+        }
+        let buff = Buffer::new(win, GlBufferType::ArrayBuffer);
+        self.vao.bind();
+        buff.bind();
+        self.vao.attrib_ptr(&VertexAttrib::new(
+            location,
+            size,
+            size as usize * std::mem::size_of::<f32>(),
+            0,
+        ));
+        buff.buffer_data(std::mem::size_of_val(data), data.as_ptr(), usage);
+        buff.unbind();
+        self.vao.unbind();
+        self.n_verts = n_verts;
+        self.attributes.push((location, size, buff));
+    }
+    /// Overwrites the attribute at `location` in place, leaving the other buffers
+    /// untouched. `data` must hold the same number of floats as before.
+    pub fn update_attribute(&mut self, location: u32, data: &[f32]) {
+        let (_, size, buff) = self.attribute(location);
+        let expected = self.n_verts as usize * *size as usize;
+        if data.len() != expected {
+            cw_panic!(
+                "MultiBufferMesh attribute {} update has {} floats, expected {}.",
+                location,
+                data.len(),
+                expected
+            );
+        }
+        buff.bind();
+        buff.buffer_sub_data(0, std::mem::size_of_val(data), data.as_ptr());
+        buff.unbind();
+    }
+    /// Reads the attribute at `location` back from the GPU.
+    pub fn get_attribute(&self, location: u32) -> Vec<f32> {
+        self.attribute(location).2.read_typed::<f32>()
+    }
+    pub fn n_verts(&self) -> i32 {
+        self.n_verts
+    }
+    /// Draws the vertices as triangles with the already bound `shader`.
+    pub fn render(&self, shader: &GPUShader) {
+        self.vao.bind();
+        shader.draw_primitives(GlDrawMode::Triangles, self.n_verts / 3);
+        self.vao.unbind();
+    }
+    fn attribute(&self, location: u32) -> &(u32, u32, Buffer) {
+        self.attributes
+            .iter()
+            .find(|(l, _, _)| *l == location)
+            .unwrap_or_else(|| {
+                cw_panic!("MultiBufferMesh has no attribute at location {}.", location);
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Particle {
+    pub pos: Vec3f32,
+    pub vel: Vec3f32,
+    /// Seconds left before the particle is removed.
+    pub life: f32,
+    pub color: Color,
+    pub size: f32,
+}
+
+/// Particles emitted from `origin`, simulated on the CPU and drawn as point sprites.
+///
+/// Each point has its position at attribute location 0, its RGBA colour at 1 and its
+/// size at 2. The vertex shader should write the size to `gl_PointSize`, which desktop
+/// GL only honours with `GlCapability::VertexProgramPointSize` enabled.
+pub struct ParticleSystem {
+    pub origin: Vec3f32,
+    /// Particles emitted per second.
+    pub emission_rate: f32,
+    /// Seconds each new particle lives.
+    pub lifetime: f32,
+    /// Starting velocity of new particles.
+    pub velocity: Vec3f32,
+    /// Each component of a new particle's velocity is offset by up to this much.
+    pub spread: f32,
+    pub gravity: Vec3f32,
+    pub color: Color,
+    pub size: f32,
+    particles: Vec<Particle>,
+    pending_emission: f32,
+    rng: Pcg32,
+    vbo: Buffer,
+    vao: VertexArray,
+    vertex_data: Vec<f32>,
+}
+impl ParticleSystem {
+    const FLOATS_PER_PARTICLE: usize = 8;
+
+    /// A system at the origin emitting 10 white particles per second that live for one
+    /// second, under a gravity of -9.8 on Y.
+    pub fn new(win: &mut Window) -> Self {
+        Self {
+            origin: Vec3f32::new(0.0, 0.0, 0.0),
+            emission_rate: 10.0,
+            lifetime: 1.0,
+            velocity: Vec3f32::new(0.0, 1.0, 0.0),
+            spread: 0.5,
+            gravity: Vec3f32::new(0.0, -9.8, 0.0),
+            color: Color::new(1.0, 1.0, 1.0, 1.0),
+            size: 4.0,
+            particles: Vec::new(),
+            pending_emission: 0.0,
+            rng: Pcg32::new(0x5eed),
+            vbo: Buffer::new(win, GlBufferType::ArrayBuffer),
+            vao: VertexArray::new(win),
+            vertex_data: Vec::new(),
+        }
+    }
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.pending_emission = 0.0;
+    }
+    /// Spawns `n` particles at `origin` immediately.
+    pub fn emit(&mut self, n: usize) {
+        for _ in 0..n {
+            let mut vel = self.velocity;
+            for i in 0..3 {
+                vel[i] += self.rng.range(-self.spread, self.spread);
+            }
+            self.particles.push(Particle {
+                pos: self.origin,
+                vel,
+                life: self.lifetime,
+                color: self.color,
+                size: self.size,
+            });
+        }
+    }
+    /// Moves every particle by its velocity over `dt` seconds, applies gravity to the
+    /// velocity, removes expired particles and emits new ones at `emission_rate`.
+    pub fn update(&mut self, dt: f32) {
+        Self::integrate(&mut self.particles, &self.gravity, dt);
+        self.pending_emission += self.emission_rate * dt;
+        let n = self.pending_emission.floor();
+        self.pending_emission -= n;
+        self.emit(n as usize);
+    }
+    fn integrate(particles: &mut Vec<Particle>, gravity: &Vec3f32, dt: f32) {
+        for p in particles.iter_mut() {
+            p.pos.add_eq_vec(&p.vel.mul_num(dt));
+            p.vel.add_eq_vec(&gravity.mul_num(dt));
+            p.life -= dt;
+        }
+        particles.retain(|p| p.life > 0.0);
+    }
+    /// Draws the particles farthest from `camera` first so blended sprites overlap
+    /// correctly. Camera uniforms are left to the caller, see `CameraUniforms`.
+    pub fn render(&mut self, shader: &GPUShader, camera: &PerspectiveCamera3D) {
+        let eye = camera.pos;
+        let dist = |p: &Particle| {
+            let d = p.pos.sub_vec(&eye);
+            d.dot(&d)
+        };
+        self.particles
+            .sort_by(|a, b| dist(b).partial_cmp(&dist(a)).unwrap_or(std::cmp::Ordering::Equal));
+        self.vertex_data.clear();
+        for p in self.particles.iter() {
+            self.vertex_data.extend_from_slice(p.pos.get_internal_array());
+            self.vertex_data.extend_from_slice(&[p.color.r, p.color.g, p.color.b, p.color.a]);
+            self.vertex_data.push(p.size);
+        }
+        let stride = Self::FLOATS_PER_PARTICLE * std::mem::size_of::<f32>();
+        self.vao.bind();
+        self.vbo.bind();
+        self.vao.attrib_ptr(&VertexAttrib::new(0, 3, stride, 0));
+        self.vao.attrib_ptr(&VertexAttrib::new(1, 4, stride, 3 * 4));
+        self.vao.attrib_ptr(&VertexAttrib::new(2, 1, stride, 7 * 4));
+        self.vbo.buffer_data(
+            self.vertex_data.len() * std::mem::size_of::<f32>(),
+            self.vertex_data.as_ptr(),
+            GlStorageMode::Stream,
+        );
+        self.vbo.unbind();
+        shader.draw_primitives(GlDrawMode::Points, self.particles.len() as i32);
+        self.vao.unbind();
+    }
+}
+
+/// Tracks the window size from resize events, keeping the GL viewport and the
+/// projection aspect in sync.
+pub struct Viewport {
+    size: (i32, i32),
+}
+impl Viewport {
+    pub fn new(size: (i32, i32)) -> Self {
+        Self { size }
+    }
+    /// Resizes the GL viewport to cover `size`. Sizes with a zero or negative dimension,
+    /// like those of a minimized window, are ignored.
+    pub fn resize(&mut self, win: &mut Window, size: (i32, i32)) {
+        if self.set_size(size) {
+            win.set_resolution(size);
+        }
+    }
+    /// Records `size` unless it is ignored by `resize`. Returns true if it was recorded.
+    fn set_size(&mut self, size: (i32, i32)) -> bool {
+        if size.0 <= 0 || size.1 <= 0 {
+            return false;
+        }
+        self.size = size;
+        true
+    }
+    /// Applies the latest resize event, if any. Returns true if the size changed.
+    pub fn update(&mut self, win: &mut Window, man: &dyn EventManager) -> bool {
+        if let (size, true) = man.screen_size_changed() {
+            if size != self.size {
+                self.resize(win, size);
+                return self.size == size;
+            }
+        }
+        false
+    }
+    pub fn size(&self) -> (i32, i32) {
+        self.size
+    }
+    /// Height divided by width, as expected by `PerspectiveCamera3D::projection`.
+    pub fn aspect(&self) -> f32 {
+        if self.size.0 == 0 {
+            1.0
+        } else {
+            self.size.1 as f32 / self.size.0 as f32
+        }
+    }
+    /// Screen rectangle covered by `aabb` after `model_view_projection`, in pixels with
+    /// the origin at the top left, clamped to the viewport.
+    ///
+    /// Edges crossing the near plane are clipped to it, so boxes the camera is inside of
+    /// still give a tight rect. Returns None if the box is entirely behind the near plane
+    /// or outside the viewport.
+    pub fn project_aabb(&self, aabb: &Aabb, model_view_projection: &Mat4<f32>) -> Option<Rect> {
+        let clip: Vec<Vec4f32> = aabb
+            .corners()
+            .iter()
+            .map(|c| {
+                let corner = Vec4f32::new(c.get_x(), c.get_y(), c.get_z(), 1.0);
+                model_view_projection.mul_row_vec(&corner)
+            })
+            .collect();
+        // Depth is mapped to [0, 1], so clip z is negative in front of the near plane
+        let in_front = |p: &Vec4f32| p.get_z() >= 0.0;
+        let mut points = Vec::with_capacity(clip.len() + 12);
+        for (i, a) in clip.iter().enumerate() {
+            if in_front(a) {
+                points.push(*a);
+            }
+            for axis in 0..3 {
+                let j = i ^ (1 << axis);
+                let b = &clip[j];
+                if j > i && in_front(a) != in_front(b) {
+                    let t = a.get_z() / (a.get_z() - b.get_z());
+                    points.push(a.add_vec(&b.sub_vec(a).mul_num(t)));
+                }
+            }
+        }
+        if points.is_empty() {
+            return None;
+        }
+        let (mut min_x, mut min_y) = (f32::INFINITY, f32::INFINITY);
+        let (mut max_x, mut max_y) = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for p in points.iter() {
+            let ndc: Vec3f32 = p.perspective_divide();
+            let (x, y) = (ndc.get_x(), ndc.get_y());
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+        let (min_x, min_y) = (min_x.max(-1.0), min_y.max(-1.0));
+        let (max_x, max_y) = (max_x.min(1.0), max_y.min(1.0));
+        if min_x > max_x || min_y > max_y {
+            return None;
+        }
+        let (w, h) = (self.size.0 as f32, self.size.1 as f32);
+        Some(Rect::new(
+            Vec2f32::new((min_x * 0.5 + 0.5) * w, (0.5 - max_y * 0.5) * h),
+            Vec2f32::new((max_x * 0.5 + 0.5) * w, (0.5 - min_y * 0.5) * h),
+        ))
+    }
+}
+
+/// Random positional jitter that fades out linearly over its duration.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraShake {
+    amplitude: f32,
+    duration: f32,
+    remaining: f32,
+    offset: Vec3f32,
+    rng: Pcg32,
+}
+impl CameraShake {
+    pub fn new() -> Self {
+        Self {
+            amplitude: 0.0,
+            duration: 0.0,
+            remaining: 0.0,
+            offset: Vec3f32::new(0.0, 0.0, 0.0),
+            rng: Pcg32::new(0x5eed),
+        }
+    }
+    /// Starts a shake of up to `amplitude` world units lasting `duration` seconds. A
+    /// weaker shake than the one already running is ignored.
+    pub fn add(&mut self, amplitude: f32, duration: f32) {
+        if duration > 0.0 && amplitude >= self.magnitude() {
+            self.amplitude = amplitude;
+            self.duration = duration;
+            self.remaining = duration;
+        }
+    }
+    /// Current maximum offset along each axis.
+    pub fn magnitude(&self) -> f32 {
+        if self.duration <= 0.0 {
+            0.0
+        } else {
+            self.amplitude * self.remaining / self.duration
+        }
+    }
+    pub fn offset(&self) -> Vec3f32 {
+        self.offset
+    }
+    /// Advances the shake by `delta` seconds and picks a new random offset.
+    pub fn update(&mut self, delta: f32) {
+        self.remaining = f32::max(self.remaining - delta, 0.0);
+        let mag = self.magnitude();
+        self.offset = Vec3f32::new(
+            self.rng.range(-mag, mag),
+            self.rng.range(-mag, mag),
+            self.rng.range(-mag, mag),
+        );
+    }
+}
+impl Default for CameraShake {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -114,27 +827,142 @@ pub struct PerspectiveCamera3D {
     pub near: f32,
     pub far: f32,
     pub pos: Vec3f32,
-    pub rot: Vec3f32,
+    pub orientation: Quaternionf32,
+    /// Offset added to `pos` when building view matrices.
+    pub shake: CameraShake,
+    /// Last projection built, keyed on (fov, near, far, aspect).
+    projection_cache: RefCell<Option<([f32; 4], Mat4<f32>)>>,
 }
 impl PerspectiveCamera3D {
+    pub fn new(fov: f32, near: f32, far: f32, pos: Vec3f32) -> Self {
+        Self {
+            fov,
+            near,
+            far,
+            pos,
+            orientation: Quaternionf32::new(0.0, 0.0, 0.0, 1.0),
+            shake: CameraShake::new(),
+            projection_cache: RefCell::new(None),
+        }
+    }
+    /// Builds an orientation matching `matrices::rotation_euler` applied to a camera
+    /// looking down +Z, where positive x pitches up and positive y yaws towards +X.
+    pub fn euler_orientation(rot: &Vec3f32) -> Quaternionf32 {
+        let qx = Quaternionf32::angle_axis(-rot.get_x(), &Vec3f32::new(1.0, 0.0, 0.0));
+        let qy = Quaternionf32::angle_axis(rot.get_y(), &Vec3f32::new(0.0, 1.0, 0.0));
+        let qz = Quaternionf32::angle_axis(-rot.get_z(), &Vec3f32::new(0.0, 0.0, 1.0));
+        qz.hamilton(&qy).hamilton(&qx)
+    }
+    pub fn set_euler(&mut self, rot: &Vec3f32) {
+        self.orientation = Self::euler_orientation(rot);
+    }
+    /// Euler angles of `orientation`, as accepted by `set_rotation`. The pitch is kept in
+    /// `[-pi/2, pi/2]`; looking straight up or down, the roll is folded into the yaw.
+    pub fn rotation(&self) -> Vec3f32 {
+        let (right, up, look) = (self.right_vector(), self.up_vector(), self.look_vector());
+        let yaw = f32::asin((-right.get_z()).clamp(-1.0, 1.0));
+        if right.get_z().abs() < 1.0 - 1e-6 {
+            let pitch = -f32::atan2(up.get_z(), look.get_z());
+            let roll = -f32::atan2(right.get_y(), right.get_x());
+            Vec3f32::new(pitch, yaw, roll)
+        } else {
+            Vec3f32::new(0.0, yaw, -f32::atan2(-up.get_x(), up.get_y()))
+        }
+    }
+    /// Sets `orientation` from euler angles, like the camera's old `rot` field.
+    pub fn set_rotation(&mut self, rot: Vec3f32) {
+        self.set_euler(&rot);
+    }
+    /// Orientation looking along `look` with no roll.
+    pub fn look_orientation(look: &Vec3f32) -> Quaternionf32 {
+        let look = look.normalized();
+        let pitch = f32::asin(look.get_y().clamp(-1.0, 1.0));
+        let yaw = f32::atan2(look.get_x(), look.get_z());
+        Self::euler_orientation(&Vec3f32::new(pitch, yaw, 0.0))
+    }
+    /// Shakes the view by up to `amplitude` world units, fading out over `duration`
+    /// seconds as `update_shake` is called.
+    pub fn add_shake(&mut self, amplitude: f32, duration: f32) {
+        self.shake.add(amplitude, duration);
+    }
+    pub fn update_shake(&mut self, delta: f32) {
+        self.shake.update(delta);
+    }
+    /// Eases the position towards `target_pos` and the view direction towards
+    /// `target_look`. Higher `stiffness` follows more tightly; the result is independent
+    /// of the frame rate. Any roll is removed.
+    pub fn smooth_follow(
+        &mut self,
+        target_pos: &Vec3f32,
+        target_look: &Vec3f32,
+        stiffness: f32,
+        delta: f32,
+    ) {
+        let t = 1.0 - f32::exp(-stiffness * delta);
+        self.pos += target_pos.sub_vec(&self.pos).mul_num(t);
+        let look = self.look_vector();
+        let look = look + target_look.normalized().sub_vec(&look).mul_num(t);
+        if look.len() > f32::EPSILON {
+            self.orientation = Self::look_orientation(&look);
+        }
+    }
+    /// Pitches about the camera's local right axis and yaws about the world up axis.
+    pub fn rotate_local(&mut self, pitch: f32, yaw: f32) {
+        let qx = Quaternionf32::angle_axis(-pitch, &Vec3f32::new(1.0, 0.0, 0.0));
+        let qy = Quaternionf32::angle_axis(yaw, &Vec3f32::new(0.0, 1.0, 0.0));
+        self.orientation = qy.hamilton(&self.orientation).hamilton(&qx).normalized();
+    }
+    pub fn up_vector(&self) -> Vec3f32 {
+        self.orientation.rotate_vec(&Vec3f32::new(0.0, 1.0, 0.0))
+    }
+    /// The camera's local right axis. `right_vector`, `up_vector` and `look_vector`
+    /// form a right-handed orthonormal basis, with `right x up = look`.
+    pub fn right_vector(&self) -> Vec3f32 {
+        self.orientation.rotate_vec(&Vec3f32::new(1.0, 0.0, 0.0))
+    }
+    /// The projection is only rebuilt when the fov, clip planes or aspect change.
     pub fn projection(&self, aspect: f32) -> Mat4<f32> {
-        matrices::perspective(self.fov, aspect, self.near, self.far)
+        let key = [self.fov, self.near, self.far, aspect];
+        let mut cache = self.projection_cache.borrow_mut();
+        match cache.as_ref() {
+            Some((cached_key, mat)) if *cached_key == key => mat.cm_copy(),
+            _ => {
+                let mat = matrices::perspective(self.fov, aspect, self.near, self.far);
+                let ret = mat.cm_copy();
+                *cache = Some((key, mat));
+                ret
+            }
+        }
     }
     pub fn camera_matrix(&self) -> Mat4<f32> {
-        let up = Vec3f32::new(0.0, 1.0, 0.0);
-        let target = Vec4f32::new(0.0, 0.0, 1.0, 1.0);
-        let camera_rot = matrices::rotation_euler(&self.rot);
-        let camera_rot_vec = camera_rot.mul_col_vec(&target);
-        let target = &self.pos + Vec3f32::new_vec(&camera_rot_vec);
-        matrices::look_at_3d(&self.pos, &target, &up)
+        let pos = self.pos + self.shake.offset();
+        let target = pos + self.look_vector();
+        matrices::look_at_3d(&pos, &target, &self.up_vector())
     }
     pub fn view(&self) -> Mat4<f32> {
         self.camera_matrix().inverse()
     }
+    /// Right-handed view matrix built directly with `matrices::look_at_rh`.
+    ///
+    /// The camera looks along its rotated forward axis, but view space is right-handed
+    /// (forward is -Z), so this must be paired with a right-handed projection.
+    pub fn view_rh(&self) -> Mat4<f32> {
+        let pos = self.pos + self.shake.offset();
+        let target = pos + self.look_vector();
+        matrices::look_at_rh(&pos, &target, &self.up_vector())
+    }
+    /// Unprojects a point in normalized device coordinates back into world space.
+    ///
+    /// Points with a w component near zero after unprojection lie at infinity and are
+    /// returned without the perspective divide.
+    pub fn unproject(&self, ndc: Vec3f32, aspect: f32) -> Vec3f32 {
+        let view_proj = self.view().mul_mat(&self.projection(aspect));
+        let inv_view_proj = view_proj.to_generic().inverse().to_mat4();
+        let clip = Vec4f32::new(ndc.get_x(), ndc.get_y(), ndc.get_z(), 1.0);
+        inv_view_proj.mul_row_vec(&clip).perspective_divide()
+    }
     pub fn look_vector(&self) -> Vec3f32 {
-        let target = Vec4f32::new(0.0, 0.0, 1.0, 1.0);
-        let m_rot = matrices::rotation_euler(&self.rot);
-        Vec3f32::new_vec(&m_rot.mul_col_vec(&target))
+        self.orientation.rotate_vec(&Vec3f32::new(0.0, 0.0, 1.0))
     }
     pub fn debug_controls(
         &mut self,
@@ -144,8 +972,8 @@ impl PerspectiveCamera3D {
         rot_speed: f32,
     ) {
         let clv = self.look_vector();
+        let right = self.right_vector();
         let mut forward = Vec3f32::new(0.0, 0.0, 0.0);
-        let up = Vec3f32::new(0.0, 1.0, 0.0);
         let mut rotate = Vec3f32::new(0.0, 0.0, 0.0);
         if man.key_pressed(Key::W) {
             forward += &clv;
@@ -154,10 +982,10 @@ impl PerspectiveCamera3D {
             forward -= &clv;
         }
         if man.key_pressed(Key::D) {
-            forward -= clv.cross(&up);
+            forward += &right;
         }
         if man.key_pressed(Key::A) {
-            forward += clv.cross(&up);
+            forward -= &right;
         }
         if man.key_pressed(Key::Q) || man.key_pressed(Key::Space) {
             forward += Vec3f32::new(0.0, 1.0, 0.0);
@@ -177,21 +1005,332 @@ impl PerspectiveCamera3D {
         if man.key_pressed(Key::Down) {
             rotate.set_x(-rot_speed);
         }
-        self.rot += rotate * delta;
+        self.rotate_local(rotate.get_x() * delta, rotate.get_y() * delta);
         self.pos += forward.normalized() * move_speed * delta;
-        const NEAR_PI_OVER_2: f32 = std::f32::consts::PI * 0.48;
-        if self.rot.get_x() >= NEAR_PI_OVER_2 {
-            self.rot.set_x(NEAR_PI_OVER_2);
+    }
+}
+
+/// Uploads a camera to a GLSL struct of the form
+/// `struct Camera { mat4 projection; mat4 view; vec3 position; };`.
+pub struct CameraUniforms<'a> {
+    pub camera: &'a PerspectiveCamera3D,
+}
+impl<'a> CameraUniforms<'a> {
+    pub fn new(camera: &'a PerspectiveCamera3D) -> Self {
+        Self { camera }
+    }
+    /// Sets `{prefix}.projection`, `{prefix}.view` and `{prefix}.position`. The shader
+    /// must be in use. The position includes any camera shake, matching the view.
+    pub fn upload(&self, shader: &GPUShader, prefix: &str, aspect: f32) {
+        let [projection, view, position] = Self::uniform_names(prefix);
+        shader.set_mat4f(&projection, &self.camera.projection(aspect));
+        shader.set_mat4f(&view, &self.camera.view());
+        shader.set_vec3f(&position, &self.position());
+    }
+    /// The `projection`, `view` and `position` member names `upload` sets.
+    pub fn uniform_names(prefix: &str) -> [String; 3] {
+        ["projection", "view", "position"].map(|member| format!("{}.{}", prefix, member))
+    }
+    /// The eye position `upload` sets, including any camera shake.
+    pub fn position(&self) -> Vec3f32 {
+        self.camera.pos + self.camera.shake.offset()
+    }
+}
+
+/// Camera circling a target point, for model viewers. The camera sits `distance` away
+/// from `target`; positive `yaw` turns it to look towards +X and positive `pitch` raises
+/// it above the target to look down. At zero yaw and pitch it looks along +Z.
+pub struct OrbitCamera {
+    pub target: Vec3f32,
+    pub distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// `zoom` will not bring the camera closer than this.
+    pub min_distance: f32,
+}
+impl OrbitCamera {
+    /// Pitch is kept just short of straight up or down so the view never flips.
+    pub const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+    pub fn new(target: Vec3f32, distance: f32) -> Self {
+        Self {
+            target,
+            distance,
+            yaw: 0.0,
+            pitch: 0.0,
+            min_distance: 0.01,
         }
-        if self.rot.get_x() <= -NEAR_PI_OVER_2 {
-            self.rot.set_x(-NEAR_PI_OVER_2);
+    }
+    pub fn look_vector(&self) -> Vec3f32 {
+        let (sy, cy) = (f32::sin(self.yaw), f32::cos(self.yaw));
+        let (sp, cp) = (f32::sin(self.pitch), f32::cos(self.pitch));
+        Vec3f32::new(sy * cp, -sp, cy * cp)
+    }
+    pub fn right_vector(&self) -> Vec3f32 {
+        Vec3f32::new(f32::cos(self.yaw), 0.0, -f32::sin(self.yaw))
+    }
+    pub fn up_vector(&self) -> Vec3f32 {
+        self.look_vector().cross(&self.right_vector())
+    }
+    pub fn position(&self) -> Vec3f32 {
+        self.target - self.look_vector().mul_num(self.distance)
+    }
+    /// Rotates around the target by `dx` radians of yaw and `dy` radians of pitch.
+    pub fn orbit(&mut self, dx: f32, dy: f32) {
+        self.yaw += dx;
+        self.pitch = (self.pitch + dy).clamp(-Self::MAX_PITCH, Self::MAX_PITCH);
+    }
+    /// Moves the target along the camera's right and up axes. The offsets are scaled by
+    /// the distance, so panning feels the same at any zoom level.
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        let offset = self.right_vector().mul_num(dx) + self.up_vector().mul_num(dy);
+        self.target += offset.mul_num(self.distance);
+    }
+    /// Positive `delta` (e.g. scrolling up) moves towards the target. Each unit scales
+    /// the distance by a constant factor.
+    pub fn zoom(&mut self, delta: f32) {
+        self.distance = f32::max(self.distance * f32::exp(-delta), self.min_distance);
+    }
+    pub fn camera_matrix(&self) -> Mat4<f32> {
+        matrices::look_at_3d(&self.position(), &self.target, &Vec3f32::new(0.0, 1.0, 0.0))
+    }
+    pub fn view(&self) -> Mat4<f32> {
+        self.camera_matrix().inverse()
+    }
+}
+
+/// A light shining along a single direction from infinitely far away, like the sun.
+pub struct DirectionalLight {
+    pub direction: Vec3f32,
+    pub color: Vec3f32,
+}
+impl DirectionalLight {
+    pub fn new(direction: Vec3f32, color: Vec3f32) -> Self {
+        Self { direction, color }
+    }
+}
+
+/// Depth-only render target storing the scene as seen from a directional light.
+///
+/// The light looks at a square area around a focus point. In the main pass, the depth
+/// texture is sampled at `world_pos * view_projection`, and the fragment is in shadow
+/// when its light-space depth minus `bias` is greater than the stored depth.
+pub struct ShadowMap {
+    framebuffer: Framebuffer,
+    pub depth: GPUTexture,
+    /// Half the width of the square area covered by the map.
+    pub extent: f32,
+    /// Length of the light volume, centred on the focus point.
+    pub distance: f32,
+    /// Offset subtracted from the fragment depth to avoid shadow acne.
+    pub bias: f32,
+    view_projection: Mat4<f32>,
+}
+impl ShadowMap {
+    pub fn new(win: &mut Window, size: u32, extent: f32, distance: f32) -> Self {
+        let mut depth = GPUTexture::new(win);
+        depth.size = (size, size);
+        depth.tex.bind();
+        depth.tex.set_texture(
+            std::ptr::null(),
+            size,
+            size,
+            GlInternalTextureFormat::DepthComponent24,
+            GlImagePixelFormat::DepthComponent,
+            GlImagePixelType::UnsignedInt,
+            Some(0),
+            4,
+        );
+        depth.tex.set_filter(GlTextureFilter::Nearest, GlTextureFilter::Nearest);
+        depth
+            .tex
+            .set_wrap(GlTextureWrap::ClampToEdge, GlTextureWrap::ClampToEdge);
+        depth.tex.unbind();
+
+        let framebuffer = Framebuffer::new(win);
+        framebuffer.bind();
+        framebuffer.attach_texture_2d(GlFramebufferAttachment::Depth, &depth.tex);
+        framebuffer.set_depth_only();
+        if !framebuffer.is_complete() {
+            cw_panic!("Shadow map framebuffer ({}x{}) is incomplete.", size, size);
         }
-        if f32::abs(self.rot.get_y()) >= std::f32::consts::PI * 2.0 {
-            self.rot.set_y(0.0);
+        framebuffer.unbind();
+        Self {
+            framebuffer,
+            depth,
+            extent,
+            distance,
+            bias: 0.005,
+            view_projection: Mat4::<f32>::from_matrix(&matrices::identity::<f32>(4)),
         }
-        if f32::abs(self.rot.get_z()) >= std::f32::consts::PI * 2.0 {
-            self.rot.set_z(0.0);
+    }
+    pub fn size(&self) -> u32 {
+        self.depth.size.0
+    }
+    pub fn view_projection(&self) -> Mat4<f32> {
+        self.view_projection.cm_copy()
+    }
+    /// Recomputes the light-space view projection for a light centred on `focus`.
+    pub fn update(&mut self, light: &DirectionalLight, focus: &Vec3f32) {
+        self.view_projection =
+            Self::light_view_projection(&light.direction, focus, self.extent, self.distance);
+    }
+    fn light_view_projection(
+        direction: &Vec3f32,
+        focus: &Vec3f32,
+        extent: f32,
+        distance: f32,
+    ) -> Mat4<f32> {
+        let dir = direction.normalized();
+        let eye = focus - dir.mul_num(distance * 0.5);
+        let up = if f32::abs(dir.get_y()) > 0.99 {
+            Vec3f32::new(1.0, 0.0, 0.0)
+        } else {
+            Vec3f32::new(0.0, 1.0, 0.0)
+        };
+        let view = matrices::look_at_3d(&eye, focus, &up).inverse();
+        let proj = matrices::orthographic(-extent, extent, -extent, extent, 0.0, distance);
+        view.mul_mat(&proj)
+    }
+    /// Renders scene depth from the light into the map. `draw` receives the light-space
+    /// view projection and should draw every shadow caster with a depth-only shader.
+    /// The previous viewport is restored afterwards.
+    pub fn render<F: FnMut(&Mat4<f32>)>(
+        &mut self,
+        win: &mut Window,
+        light: &DirectionalLight,
+        focus: &Vec3f32,
+        mut draw: F,
+    ) {
+        self.update(light, focus);
+        let ctx = win.get_gl_context();
+        let (x, y, w, h) = ctx.get_viewport();
+        self.framebuffer.bind();
+        ctx.viewport(0, 0, self.size(), self.size());
+        ctx.clear(&[GlClearMask::Depth]);
+        draw(&self.view_projection);
+        self.framebuffer.unbind();
+        ctx.viewport(x, y, w, h);
+    }
+}
+
+/// Maps a segment's linear progress in `[0, 1]` to eased progress, usually also in
+/// `[0, 1]` with `0 -> 0` and `1 -> 1`.
+pub type Easing = fn(f32) -> f32;
+
+pub struct Keyframe<T: Lerp> {
+    pub time: f32,
+    pub value: T,
+    /// Easing applied between this key and the next. None is linear.
+    pub easing: Option<Easing>,
+}
+
+/// Keyframes of any interpolatable value, kept sorted by time.
+pub struct AnimationTrack<T: Lerp> {
+    keys: Vec<Keyframe<T>>,
+}
+impl<T: Lerp + Clone> AnimationTrack<T> {
+    pub fn new() -> Self {
+        Self { keys: Vec::new() }
+    }
+    /// Adds a key with linear interpolation to the next key. Keys at the same time keep
+    /// the order they were added in.
+    pub fn add_key(&mut self, time: f32, value: T) {
+        self.insert(Keyframe {
+            time,
+            value,
+            easing: None,
+        });
+    }
+    /// Adds a key whose segment to the next key is shaped by `easing`.
+    pub fn add_key_eased(&mut self, time: f32, value: T, easing: Easing) {
+        self.insert(Keyframe {
+            time,
+            value,
+            easing: Some(easing),
+        });
+    }
+    fn insert(&mut self, key: Keyframe<T>) {
+        let i = self.keys.partition_point(|k| k.time <= key.time);
+        self.keys.insert(i, key);
+    }
+    pub fn keys(&self) -> &[Keyframe<T>] {
+        &self.keys
+    }
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+    /// Time between the first and last keys.
+    pub fn duration(&self) -> f32 {
+        match (self.keys.first(), self.keys.last()) {
+            (Some(first), Some(last)) => last.time - first.time,
+            _ => 0.0,
+        }
+    }
+    /// Value at time `t`, interpolated between the surrounding keys. Times before the
+    /// first key or after the last give that key's value. Panics if there are no keys.
+    pub fn sample(&self, t: f32) -> T {
+        let (first, last) = match (self.keys.first(), self.keys.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => {
+                cw_panic!("AnimationTrack.sample: track has no keys.");
+            }
+        };
+        if t <= first.time {
+            return first.value.clone();
+        }
+        if t >= last.time {
+            return last.value.clone();
         }
+        let i = self.keys.partition_point(|k| k.time <= t);
+        let (from, to) = (&self.keys[i - 1], &self.keys[i]);
+        let progress = (t - from.time) / (to.time - from.time);
+        let progress = from.easing.map_or(progress, |ease| ease(progress));
+        from.value.interpolate(&to.value, progress)
+    }
+}
+impl<T: Lerp + Clone> Default for AnimationTrack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Per-object model matrices in a uniform buffer, laid out to match
+/// `layout(std140) uniform MeshBlock { mat4 transform; mat4 rotation; } mesh;`.
+/// Both matrices are written with one buffer update instead of two uniform calls.
+pub struct ModelUniformBuffer {
+    pub buffer: UniformGPUBuffer,
+    /// Uniform buffer binding point the block is read from.
+    pub binding: u32,
+}
+impl ModelUniformBuffer {
+    pub const BLOCK_NAME: &'static str = "MeshBlock";
+    const N_FLOATS: usize = 32;
+
+    pub fn new(win: &Window, binding: u32) -> Self {
+        Self {
+            buffer: UniformGPUBuffer::new(win, Self::N_FLOATS * std::mem::size_of::<f32>()),
+            binding,
+        }
+    }
+    /// Points the shader's `MeshBlock` at this buffer's binding point. Returns false if
+    /// the shader has no such block.
+    pub fn attach(&self, shader: &GPUShader) -> bool {
+        shader.bind_uniform_block(Self::BLOCK_NAME, self.binding)
+    }
+    /// Writes both matrices and binds the buffer for the next draw.
+    pub fn upload(&self, transform: &Mat4<f32>, rotation: &Mat4<f32>) {
+        self.buffer.sub_data(0, transform.as_slice());
+        self.buffer.sub_data(16 * 4, rotation.as_slice());
+        self.buffer.bind_base(self.binding);
+    }
+    /// Reads back the `(transform, rotation)` matrices currently in the buffer.
+    pub fn get_matrices(&self) -> (Mat4<f32>, Mat4<f32>) {
+        let data = self.buffer.get_data();
+        (
+            Mat4::<f32>::from_flat(&data[..16], 4, 4),
+            Mat4::<f32>::from_flat(&data[16..Self::N_FLOATS], 4, 4),
+        )
     }
 }
 
@@ -205,28 +1344,593 @@ pub struct Object3D {
 impl Object3D {
 	pub fn new(win: &mut Window, mesh_data: &str, tex_path: &str) -> Self {
 		Self {
-			texture: win.load_texture_rgba(tex_path, None),
-			mesh: Mesh3D::<VertexVTN>::from_data(win, &Mesh3D::<VertexVTN>::tris_from_obj_data(mesh_data)),
+			texture: win.load_texture_rgba(tex_path, TextureLoadOptions::new()),
+			mesh: Mesh3D::<VertexVTN>::from_obj(win, mesh_data, &ObjImportOptions::default()),
 			rot: Quaternionf32::angle_axis(0.0, &Vec3f32::new(0.0, 1.0, 0.0)),
 			scale: Vec3f32::new(1.0, 1.0, 1.0),
 			pos: Vec3f32::new(0.0, 0.0, 0.0),
 		}
 	}
-	fn mesh_matrices(&self) -> (Mat4<f32>, Mat4<f32>) {
-		let rot = matrices::rotation_euler(&self.rot);
-		let scale = matrices::scale_3d(&self.scale);
-		let translate = matrices::translation_3d(&self.pos);
+	/// Row-vector rotation matrix for `rot`, matching `Quaternionf32::rotate_vec`.
+	fn rotation_matrix(rot: &Quaternionf32) -> Mat4<f32> {
+		let x = rot.rotate_vec(&Vec3f32::new(1.0, 0.0, 0.0));
+		let y = rot.rotate_vec(&Vec3f32::new(0.0, 1.0, 0.0));
+		let z = rot.rotate_vec(&Vec3f32::new(0.0, 0.0, 1.0));
+		Mat4::<f32>::from_flat(
+			&[
+				x[0], x[1], x[2], 0.0,
+				y[0], y[1], y[2], 0.0,
+				z[0], z[1], z[2], 0.0,
+				0.0, 0.0, 0.0, 1.0,
+			],
+			4,
+			4,
+		)
+	}
+	/// `(transform, rotation)` matrices for the given scale, then rotation, then translation.
+	fn transform_matrices(
+		rot: &Quaternionf32,
+		scale: &Vec3f32,
+		pos: &Vec3f32,
+	) -> (Mat4<f32>, Mat4<f32>) {
+		let rot = Self::rotation_matrix(rot);
+		let scale = matrices::scale_3d(scale);
+		let translate = matrices::translation_3d(pos);
 		let transform = scale.mul_mat(&rot).mul_mat(&translate);
 		(transform, rot)
 	}
+	fn mesh_matrices(&self) -> (Mat4<f32>, Mat4<f32>) {
+		Self::transform_matrices(&self.rot, &self.scale, &self.pos)
+	}
+	/// Transforms a point from this object's local space into world space.
+	pub fn local_to_world(&self, point: &Vec3f32) -> Vec3f32 {
+		Self::transform_point(&self.mesh_matrices().0, point)
+	}
+	/// Transforms a world space point into this object's local space. The scale must be
+	/// non-zero on every axis.
+	pub fn world_to_local(&self, point: &Vec3f32) -> Vec3f32 {
+		Self::inverse_transform_point(&self.mesh_matrices().0, point)
+	}
+	fn transform_point(transform: &Mat4<f32>, point: &Vec3f32) -> Vec3f32 {
+		let p = Vec4f32::new(point.get_x(), point.get_y(), point.get_z(), 1.0);
+		Vec3f32::new_vec(&transform.mul_row_vec(&p))
+	}
+	fn inverse_transform_point(transform: &Mat4<f32>, point: &Vec3f32) -> Vec3f32 {
+		let p = Vec4f32::new(point.get_x(), point.get_y(), point.get_z(), 1.0);
+		// Mat4::inverse assumes a rigid transform, so invert scaled transforms generically
+		let inv = transform.to_generic().inverse().to_mat4();
+		Vec3f32::new_vec(&inv.mul_row_vec(&p))
+	}
+	/// Screen rectangle covered by the mesh bounds, see `Viewport::project_aabb`. The
+	/// bounds are recomputed from the mesh vertices on every call.
+	pub fn screen_rect(&self, camera: &PerspectiveCamera3D, viewport: &Viewport) -> Option<Rect> {
+		let (transform, _) = self.mesh_matrices();
+		let mvp = transform
+			.mul_mat(&camera.view())
+			.mul_mat(&camera.projection(viewport.aspect()));
+		viewport.project_aabb(&self.mesh.bounding_box(), &mvp)
+	}
+	/// Moves the mesh so its centroid sits at the local origin, making it the point the
+	/// object rotates and scales about. `pos` is adjusted so the object stays in place.
+	/// Returns the centroid's previous local position.
+	pub fn recenter_origin(&mut self) -> Vec3f32 {
+		let centroid = self.mesh.centroid();
+		self.pos = self.local_to_world(&centroid);
+		self.mesh.translate(&centroid.mul_num(-1.0));
+		centroid
+	}
 	pub fn render(&self, shader: &GPUShader) {
-		shader.set_int("material.diffuse", 0);
 		let (mesh_transform, mesh_rot) = self.mesh_matrices();
 
 		shader.set_mat4f("mesh.transform", &mesh_transform);
 		shader.set_mat4f("mesh.rotation", &mesh_rot);
-		self.texture.lock().unwrap().tex.bind();
+		shader.bind_texture(0, &mut self.texture.lock().unwrap(), "material.diffuse");
+		self.mesh.buffer.vao.bind();
+		shader.draw(self.mesh.n_tris());
+	}
+	/// Like `render`, but writes the model matrices into `models` with a single buffer
+	/// update. The shader must declare `MeshBlock` and have been attached to `models`.
+	pub fn render_ubo(&self, shader: &GPUShader, models: &ModelUniformBuffer) {
+		let (mesh_transform, mesh_rot) = self.mesh_matrices();
+
+		models.upload(&mesh_transform, &mesh_rot);
+		shader.bind_texture(0, &mut self.texture.lock().unwrap(), "material.diffuse");
 		self.mesh.buffer.vao.bind();
 		shader.draw(self.mesh.n_tris());
 	}
-}
\ No newline at end of file
+}
+
+/// JavaScript handle to an `Object3D`. Until the texture URL finishes loading, the
+/// object renders with the solid placeholder texture.
+#[cfg(target_family = "wasm")]
+#[wasm_bindgen]
+pub struct WebObject3D {
+	obj: Object3D,
+}
+#[cfg(target_family = "wasm")]
+#[wasm_bindgen]
+impl WebObject3D {
+	#[wasm_bindgen(constructor)]
+	pub fn new(win: &mut Window, obj_data: &str, tex_url: &str) -> Self {
+		Self {
+			obj: Object3D::new(win, obj_data, tex_url),
+		}
+	}
+	#[wasm_bindgen(js_name = render)]
+	pub fn render(&self, shader: &GPUShader) {
+		self.obj.render(shader);
+	}
+	#[wasm_bindgen(js_name = nTris)]
+	pub fn n_tris(&self) -> i32 {
+		self.obj.mesh.n_tris()
+	}
+	#[wasm_bindgen(js_name = setPosition)]
+	pub fn set_position(&mut self, pos: &Vec3f32) {
+		self.obj.pos = pos.cm_copy();
+	}
+	#[wasm_bindgen(js_name = setScale)]
+	pub fn set_scale(&mut self, scale: &Vec3f32) {
+		self.obj.scale = scale.cm_copy();
+	}
+	#[wasm_bindgen(js_name = setRotation)]
+	pub fn set_rotation(&mut self, rot: &Quaternionf32) {
+		self.obj.rot = rot.cm_copy();
+	}
+}
+
+#[cfg(all(test, target_family = "wasm"))]
+mod web_tests {
+    use super::*;
+    use wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure};
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    /// Adds a canvas for `Window::create` to find by id.
+    fn test_window(id: &str) -> Window {
+        let document = web_sys::window().unwrap().document().unwrap();
+        let canvas = document.create_element("canvas").unwrap();
+        canvas.set_id(id);
+        document.body().unwrap().append_child(&canvas).unwrap();
+        Window::create(&WindowCreateArgs::new(id.into(), 64, 64, WindowSizeMode::Windowed))
+    }
+
+    #[wasm_bindgen_test]
+    fn web_object_counts_the_mesh_triangles() {
+        let mut win = test_window("web-object-3d");
+        let obj_data = include_str!("../resource/cubent.obj");
+        // The texture never loads, so the placeholder stays bound
+        let obj = WebObject3D::new(&mut win, obj_data, "missing.png");
+        let expected = Mesh3D::<VertexVTN>::tris_from_obj_data(obj_data).len();
+        assert_eq!(obj.n_tris() as usize, expected);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use charmath::linear::matrix::MatrixBase;
+
+    fn assert_vec3_near(a: &Vec3f32, b: &Vec3f32) {
+        assert!(
+            a.sub_vec(b).len() < 1e-4,
+            "{:?} != {:?}",
+            a.as_tuple(),
+            b.as_tuple()
+        );
+    }
+
+    #[test]
+    fn unproject_inverts_the_view_projection() {
+        let mut camera = PerspectiveCamera3D::new(1.2, 0.1, 100.0, Vec3f32::new(1.0, 2.0, -3.0));
+        camera.set_rotation(Vec3f32::new(0.2, -0.4, 0.0));
+        let aspect = 0.75;
+        let point = Vec3f32::new(2.0, 1.5, 4.0);
+        let view_proj = camera.view().mul_mat(&camera.projection(aspect));
+        let clip = view_proj.mul_row_vec(&Vec4f32::new(point[0], point[1], point[2], 1.0));
+        let ndc: Vec3f32 = clip.perspective_divide();
+        assert_vec3_near(&camera.unproject(ndc, aspect), &point);
+    }
+
+    #[test]
+    fn indexed_obj_data_fits_short_indices() {
+        use charwin::data::smallest_index_type;
+        use charwin::window::GlIndexType;
+        let data = include_str!("../resource/torusnt.obj");
+        let tris = Mesh3D::<VertexVTN>::tris_from_obj_data(data);
+        let (verts, indices) = Mesh3D::<VertexVTN>::indexed_obj_data(data, 1e-5);
+        assert_eq!(indices.len(), tris.len() * 3);
+        assert!(verts.len() < tris.len() * 3);
+        assert!(indices.iter().all(|i| (*i as usize) < verts.len()));
+        assert_eq!(smallest_index_type(verts.len()), GlIndexType::UnsignedShort);
+    }
+
+    const FACE_FORMATS_OBJ: &str = "v 0 0 0
+v 1 0 0
+v 0 1 0
+vt 0.5 0.25
+vn 0 0 1
+f 1 2 3
+f 1/1 2/1 3/1
+f 1//1 2//1 3//1
+f 1/1/1 2/1/1 3/1/1";
+
+    #[test]
+    fn obj_faces_accept_every_corner_format() {
+        let tris = Mesh3D::<VertexVTN>::tris_from_obj_data(FACE_FORMATS_OBJ);
+        assert_eq!(tris.len(), 4);
+        for tri in tris.iter() {
+            assert_eq!(tri.v[1].v.as_tuple(), (1.0, 0.0, 0.0));
+            assert_eq!(tri.v[2].v.as_tuple(), (0.0, 1.0, 0.0));
+        }
+        let has_uv = |tri: &Triangle<VertexVTN>| tri.v[0].t.as_tuple() == (0.5, 0.25);
+        let has_normal = |tri: &Triangle<VertexVTN>| tri.v[0].n.as_tuple() == (0.0, 0.0, 1.0);
+        assert_eq!(
+            tris.iter().map(has_uv).collect::<Vec<_>>(),
+            [false, true, false, true]
+        );
+        assert_eq!(
+            tris.iter().map(has_normal).collect::<Vec<_>>(),
+            [false, false, true, true]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "fewer than three vertices")]
+    fn obj_faces_need_three_corners() {
+        Mesh3D::<VertexVTN>::tris_from_obj_data("v 0 0 0\nv 1 0 0\nf 1 2");
+    }
+
+    #[test]
+    fn camera_rotation_round_trips() {
+        let mut camera = PerspectiveCamera3D::new(75.0, 0.1, 100.0, Vec3f32::new(0.0, 0.0, 0.0));
+        for rot in [
+            Vec3f32::new(0.0, 0.0, 0.0),
+            Vec3f32::new(0.3, -1.2, 0.1),
+            Vec3f32::new(-1.0, 1.3, -0.4),
+        ] {
+            camera.set_rotation(rot.cm_copy());
+            assert_vec3_near(&camera.rotation(), &rot);
+        }
+    }
+
+    #[test]
+    fn camera_rotation_at_gimbal_lock_keeps_orientation() {
+        let mut camera = PerspectiveCamera3D::new(75.0, 0.1, 100.0, Vec3f32::new(0.0, 0.0, 0.0));
+        camera.set_rotation(Vec3f32::new(0.4, std::f32::consts::FRAC_PI_2, 0.2));
+        let (look, up) = (camera.look_vector(), camera.up_vector());
+        camera.set_rotation(camera.rotation());
+        assert_vec3_near(&camera.look_vector(), &look);
+        assert_vec3_near(&camera.up_vector(), &up);
+    }
+
+    #[test]
+    fn camera_full_yaw_turn_keeps_a_finite_view() {
+        let mut camera = PerspectiveCamera3D::new(75.0, 0.1, 100.0, Vec3f32::new(0.0, 0.0, 0.0));
+        camera.set_rotation(Vec3f32::new(0.3, 0.0, 0.0));
+        let look = camera.look_vector();
+        for _ in 0..360 {
+            camera.rotate_local(0.0, 1f32.to_radians());
+            assert!(camera.view().as_slice().iter().all(|v| v.is_finite()));
+        }
+        assert_vec3_near(&camera.look_vector(), &look);
+    }
+
+    #[test]
+    fn shadow_map_keeps_the_focus_inside_the_light_volume() {
+        let dir = Vec3f32::new(0.3, -1.0, 0.4);
+        let focus = Vec3f32::new(3.0, 1.0, -2.0);
+        let view_proj = ShadowMap::light_view_projection(&dir, &focus, 5.0, 20.0);
+        let to_light_space = |p: &Vec3f32| -> Vec3f32 {
+            view_proj
+                .mul_row_vec(&Vec4f32::new(p[0], p[1], p[2], 1.0))
+                .perspective_divide()
+        };
+        assert_vec3_near(&to_light_space(&focus), &Vec3f32::new(0.0, 0.0, 0.5));
+        let nearby = [
+            focus + Vec3f32::new(3.0, 0.0, 0.0),
+            focus + dir.normalized().mul_num(9.0),
+            focus - dir.normalized().mul_num(9.0),
+        ];
+        for p in nearby {
+            let (x, y, z) = to_light_space(&p).as_tuple();
+            assert!(
+                x.abs() <= 1.0 && y.abs() <= 1.0,
+                "({}, {}) outside the map",
+                x,
+                y
+            );
+            assert!((0.0..=1.0).contains(&z), "depth {} outside [0, 1]", z);
+        }
+    }
+
+    #[test]
+    fn viewport_ignores_empty_sizes() {
+        let mut viewport = Viewport::new((800, 600));
+        assert!(viewport.set_size((1024, 512)));
+        assert_eq!(viewport.size(), (1024, 512));
+        assert_eq!(viewport.aspect(), 0.5);
+        assert!(!viewport.set_size((0, 300)));
+        assert!(!viewport.set_size((640, -1)));
+        assert_eq!(viewport.size(), (1024, 512));
+        assert_eq!(viewport.aspect(), 0.5);
+    }
+
+    #[test]
+    fn projection_cache_follows_aspect_and_fov_changes() {
+        let mut camera = PerspectiveCamera3D::new(75.0, 0.1, 100.0, Vec3f32::new(0.0, 0.0, 0.0));
+        let expected = |camera: &PerspectiveCamera3D, aspect: f32| {
+            matrices::perspective(camera.fov, aspect, camera.near, camera.far).flatten()
+        };
+        assert_eq!(camera.projection(1.0).flatten(), expected(&camera, 1.0));
+        assert_eq!(camera.projection(1.0).flatten(), expected(&camera, 1.0));
+        assert_eq!(camera.projection(0.5).flatten(), expected(&camera, 0.5));
+        camera.fov = 60.0;
+        assert_eq!(camera.projection(0.5).flatten(), expected(&camera, 0.5));
+    }
+
+    #[test]
+    fn obj_import_options_flip_winding_and_scale() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3";
+        let normal = |tri: &Triangle<VertexVTN>| {
+            let a = tri.v[0].v.cm_copy();
+            tri.v[1].v.sub_vec(&a).cross(&tri.v[2].v.sub_vec(&a))
+        };
+        let plain = Mesh3D::<VertexVTN>::parse_obj(obj, &ObjImportOptions::default());
+        let options = ObjImportOptions {
+            flip_winding: true,
+            scale: 2.0,
+            ..ObjImportOptions::default()
+        };
+        let imported = Mesh3D::<VertexVTN>::parse_obj(obj, &options);
+        assert_eq!(imported.len(), 1);
+        assert_vec3_near(&normal(&plain[0]), &Vec3f32::new(0.0, 0.0, 1.0));
+        assert_eq!(normal(&imported[0]).get_z().signum(), -1.0);
+        assert_vec3_near(&imported[0].v[1].v, &Vec3f32::new(0.0, 2.0, 0.0));
+        assert_vec3_near(&imported[0].v[2].v, &Vec3f32::new(2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn obj_import_options_recenter_and_flip_uv_y() {
+        let obj = "v 0 0 0\nv 3 0 0\nv 0 3 0\nvt 0.5 0.25\nf 1/1 2/1 3/1";
+        let options = ObjImportOptions {
+            recenter: true,
+            flip_uv_y: true,
+            ..ObjImportOptions::default()
+        };
+        let tris = Mesh3D::<VertexVTN>::parse_obj(obj, &options);
+        assert_vec3_near(&tris[0].v[0].v, &Vec3f32::new(-1.0, -1.0, 0.0));
+        assert_eq!(tris[0].v[0].t.as_tuple(), (0.5, 0.75));
+    }
+
+    #[test]
+    fn sampled_points_lie_on_the_triangle() {
+        let obj = "v 1 0 0\nv 0 2 0\nv 0 0 3\nf 1 2 3";
+        let tris = Mesh3D::<VertexVTN>::parse_obj(obj, &ObjImportOptions::default());
+        let points = Mesh3D::sample_triangles(&tris, 500, &mut Pcg32::new(7));
+        assert_eq!(points.len(), 500);
+        for point in points.iter() {
+            // Plane x + y / 2 + z / 3 = 1, inside the positive octant
+            let (x, y, z) = point.as_tuple();
+            assert!((x + y / 2.0 + z / 3.0 - 1.0).abs() < 1e-5);
+            assert!(x >= -1e-6 && y >= -1e-6 && z >= -1e-6);
+        }
+        let degenerate = Mesh3D::<VertexVTN>::parse_obj(
+            "v 0 0 0\nv 1 0 0\nv 2 0 0\nf 1 2 3",
+            &ObjImportOptions::default(),
+        );
+        assert!(Mesh3D::sample_triangles(&degenerate, 10, &mut Pcg32::new(7)).is_empty());
+    }
+
+    #[test]
+    fn camera_basis_is_right_handed_and_orthonormal() {
+        let mut camera = PerspectiveCamera3D::new(75.0, 0.1, 100.0, Vec3f32::new(0.0, 0.0, 0.0));
+        camera.set_rotation(Vec3f32::new(0.5, -0.7, 0.3));
+        let (right, up, look) = (
+            camera.right_vector(),
+            camera.up_vector(),
+            camera.look_vector(),
+        );
+        for axis in [&right, &up, &look] {
+            assert!((axis.len() - 1.0).abs() < 1e-5);
+        }
+        assert!(right.dot(&up).abs() < 1e-5);
+        assert!(up.dot(&look).abs() < 1e-5);
+        assert!(look.dot(&right).abs() < 1e-5);
+        assert_vec3_near(&right.cross(&up), &look);
+    }
+
+    #[test]
+    fn world_and_local_transforms_are_inverses() {
+        let axis = Vec3f32::new(0.0, 1.0, 0.0);
+        let rot = Quaternionf32::angle_axis(0.9, &axis);
+        let (scale, pos) = (Vec3f32::new(2.0, 2.0, 0.5), Vec3f32::new(1.0, 2.0, 3.0));
+        let (transform, _) = Object3D::transform_matrices(&rot, &scale, &pos);
+        let on_axis = Object3D::transform_point(&transform, &Vec3f32::new(0.0, 1.0, 0.0));
+        assert_vec3_near(&on_axis, &Vec3f32::new(1.0, 4.0, 3.0));
+        for p in [Vec3f32::new(0.5, -1.0, 4.0), Vec3f32::new(-3.0, 0.2, 0.0)] {
+            let local = Object3D::inverse_transform_point(&transform, &p);
+            assert_vec3_near(&Object3D::transform_point(&transform, &local), &p);
+            assert_vec3_near(
+                &local,
+                &Quaternionf32::angle_axis(-0.9, &axis)
+                    .rotate_vec(&p.sub_vec(&pos))
+                    .div_vec(&scale),
+            );
+        }
+    }
+
+    #[test]
+    fn orbit_moves_around_the_target() {
+        let target = Vec3f32::new(1.0, 2.0, 3.0);
+        let mut camera = OrbitCamera::new(target.cm_copy(), 5.0);
+        assert_vec3_near(&camera.position(), &Vec3f32::new(1.0, 2.0, -2.0));
+        camera.orbit(std::f32::consts::FRAC_PI_2, 0.0);
+        assert_vec3_near(&camera.position(), &Vec3f32::new(-4.0, 2.0, 3.0));
+        camera.orbit(0.0, 10.0);
+        assert_eq!(camera.pitch, OrbitCamera::MAX_PITCH);
+        assert!((camera.position().sub_vec(&target).len() - 5.0).abs() < 1e-4);
+        camera.zoom(1000.0);
+        assert_eq!(camera.distance, camera.min_distance);
+    }
+
+    #[test]
+    fn project_aabb_centers_a_box_in_front_of_the_camera() {
+        let camera = PerspectiveCamera3D::new(75.0, 0.1, 100.0, Vec3f32::new(0.0, 0.0, 0.0));
+        let viewport = Viewport::new((800, 600));
+        let view_proj = camera.view().mul_mat(&camera.projection(viewport.aspect()));
+        let cube = |z: f32| {
+            Aabb::new(
+                Vec3f32::new(-0.5, -0.5, z - 0.5),
+                Vec3f32::new(0.5, 0.5, z + 0.5),
+            )
+        };
+        let rect = viewport.project_aabb(&cube(5.0), &view_proj).unwrap();
+        let (cx, cy) = rect.center().as_tuple();
+        assert!((cx - 400.0).abs() < 1e-3 && (cy - 300.0).abs() < 1e-3);
+        assert!(rect.width() > 0.0 && rect.width() < 800.0);
+        assert!(rect.height() > 0.0 && rect.height() < 600.0);
+        let nearer = viewport.project_aabb(&cube(2.0), &view_proj).unwrap();
+        assert!(nearer.width() > rect.width());
+        assert!(viewport.project_aabb(&cube(-5.0), &view_proj).is_none());
+    }
+
+    #[test]
+    fn camera_shake_decays_over_its_duration() {
+        let mut camera = PerspectiveCamera3D::new(75.0, 0.1, 100.0, Vec3f32::new(0.0, 0.0, 0.0));
+        camera.add_shake(0.5, 1.0);
+        camera.update_shake(0.25);
+        assert!((camera.shake.magnitude() - 0.375).abs() < 1e-6);
+        assert!(camera.shake.offset().as_tuple().0.abs() <= 0.375);
+        for _ in 0..4 {
+            camera.update_shake(0.25);
+        }
+        assert_eq!(camera.shake.magnitude(), 0.0);
+        assert_eq!(camera.shake.offset().as_tuple(), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn smooth_follow_approaches_the_target() {
+        let mut camera = PerspectiveCamera3D::new(75.0, 0.1, 100.0, Vec3f32::new(0.0, 0.0, 0.0));
+        let (target_pos, target_look) = (Vec3f32::new(4.0, 0.0, -2.0), Vec3f32::new(1.0, 0.0, 0.0));
+        let mut last = f32::INFINITY;
+        for _ in 0..60 {
+            camera.smooth_follow(&target_pos, &target_look, 5.0, 1.0 / 30.0);
+            let remaining = camera.pos.sub_vec(&target_pos).len();
+            assert!(remaining < last);
+            last = remaining;
+        }
+        assert!(last < 1e-3);
+        assert_vec3_near(&camera.look_vector(), &target_look);
+    }
+
+    #[test]
+    fn animation_track_samples_between_sorted_keys() {
+        let mut track = AnimationTrack::<f32>::new();
+        track.add_key(2.0, 10.0);
+        track.add_key(0.0, 0.0);
+        track.add_key_eased(1.0, 4.0, |t| t * t);
+        assert_eq!(track.duration(), 2.0);
+        assert_eq!(track.sample(-1.0), 0.0);
+        assert_eq!(track.sample(0.5), 2.0);
+        assert_eq!(track.sample(1.5), 5.5);
+        assert_eq!(track.sample(3.0), 10.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "track has no keys")]
+    fn animation_track_without_keys_panics() {
+        AnimationTrack::<f32>::new().sample(0.0);
+    }
+
+    #[test]
+    fn particles_move_by_velocity_and_expire() {
+        let particle = |vel: Vec3f32, life: f32| Particle {
+            pos: Vec3f32::new(1.0, 2.0, 3.0),
+            vel,
+            life,
+            color: Color::new(1.0, 1.0, 1.0, 1.0),
+            size: 1.0,
+        };
+        let mut particles = vec![
+            particle(Vec3f32::new(2.0, 0.0, -4.0), 1.0),
+            particle(Vec3f32::new(0.0, 0.0, 0.0), 0.25),
+        ];
+        let gravity = Vec3f32::new(0.0, -10.0, 0.0);
+        ParticleSystem::integrate(&mut particles, &gravity, 0.5);
+        assert_eq!(particles.len(), 1);
+        assert_vec3_near(&particles[0].pos, &Vec3f32::new(2.0, 2.0, 1.0));
+        assert_vec3_near(&particles[0].vel, &Vec3f32::new(2.0, -5.0, -4.0));
+        assert_eq!(particles[0].life, 0.5);
+    }
+
+    #[test]
+    fn camera_uniforms_use_the_prefixed_member_names() {
+        assert_eq!(
+            CameraUniforms::uniform_names("camera"),
+            ["camera.projection", "camera.view", "camera.position"]
+        );
+        let mut camera = PerspectiveCamera3D::new(75.0, 0.1, 100.0, Vec3f32::new(1.0, 2.0, 3.0));
+        assert_eq!(
+            CameraUniforms::new(&camera).position().as_tuple(),
+            (1.0, 2.0, 3.0)
+        );
+        camera.add_shake(0.5, 1.0);
+        camera.update_shake(0.1);
+        let shaken = CameraUniforms::new(&camera).position();
+        assert_vec3_near(&shaken, &(camera.pos + camera.shake.offset()));
+    }
+
+    #[test]
+    fn centroid_is_area_weighted() {
+        let options = ObjImportOptions::default();
+        // A unit square at x 0..1 and a tiny triangle far away barely move the centroid
+        let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3\nf 1 3 4
+v 9 9 0\nv 9.01 9 0\nv 9 9.01 0\nf 5 6 7";
+        let tris = Mesh3D::<VertexVTN>::parse_obj(obj, &options);
+        let centroid = Mesh3D::triangles_centroid(&tris);
+        assert!(centroid.sub_vec(&Vec3f32::new(0.5, 0.5, 0.0)).len() < 1e-3);
+        assert_vec3_near(
+            &Mesh3D::triangles_centroid(&tris[..2]),
+            &Vec3f32::new(0.5, 0.5, 0.0),
+        );
+        let flat = Mesh3D::<VertexVTN>::parse_obj("v 0 0 0\nv 1 0 0\nv 2 0 0\nf 1 2 3", &options);
+        assert_vec3_near(
+            &Mesh3D::triangles_centroid(&flat),
+            &Vec3f32::new(1.0, 0.0, 0.0),
+        );
+        assert_vec3_near(
+            &Mesh3D::<VertexVTN>::triangles_centroid(&[]),
+            &Vec3f32::new(0.0, 0.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn parse_obj_counts_triangles() {
+        let obj = "# quad\no quad\nv 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvn 0 0 1
+f 1/1/1 2/1/1 3/1/1\nf 1/1/1 3/1/1 4/1/1\n";
+        let tris = Mesh3D::<VertexVTN>::parse_obj(obj, &ObjImportOptions::default());
+        assert_eq!(tris.len(), 2);
+        assert!(Mesh3D::<VertexVTN>::parse_obj("", &ObjImportOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn obj_polygons_are_fanned_into_triangles() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv -1 0.5 0\nf 1 2 3 4\nf 1 2 3 4 5";
+        let tris = Mesh3D::<VertexVTN>::tris_from_obj_data(obj);
+        assert_eq!(tris.len(), 5);
+        let corners = |tri: &Triangle<VertexVTN>| {
+            [
+                tri.v[0].v.as_tuple(),
+                tri.v[1].v.as_tuple(),
+                tri.v[2].v.as_tuple(),
+            ]
+        };
+        assert_eq!(
+            corners(&tris[1]),
+            [(0.0, 0.0, 0.0), (1.0, 1.0, 0.0), (0.0, 1.0, 0.0)]
+        );
+        let quad_area: f32 = tris[..2].iter().map(|tri| tri.area()).sum();
+        assert!((quad_area - 1.0).abs() < 1e-6);
+        assert_eq!(corners(&tris[4])[2], (-1.0, 0.5, 0.0));
+    }
+}